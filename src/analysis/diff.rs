@@ -0,0 +1,389 @@
+//! Semantic diff between two `Bubble` or `Floaout`
+//!
+//! Details, Bubbles in Floaout, samples and Bubble fields are compared instead of bytes, so a diff tells what changed.
+//! Blocks and Bubbles which are in only one file aren't compared, and they are reported as a difference of `blocks` or `bubbles`.
+//! Bubble fields are compared only if Bubble field sizes are same.
+//! `overall` isn't compared, because it follows from Bubble fields of blocks.
+
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::json::Json;
+use std::fmt;
+use std::io::{BufReader, Read, Result, Seek};
+
+/// Tolerances of diff
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffOptions {
+    /// Samples whose difference is under this level in dBFS are same.
+    pub sample_tolerance: f64,
+    /// Bubble field values whose difference is this or less are same.
+    pub field_tolerance: u8
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            sample_tolerance: -120.0,
+            field_tolerance: 0
+        }
+    }
+}
+
+/// A detail which is different
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DetailDiff {
+    /// Name of detail
+    pub name: String,
+    /// Value in the left file
+    pub left: String,
+    /// Value in the right file
+    pub right: String
+}
+
+impl DetailDiff {
+    fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("name".into(), self.name.as_str().into()),
+            ("left".into(), self.left.as_str().into()),
+            ("right".into(), self.right.as_str().into())
+        ])
+    }
+}
+
+/// Differences of a Bubble
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BubbleDiff {
+    /// Index of Bubble
+    pub index: usize,
+    /// Name of Bubble in the left file
+    pub name: String,
+    /// Bubble ID, name or color which is different
+    pub details: Vec<DetailDiff>,
+    /// The first block whose sample or Bubble field is different
+    pub first_block: Option<u64>,
+    /// The largest difference of samples in dBFS, which is negative infinity if all samples are same
+    /// and infinity if a sample is NaN or infinite in only one file
+    pub max_difference: f64,
+    /// Number of different samples
+    pub samples: u64,
+    /// Number of different cells of all blocks
+    pub cells: u64,
+    /// Each different cell of Bubble field, and number of blocks where it's different
+    pub cell_blocks: Vec<((usize, usize, usize), u64)>
+}
+
+impl BubbleDiff {
+    /// This method returns whether Bubbles are same.
+    pub fn is_same(&self) -> bool {
+        self.details.is_empty() && self.first_block.is_none()
+    }
+
+    fn to_json(&self) -> Json {
+        let cell_blocks = self.cell_blocks.iter().map(|&((l, w, h), blocks)| Json::Object(vec![
+            ("cell".into(), Json::Array(vec![(l as f64).into(), (w as f64).into(), (h as f64).into()])),
+            ("blocks".into(), (blocks as f64).into())
+        ])).collect();
+        Json::Object(vec![
+            ("index".into(), (self.index as f64).into()),
+            ("name".into(), self.name.as_str().into()),
+            ("details".into(), Json::Array(self.details.iter().map(DetailDiff::to_json).collect())),
+            ("first_block".into(), self.first_block.map_or(Json::Null, |block| (block as f64).into())),
+            // JSON has no infinity.
+            ("max_difference".into(), if self.max_difference.is_finite() { self.max_difference.into() } else { Json::Null }),
+            ("non_finite".into(), (self.max_difference == f64::INFINITY).into()),
+            ("samples".into(), (self.samples as f64).into()),
+            ("cells".into(), (self.cells as f64).into()),
+            ("cell_blocks".into(), Json::Array(cell_blocks))
+        ])
+    }
+}
+
+/// Differences of two `Bubble` or `Floaout`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff {
+    /// Details which are different
+    pub details: Vec<DetailDiff>,
+    /// Number of compared blocks
+    pub blocks: u64,
+    /// Bubbles which are different
+    pub bubbles: Vec<BubbleDiff>
+}
+
+impl Diff {
+    /// This method returns whether files are same.
+    pub fn is_same(&self) -> bool {
+        self.details.is_empty() && self.bubbles.is_empty()
+    }
+
+    /// This method returns the first block which is different in any Bubble.
+    pub fn first_block(&self) -> Option<u64> {
+        self.bubbles.iter().filter_map(|bubble| bubble.first_block).min()
+    }
+
+    /// This method writes diff in JSON.
+    /// The largest difference of samples is `null` if all samples are same or if a sample is NaN or infinite in only one file,
+    /// and `non_finite` is `true` in the latter case.
+    pub fn to_json(&self) -> String {
+        Json::Object(vec![
+            ("same".into(), self.is_same().into()),
+            ("details".into(), Json::Array(self.details.iter().map(DetailDiff::to_json).collect())),
+            ("blocks".into(), (self.blocks as f64).into()),
+            ("first_block".into(), self.first_block().map_or(Json::Null, |block| (block as f64).into())),
+            ("bubbles".into(), Json::Array(self.bubbles.iter().map(BubbleDiff::to_json).collect()))
+        ]).to_string()
+    }
+}
+
+/// This writes diff for people.
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_same() {
+            return writeln!(f, "Same in {} blocks", self.blocks);
+        }
+        for detail in &self.details {
+            writeln!(f, "{}: {} -> {}", detail.name, detail.left, detail.right)?;
+        }
+        for bubble in &self.bubbles {
+            writeln!(f, "Bubble {} {:?}", bubble.index, bubble.name)?;
+            for detail in &bubble.details {
+                writeln!(f, "  {}: {} -> {}", detail.name, detail.left, detail.right)?;
+            }
+            if let Some(block) = bubble.first_block {
+                writeln!(f, "  first different block: {}", block)?;
+            }
+            if bubble.samples != 0 {
+                writeln!(f, "  samples: {} different, {:.1} dB at most", bubble.samples, bubble.max_difference)?;
+            }
+            if bubble.cells != 0 {
+                writeln!(f, "  cells: {} different in {} cells", bubble.cells, bubble.cell_blocks.len())?;
+                for ((l, w, h), blocks) in &bubble.cell_blocks {
+                    writeln!(f, "    ({}, {}, {}): {} blocks", l, w, h, blocks)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// This function pushes a detail if values are different.
+fn push_detail<T: PartialEq + fmt::Debug>(details: &mut Vec<DetailDiff>, name: &str, left: T, right: T) {
+    if left != right {
+        details.push(DetailDiff {
+            name: name.into(),
+            left: format!("{:?}", left),
+            right: format!("{:?}", right)
+        });
+    }
+}
+
+/// This structure compares blocks of a Bubble which are added one by one.
+struct BubbleComparer {
+    diff: BubbleDiff,
+    tolerance: f64,
+    field_tolerance: u8,
+    max: f64,
+    // Number of blocks where each cell is different, in order of length, width and height
+    cell_blocks: Option<Vec<u64>>,
+    // Width and height of Bubble field
+    shape: (usize, usize)
+}
+
+impl BubbleComparer {
+    fn new(diff: BubbleDiff, options: &DiffOptions, fields: bool) -> Self {
+        Self {
+            diff,
+            tolerance: 10f64.powf(options.sample_tolerance / 20.0),
+            field_tolerance: options.field_tolerance,
+            max: 0.0,
+            cell_blocks: if fields { Some(Vec::new()) } else { None },
+            shape: (0, 0)
+        }
+    }
+
+    fn add(&mut self, block: u64, left: &BubbleBlock, right: &BubbleBlock) {
+        let (a, b): (f64, f64) = (left.wav_block.into(), right.wav_block.into());
+        let difference = if a == b || (a.is_nan() && b.is_nan()) {
+            0.0
+        } else {
+            let difference = (a - b).abs();
+            if difference.is_nan() { f64::INFINITY } else { difference }
+        };
+        self.max = self.max.max(difference);
+        let mut different = difference >= self.tolerance;
+        if different {
+            self.diff.samples += 1;
+        }
+        if let Some(cell_blocks) = &mut self.cell_blocks {
+            let values = left.bub_field.values();
+            if cell_blocks.is_empty() {
+                let width = values.first().map_or(0, Vec::len);
+                let height = values.first().and_then(|v| v.first()).map_or(0, Vec::len);
+                self.shape = (width, height);
+                cell_blocks.resize(values.len() * width * height, 0);
+            }
+            let cells = values.iter().flatten().flatten().zip(right.bub_field.values().iter().flatten().flatten());
+            for (count, (a, b)) in cell_blocks.iter_mut().zip(cells) {
+                if a.abs_diff(*b) > self.field_tolerance {
+                    *count += 1;
+                    self.diff.cells += 1;
+                    different = true;
+                }
+            }
+        }
+        if different && self.diff.first_block.is_none() {
+            self.diff.first_block = Some(block);
+        }
+    }
+
+    fn finish(mut self) -> BubbleDiff {
+        self.diff.max_difference = 20.0 * self.max.log10();
+        if let Some(cell_blocks) = self.cell_blocks {
+            let (width, height) = self.shape;
+            self.diff.cell_blocks = cell_blocks.into_iter().enumerate().filter(|&(_, blocks)| blocks != 0).map(|(i, blocks)| {
+                ((i / (width * height), i / height % width, i % height), blocks)
+            }).collect();
+        }
+
+        self.diff
+    }
+}
+
+/// This function compares blocks and returns Bubbles which are different.
+fn compare_blocks<F>(blocks: u64, mut comparers: Vec<BubbleComparer>, mut read: F) -> Result<Vec<BubbleDiff>>
+where
+    F: FnMut() -> Result<(Vec<BubbleBlock>, Vec<BubbleBlock>)>
+{
+    for block in 0..blocks {
+        let (left, right) = read()?;
+        for ((comparer, left), right) in comparers.iter_mut().zip(&left).zip(&right) {
+            comparer.add(block, left, right);
+        }
+    }
+
+    Ok(comparers.into_iter().map(BubbleComparer::finish).filter(|diff| !diff.is_same()).collect())
+}
+
+/// This function reads two Bubbles and returns differences of them.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::analysis::diff::{diff_bub, DiffOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut left = io::BufReader::new(File::open("foo.bub")?);
+///     let mut right = io::BufReader::new(File::open("bar.bub")?);
+///
+///     let diff = diff_bub(&mut left, &mut right, &DiffOptions::default())?;
+///     print!("{}", diff);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn diff_bub<R, S>(left: &mut BufReader<R>, right: &mut BufReader<S>, options: &DiffOptions) -> Result<Diff>
+where
+    R: Read + Seek,
+    S: Read + Seek
+{
+    let left_bub: Bubble = left.read_details()?;
+    let right_bub: Bubble = right.read_details()?;
+    let mut details = Vec::new();
+    push_detail(&mut details, "version", left_bub.version, right_bub.version);
+    push_detail::<(u8, u8, u8)>(&mut details, "bub_field_size", left_bub.bub_field_size.into(), right_bub.bub_field_size.into());
+    push_detail(&mut details, "blocks", left_bub.blocks, right_bub.blocks);
+    push_detail(&mut details, "sampling_rate", left_bub.sampling_rate, right_bub.sampling_rate);
+    push_detail(&mut details, "bits_per_sample", left_bub.bits_per_sample, right_bub.bits_per_sample);
+    let mut bub_details = Vec::new();
+    push_detail(&mut bub_details, "bubble_id", left_bub.bubble_id, right_bub.bubble_id);
+    push_detail(&mut bub_details, "name", &left_bub.name, &right_bub.name);
+    push_detail::<(u8, u8, u8)>(&mut bub_details, "color", left_bub.color.into(), right_bub.color.into());
+
+    let blocks = left_bub.blocks.min(right_bub.blocks);
+    let fields = left_bub.bub_field_size == right_bub.bub_field_size;
+    let comparer = BubbleComparer::new(BubbleDiff { name: left_bub.name.clone(), details: bub_details, ..Default::default() }, options, fields);
+    let bubbles = compare_blocks(blocks, vec![comparer], || {
+        let left_block: BubbleBlock = left.read_block(&left_bub)?;
+        let right_block: BubbleBlock = right.read_block(&right_bub)?;
+        Ok((vec![left_block], vec![right_block]))
+    })?;
+
+    Ok(
+        Diff {
+            details,
+            blocks,
+            bubbles
+        }
+    )
+}
+
+/// This function reads two Floaouts and returns differences of them.
+/// Bubbles are compared by their index.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::{File, write};
+/// use floaout::analysis::diff::{diff_oao, DiffOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut left = io::BufReader::new(File::open("expected.oao")?);
+///     let mut right = io::BufReader::new(File::open("rendered.oao")?);
+///     let options = DiffOptions {
+///         sample_tolerance: -90.0,
+///         field_tolerance: 1
+///     };
+///
+///     let diff = diff_oao(&mut left, &mut right, &options)?;
+///     if !diff.is_same() {
+///         write("diff.json", diff.to_json())?;
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn diff_oao<R, S>(left: &mut BufReader<R>, right: &mut BufReader<S>, options: &DiffOptions) -> Result<Diff>
+where
+    R: Read + Seek,
+    S: Read + Seek
+{
+    let left_oao: Floaout = left.read_details()?;
+    let left_bubs: BubblesInFloaout = left.read_bubs_details(&left_oao)?;
+    let right_oao: Floaout = right.read_details()?;
+    let right_bubs: BubblesInFloaout = right.read_bubs_details(&right_oao)?;
+    let mut details = Vec::new();
+    push_detail(&mut details, "version", left_oao.version, right_oao.version);
+    push_detail(&mut details, "song_id", left_oao.song_id, right_oao.song_id);
+    push_detail::<(u8, u8, u8)>(&mut details, "bub_field_size", left_oao.bub_field_size.into(), right_oao.bub_field_size.into());
+    push_detail(&mut details, "bubbles", left_oao.bubbles, right_oao.bubbles);
+    push_detail(&mut details, "blocks", left_oao.blocks, right_oao.blocks);
+    push_detail(&mut details, "sampling_rate", left_oao.sampling_rate, right_oao.sampling_rate);
+    push_detail(&mut details, "bits_per_sample", left_oao.bits_per_sample, right_oao.bits_per_sample);
+    push_detail(&mut details, "title", &left_oao.title, &right_oao.title);
+    push_detail(&mut details, "artist", &left_oao.artist, &right_oao.artist);
+
+    let blocks = left_oao.blocks.min(right_oao.blocks);
+    let fields = left_oao.bub_field_size == right_oao.bub_field_size;
+    let comparers = left_bubs.0.iter().zip(&right_bubs.0).enumerate().map(|(index, (left_bub, right_bub))| {
+        let mut bub_details = Vec::new();
+        push_detail(&mut bub_details, "bubble_id", left_bub.bubble_id, right_bub.bubble_id);
+        push_detail(&mut bub_details, "name", &left_bub.name, &right_bub.name);
+        push_detail::<(u8, u8, u8)>(&mut bub_details, "color", left_bub.color.into(), right_bub.color.into());
+        BubbleComparer::new(BubbleDiff { index, name: left_bub.name.clone(), details: bub_details, ..Default::default() }, options, fields)
+    }).collect();
+    let bubbles = compare_blocks(blocks, comparers, || {
+        let left_block: FloaoutBlock = left.read_block(&left_oao)?;
+        let right_block: FloaoutBlock = right.read_block(&right_oao)?;
+        Ok((left_block.0, right_block.0))
+    })?;
+
+    Ok(
+        Diff {
+            details,
+            blocks,
+            bubbles
+        }
+    )
+}
\ No newline at end of file
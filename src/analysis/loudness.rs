@@ -0,0 +1,459 @@
+//! Loudness measurement by ITU-R BS.1770 and EBU R128
+//!
+//! Loudness is in LUFS, loudness range is in LU, and true peak is in dBTP.
+//! Loudness which can't be measured, such as silence, is `f64::NEG_INFINITY`.
+
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::format::wav::{Wav, WavBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::render::Renderer;
+use crate::render::layout::{SpeakerLayout, SpeakerPosition};
+use std::f64::consts::PI;
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek};
+
+/// Blocks of momentary loudness in 100 ms
+const MOMENTARY: usize = 4;
+/// Blocks of short-term loudness in 100 ms
+const SHORT_TERM: usize = 30;
+/// Absolute gate in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Taps of each phase of true peak interpolation
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Biquad filter in transposed direct form II
+#[derive(Clone, Debug)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2]
+}
+
+impl Biquad {
+    /// This function returns the first stage of K-weighting, which is high shelf.
+    fn shelf(sampling_rate: u32) -> Self {
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / sampling_rate as f64).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Self {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2]
+        }
+    }
+
+    /// This function returns the second stage of K-weighting, which is high pass.
+    fn high_pass(sampling_rate: u32) -> Self {
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / sampling_rate as f64).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Self {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2]
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+
+        y
+    }
+}
+
+/// Oversampling peak meter of a channel
+#[derive(Clone, Debug)]
+pub(crate) struct TruePeak {
+    factor: usize,
+    // Coefficients of phase `p` are `coefficients[p * TRUE_PEAK_TAPS..]`.
+    coefficients: Vec<f64>,
+    history: [f64; TRUE_PEAK_TAPS],
+    pos: usize,
+    peak: f64
+}
+
+impl TruePeak {
+    /// This function returns meter which oversamples to at least 192 kHz, but no more than 4 times.
+    pub(crate) fn new(sampling_rate: u32) -> Self {
+        let factor = (192000 / sampling_rate.max(1) as usize).clamp(1, 4);
+        let len = factor * TRUE_PEAK_TAPS;
+        let center = (len - 1) as f64 / 2.0;
+        let mut coefficients = vec![0.0; len];
+        for p in 0..factor {
+            let phase = &mut coefficients[p * TRUE_PEAK_TAPS..(p + 1) * TRUE_PEAK_TAPS];
+            for (k, c) in phase.iter_mut().enumerate() {
+                // Windowed sinc
+                let n = (k * factor + p) as f64;
+                let x = (n - center) / factor as f64;
+                let sinc = if x == 0.0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let window = 0.5 - 0.5 * (2.0 * PI * (n + 0.5) / len as f64).cos();
+                *c = sinc * window;
+            }
+            let sum: f64 = phase.iter().sum();
+            phase.iter_mut().for_each(|c| *c /= sum);
+        }
+
+        Self {
+            factor,
+            coefficients,
+            history: [0.0; TRUE_PEAK_TAPS],
+            pos: 0,
+            peak: 0.0
+        }
+    }
+
+    /// Interpolated samples are between the sample `DELAY` samples ago and the next one.
+    pub(crate) const DELAY: usize = TRUE_PEAK_TAPS / 2;
+
+    /// This method takes a sample, and returns the largest absolute value of the sample `DELAY` samples ago
+    /// and the interpolated samples after it.
+    pub(crate) fn interpolate(&mut self, x: f64) -> f64 {
+        self.pos = (self.pos + 1) % TRUE_PEAK_TAPS;
+        self.history[self.pos] = x;
+        let mut peak = self.history[(self.pos + TRUE_PEAK_TAPS - Self::DELAY) % TRUE_PEAK_TAPS].abs();
+        if self.factor == 1 {
+            return peak;
+        }
+        for p in 0..self.factor {
+            let phase = &self.coefficients[p * TRUE_PEAK_TAPS..(p + 1) * TRUE_PEAK_TAPS];
+            let y: f64 = phase.iter().enumerate().map(|(k, c)| c * self.history[(self.pos + TRUE_PEAK_TAPS - k) % TRUE_PEAK_TAPS]).sum();
+            peak = peak.max(y.abs());
+        }
+
+        peak
+    }
+
+    fn process(&mut self, x: f64) {
+        let peak = self.interpolate(x);
+        self.peak = self.peak.max(peak).max(x.abs());
+    }
+}
+
+/// This function returns loudness in LUFS from weighted mean square.
+fn lufs(mean_square: f64) -> f64 {
+    if mean_square > 0.0 {
+        -0.691 + 10.0 * mean_square.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+/// This function returns mean of values which are louder than the gate.
+fn gated_mean(mean_squares: &[f64], gate: f64) -> Option<f64> {
+    let (sum, n) = mean_squares.iter().filter(|&&z| lufs(z) > gate).fold((0.0, 0usize), |(sum, n), z| (sum + z, n + 1));
+
+    if n == 0 { None } else { Some(sum / n as f64) }
+}
+
+/// Result of loudness measurement
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS
+    pub integrated: f64,
+    /// The largest momentary loudness in LUFS
+    pub momentary_max: f64,
+    /// The largest short-term loudness in LUFS
+    pub short_term_max: f64,
+    /// Loudness range in LU
+    pub loudness_range: f64,
+    /// True peak in dBTP
+    pub true_peak: f64
+}
+
+/// This structure measures loudness of samples which are added frame by frame.
+#[derive(Clone, Debug)]
+pub struct LoudnessMeter {
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    true_peaks: Vec<TruePeak>,
+    // Samples in 100 ms
+    block_size: usize,
+    count: usize,
+    sum: f64,
+    // Weighted mean square of each 100 ms
+    blocks: Vec<f64>
+}
+
+impl LoudnessMeter {
+    /// This method returns meter whose channels have the weights.
+    /// Weight is 1.0 for front and center, about 1.41 for surround and 0.0 for LFE.
+    pub fn new(weights: Vec<f64>, sampling_rate: u32) -> Result<Self> {
+        if weights.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Loudness meter needs at least 1 channel."));
+        }
+        if sampling_rate < 10 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate is too low to measure loudness."));
+        }
+        let channels = weights.len();
+
+        Ok(
+            Self {
+                weights,
+                filters: vec![[Biquad::shelf(sampling_rate), Biquad::high_pass(sampling_rate)]; channels],
+                true_peaks: vec![TruePeak::new(sampling_rate); channels],
+                block_size: (sampling_rate as f64 / 10.0).round() as usize,
+                count: 0,
+                sum: 0.0,
+                blocks: Vec::new()
+            }
+        )
+    }
+
+    /// This method returns meter of 1 channel.
+    pub fn mono(sampling_rate: u32) -> Result<Self> {
+        Self::new(vec![1.0], sampling_rate)
+    }
+
+    /// This method returns meter whose channels are speakers of the layout.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::analysis::loudness::LoudnessMeter;
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let meter = LoudnessMeter::from_layout(&SpeakerLayout::surround_5_1(), 48000).unwrap();
+    ///
+    /// assert_eq!(meter.weights(), &[1.0, 1.0, 1.0, 0.0, 1.41, 1.41]);
+    /// ```
+    pub fn from_layout(layout: &SpeakerLayout, sampling_rate: u32) -> Result<Self> {
+        let weights = layout.speakers.iter().map(|speaker| {
+            if speaker.lfe {
+                return 0.0;
+            }
+            let (azimuth, elevation) = match speaker.position {
+                SpeakerPosition::Polar { azimuth, elevation, .. } => (azimuth, elevation),
+                SpeakerPosition::Field(pos) => {
+                    let (right, front, up) = (pos.x - 0.5, pos.y - 0.5, pos.z - 0.5);
+                    ((-right).atan2(front).to_degrees(), up.atan2(right.hypot(front)).to_degrees())
+                }
+            };
+            let azimuth = (azimuth + 180.0).rem_euclid(360.0) - 180.0;
+            if elevation.abs() < 30.0 && (60.0..=120.0).contains(&azimuth.abs()) { 1.41 } else { 1.0 }
+        }).collect();
+
+        Self::new(weights, sampling_rate)
+    }
+
+    /// This method returns weight of each channel.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// This method adds a frame which has a sample of each channel.
+    pub fn add_frame(&mut self, frame: &[f64]) {
+        for (i, &x) in frame.iter().enumerate().take(self.weights.len()) {
+            self.true_peaks[i].process(x);
+            let [shelf, high_pass] = &mut self.filters[i];
+            let y = high_pass.process(shelf.process(x));
+            self.sum += self.weights[i] * y * y;
+        }
+        self.count += 1;
+        if self.count == self.block_size {
+            self.blocks.push(self.sum / self.block_size as f64);
+            self.count = 0;
+            self.sum = 0.0;
+        }
+    }
+
+    /// This method returns mean square of each window of `n` blocks, moving by a block.
+    fn windows(&self, n: usize) -> Vec<f64> {
+        self.blocks.windows(n).map(|window| window.iter().sum::<f64>() / n as f64).collect()
+    }
+
+    fn last_window(&self, n: usize) -> f64 {
+        match self.blocks.len().checked_sub(n) {
+            Some(start) => lufs(self.blocks[start..].iter().sum::<f64>() / n as f64),
+            None => f64::NEG_INFINITY
+        }
+    }
+
+    /// This method returns momentary loudness of the last 400 ms.
+    pub fn momentary(&self) -> f64 {
+        self.last_window(MOMENTARY)
+    }
+
+    /// This method returns short-term loudness of the last 3 seconds.
+    pub fn short_term(&self) -> f64 {
+        self.last_window(SHORT_TERM)
+    }
+
+    /// This method returns integrated loudness gated by -70 LUFS and -10 LU.
+    pub fn integrated(&self) -> f64 {
+        let windows = self.windows(MOMENTARY);
+        match gated_mean(&windows, ABSOLUTE_GATE) {
+            // Relative gate doesn't let windows under the absolute gate in.
+            Some(mean) => lufs(gated_mean(&windows, (lufs(mean) - 10.0).max(ABSOLUTE_GATE)).unwrap_or(mean)),
+            None => f64::NEG_INFINITY
+        }
+    }
+
+    /// This method returns loudness range, which is from 10th to 95th percentile of short-term loudness gated by -70 LUFS and -20 LU.
+    pub fn loudness_range(&self) -> f64 {
+        let windows = self.windows(SHORT_TERM);
+        let mean = match gated_mean(&windows, ABSOLUTE_GATE) {
+            Some(mean) => mean,
+            None => return 0.0
+        };
+        let gate = lufs(mean) - 20.0;
+        let mut loudness: Vec<f64> = windows.into_iter().map(lufs).filter(|&l| l > ABSOLUTE_GATE && l > gate).collect();
+        loudness.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+
+        percentile(0.95) - percentile(0.10)
+    }
+
+    /// This method returns the largest true peak of all channels.
+    pub fn true_peak(&self) -> f64 {
+        let peak = self.true_peaks.iter().map(|true_peak| true_peak.peak).fold(0.0, f64::max);
+
+        20.0 * peak.log10()
+    }
+
+    /// This method returns all results.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::analysis::loudness::LoudnessMeter;
+    ///
+    /// let mut meter = LoudnessMeter::mono(48000).unwrap();
+    /// // 1 kHz sine wave at -20 dBFS for 1 second
+    /// for i in 0..48000 {
+    ///     meter.add_frame(&[0.1 * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / 48000.0).sin()]);
+    /// }
+    /// let loudness = meter.loudness();
+    ///
+    /// assert!((loudness.integrated + 23.0).abs() < 0.1);
+    /// assert!((loudness.true_peak + 20.0).abs() < 0.1);
+    /// ```
+    pub fn loudness(&self) -> Loudness {
+        let max = |n: usize| self.windows(n).into_iter().map(lufs).fold(f64::NEG_INFINITY, f64::max);
+
+        Loudness {
+            integrated: self.integrated(),
+            momentary_max: max(MOMENTARY),
+            short_term_max: max(SHORT_TERM),
+            loudness_range: self.loudness_range(),
+            true_peak: self.true_peak()
+        }
+    }
+}
+
+/// This function reads Wav and measures loudness.
+/// Weight of each channel comes from channel mask, or usual layout of the number of channels.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::analysis::loudness::measure_wav;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.wav")?);
+///
+///     let loudness = measure_wav(&mut reader)?;
+///     println!("{} LUFS", loudness.integrated);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn measure_wav<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<Loudness> {
+    let wav: Wav = reader.read_details()?;
+    let channels = wav.channels as usize;
+    let layout = if wav.channel_mask != 0 {
+        SpeakerLayout::from_channel_mask(wav.channel_mask)
+    } else {
+        SpeakerLayout::from_channels(wav.channels)
+    };
+    let mut meter = match layout {
+        Some(layout) if layout.speakers.len() == channels => LoudnessMeter::from_layout(&layout, wav.sampling_rate)?,
+        _ => LoudnessMeter::new(vec![1.0; channels], wav.sampling_rate)?
+    };
+    let frames = wav.data_size as u64 / wav.data_block_size.max(1) as u64;
+    let mut frame = vec![0.0; channels];
+    for _ in 0..frames {
+        for n in frame.iter_mut() {
+            let wav_block: WavBlock = reader.read_block(&wav)?;
+            *n = wav_block.into();
+        }
+        meter.add_frame(&frame);
+    }
+
+    Ok(meter.loudness())
+}
+
+/// This function reads Bubble and measures loudness of its samples.
+pub fn measure_bub<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<Loudness> {
+    let bub: Bubble = reader.read_details()?;
+    let mut meter = LoudnessMeter::mono(bub.sampling_rate)?;
+    for _ in 0..bub.blocks {
+        let bub_block: BubbleBlock = reader.read_block(&bub)?;
+        meter.add_frame(&[bub_block.wav_block.into()]);
+    }
+
+    Ok(meter.loudness())
+}
+
+/// Result of loudness measurement of Floaout
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct FloaoutLoudness {
+    /// Loudness of samples of each Bubble in order of `BubblesInFloaout`
+    pub bubbles: Vec<Loudness>,
+    /// Loudness rendered on speaker layout of Renderer
+    pub rendered: Option<Loudness>
+}
+
+/// This function reads Floaout and measures loudness of each Bubble.
+/// If `renderer` is `Some`, loudness rendered by it is measured too.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::analysis::loudness::measure_oao;
+/// use floaout::render::Renderer;
+/// use floaout::render::layout::SpeakerLayout;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///     let mut renderer = Renderer::new(SpeakerLayout::surround_5_1(), 48000)?;
+///
+///     let loudness = measure_oao(&mut reader, Some(&mut renderer))?;
+///     println!("{} LUFS on 5.1", loudness.rendered.unwrap().integrated);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn measure_oao<R: Read + Seek>(reader: &mut BufReader<R>, mut renderer: Option<&mut Renderer>) -> Result<FloaoutLoudness> {
+    let oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    let mut meters = vec![LoudnessMeter::mono(oao.sampling_rate)?; oao.bubbles as usize];
+    let mut rendered = match renderer.as_deref() {
+        Some(renderer) => {
+            if renderer.sampling_rate() != oao.sampling_rate {
+                return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate of Floaout and Renderer are different."));
+            }
+            Some(LoudnessMeter::from_layout(renderer.layout(), oao.sampling_rate)?)
+        },
+        None => None
+    };
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        for (meter, bub_block) in meters.iter_mut().zip(&oao_block.0) {
+            meter.add_frame(&[bub_block.wav_block.into()]);
+        }
+        if let (Some(renderer), Some(meter)) = (renderer.as_deref_mut(), rendered.as_mut()) {
+            meter.add_frame(&renderer.render_block(&oao_block));
+        }
+    }
+
+    Ok(
+        FloaoutLoudness {
+            bubbles: meters.iter().map(LoudnessMeter::loudness).collect(),
+            rendered: rendered.map(|meter| meter.loudness())
+        }
+    )
+}
\ No newline at end of file
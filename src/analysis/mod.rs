@@ -0,0 +1,9 @@
+//! Analysis
+//!
+//! This module contains measurement of `Wav`, `Bubble` and `Floaout`.
+//! Every measurement reads block by block, so it doesn't load whole file.
+
+pub mod diff;
+pub mod loudness;
+pub mod spatial;
+pub mod stats;
\ No newline at end of file
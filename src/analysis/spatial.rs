@@ -0,0 +1,332 @@
+//! Spatial analysis of Bubble fields
+//!
+//! Bubble fields of each window are averaged, and the shape of each Bubble and overlap between Bubbles are computed from them.
+//! Positions are in `FieldPosition`, and speed is in Bubble field per second.
+
+use crate::format::{BubbleField, BubbleFieldSize, FieldPosition};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use std::convert::TryInto;
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek};
+
+/// Options of spatial analysis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatialOptions {
+    /// Length of window in seconds. Each window is at least 1 block.
+    pub window: f64,
+    /// Bubble whose centroid moves less than this is static.
+    pub static_distance: f64,
+    /// Samples at this level in dBFS or more are active.
+    pub silence_threshold: f64,
+    /// Cell which this number of Bubbles or more are in is crowded.
+    pub crowded: usize
+}
+
+impl Default for SpatialOptions {
+    fn default() -> Self {
+        Self {
+            window: 0.1,
+            static_distance: 0.01,
+            silence_threshold: -60.0,
+            crowded: 3
+        }
+    }
+}
+
+/// Shape of Bubble field
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct FieldShape {
+    /// Center weighted by each value
+    pub centroid: FieldPosition,
+    /// Mean of squared distance from the centroid weighted by each value
+    pub spread: f64,
+    /// Number of cells which aren't 0
+    pub occupied: usize,
+    /// The smallest and the largest centers of cells which aren't 0
+    pub bounds: (FieldPosition, FieldPosition)
+}
+
+impl FieldShape {
+    /// This method returns shape of Bubble field.
+    /// If all values are 0, this returns `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::analysis::spatial::FieldShape;
+    /// use floaout::format::BubbleField;
+    ///
+    /// // width 4
+    /// let bub_field: BubbleField = vec![vec![vec![255], vec![0], vec![255], vec![0]]].into();
+    /// let shape = FieldShape::from_bub_field(&bub_field).unwrap();
+    ///
+    /// assert_eq!(shape.centroid, (0.375, 0.5, 0.5).into());
+    /// assert_eq!(shape.spread, 0.0625);
+    /// assert_eq!(shape.occupied, 2);
+    /// assert_eq!(shape.bounds, ((0.125, 0.5, 0.5).into(), (0.625, 0.5, 0.5).into()));
+    /// ```
+    pub fn from_bub_field(bub_field: &BubbleField) -> Option<Self> {
+        let values = bub_field.values();
+        let size = (values.len(), values.first().map_or(0, Vec::len), values.first().and_then(|plane| plane.first()).map_or(0, Vec::len));
+        let grid: Vec<f64> = values.iter().flatten().flatten().map(|&n| n as f64).collect();
+
+        Self::from_grid(&grid, size)
+    }
+
+    /// This method returns shape of values indexed by `[length][width][height]` in a slice.
+    fn from_grid(grid: &[f64], (length, width, height): (usize, usize, usize)) -> Option<Self> {
+        let center = |i: usize| -> FieldPosition {
+            let (l, w, h) = (i / (width * height), i / height % width, i % height);
+            ((w as f64 + 0.5) / width as f64, (l as f64 + 0.5) / length as f64, (h as f64 + 0.5) / height as f64).into()
+        };
+        let total: f64 = grid.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut sum = (0.0, 0.0, 0.0);
+        let mut occupied = 0;
+        let (mut min, mut max) = ((f64::MAX, f64::MAX, f64::MAX), (f64::MIN, f64::MIN, f64::MIN));
+        for (i, &n) in grid.iter().enumerate().filter(|&(_, &n)| n > 0.0) {
+            let FieldPosition { x, y, z } = center(i);
+            sum = (sum.0 + n * x, sum.1 + n * y, sum.2 + n * z);
+            occupied += 1;
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        }
+        let centroid: FieldPosition = (sum.0 / total, sum.1 / total, sum.2 / total).into();
+        let spread = grid.iter().enumerate().map(|(i, &n)| n * center(i).distance(centroid).powi(2)).sum::<f64>() / total;
+
+        Some(
+            Self {
+                centroid,
+                spread,
+                occupied,
+                bounds: (min.into(), max.into())
+            }
+        )
+    }
+}
+
+/// This function returns how much two grids overlap from 0.0 to 1.0.
+fn overlap(a: &[f64], b: &[f64]) -> f64 {
+    let (sum_a, sum_b): (f64, f64) = (a.iter().sum(), b.iter().sum());
+    if sum_a <= 0.0 || sum_b <= 0.0 {
+        return 0.0;
+    }
+
+    a.iter().zip(b).map(|(n, m)| n.min(*m)).sum::<f64>() / sum_a.min(sum_b)
+}
+
+/// Analysis of a window
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpatialWindow {
+    /// The first block
+    pub start: u64,
+    /// Number of blocks
+    pub blocks: u64,
+    /// Shape of averaged Bubble field of each Bubble
+    pub shapes: Vec<Option<FieldShape>>,
+    /// Speed of centroid of each Bubble from its previous window which has a centroid
+    pub speeds: Vec<Option<f64>>,
+    /// Overlap of each pair of Bubbles. `overlaps[i][j]` is overlap of Bubble `i` and `j`, and 1.0 means one is within the other.
+    pub overlaps: Vec<Vec<f64>>,
+    /// Cells `(length, width, height)` which crowded Bubbles are in
+    pub crowded: Vec<(usize, usize, usize)>
+}
+
+/// Summary of a Bubble over all windows
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BubbleSpatial {
+    /// Name of Bubble
+    pub name: String,
+    /// Diagonal of the box which contains centroids of all blocks
+    pub travel: f64,
+    /// Whether the centroid never moves
+    pub is_static: bool,
+    /// The largest speed between windows
+    pub max_speed: f64,
+    /// Number of blocks whose Bubble field is all 0 while the sample is active
+    pub empty_while_active: u64
+}
+
+/// Result of spatial analysis
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpatialReport {
+    /// Sampling rate
+    pub sampling_rate: u32,
+    /// Analysis of each window
+    pub windows: Vec<SpatialWindow>,
+    /// Summary of each Bubble
+    pub bubbles: Vec<BubbleSpatial>
+}
+
+/// State of a Bubble
+#[derive(Clone, Debug)]
+struct BubbleState {
+    summary: BubbleSpatial,
+    grid: Vec<f64>,
+    bounds: Option<(FieldPosition, FieldPosition)>,
+    last: Option<(u64, FieldPosition)>
+}
+
+/// This structure analyzes blocks of Floaout which are added one by one.
+#[derive(Clone, Debug)]
+pub struct SpatialMeter {
+    options: SpatialOptions,
+    sampling_rate: u32,
+    size: (usize, usize, usize),
+    window: u64,
+    threshold: f64,
+    blocks: u64,
+    // The first block of the current window
+    window_start: u64,
+    bubbles: Vec<BubbleState>,
+    windows: Vec<SpatialWindow>
+}
+
+impl SpatialMeter {
+    /// This method returns meter of Bubbles which have the names.
+    pub fn new(names: Vec<String>, bub_field_size: BubbleFieldSize, sampling_rate: u32, options: &SpatialOptions) -> Result<Self> {
+        let size: (usize, usize, usize) = bub_field_size.try_into().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let cells = size.0 * size.1 * size.2;
+
+        Ok(
+            Self {
+                options: *options,
+                sampling_rate,
+                size,
+                window: ((options.window * sampling_rate as f64).round() as u64).max(1),
+                threshold: 10f64.powf(options.silence_threshold / 20.0),
+                blocks: 0,
+                window_start: 0,
+                bubbles: names.into_iter().map(|name| BubbleState {
+                    summary: BubbleSpatial {
+                        name,
+                        ..Default::default()
+                    },
+                    grid: vec![0.0; cells],
+                    bounds: None,
+                    last: None
+                }).collect(),
+                windows: Vec::new()
+            }
+        )
+    }
+
+    /// This method adds a block of Floaout.
+    pub fn add_oao_block(&mut self, oao_block: &FloaoutBlock) {
+        for (bubble, bub_block) in self.bubbles.iter_mut().zip(&oao_block.0) {
+            for (n, &m) in bubble.grid.iter_mut().zip(bub_block.bub_field.values().iter().flatten().flatten()) {
+                *n += m as f64;
+            }
+            let sample: f64 = bub_block.wav_block.into();
+            match bub_block.bub_field.centroid() {
+                Some(centroid) => {
+                    let (min, max) = bubble.bounds.get_or_insert((centroid, centroid));
+                    *min = (min.x.min(centroid.x), min.y.min(centroid.y), min.z.min(centroid.z)).into();
+                    *max = (max.x.max(centroid.x), max.y.max(centroid.y), max.z.max(centroid.z)).into();
+                },
+                None if sample.abs() >= self.threshold => bubble.summary.empty_while_active += 1,
+                None => {}
+            }
+        }
+        self.blocks += 1;
+        if self.blocks - self.window_start == self.window {
+            self.finish_window();
+        }
+    }
+
+    /// This method analyzes blocks which are added after the last window.
+    fn finish_window(&mut self) {
+        let (start, blocks) = (self.window_start, self.blocks - self.window_start);
+        self.window_start = self.blocks;
+        let shapes: Vec<Option<FieldShape>> = self.bubbles.iter().map(|bubble| FieldShape::from_grid(&bubble.grid, self.size)).collect();
+        let sampling_rate = self.sampling_rate as f64;
+        let speeds = self.bubbles.iter_mut().zip(&shapes).map(|(bubble, shape)| {
+            let shape = (*shape)?;
+            let speed = bubble.last.map(|(last_start, last)| {
+                shape.centroid.distance(last) * sampling_rate / (start - last_start) as f64
+            });
+            bubble.last = Some((start, shape.centroid));
+            if let Some(speed) = speed {
+                bubble.summary.max_speed = bubble.summary.max_speed.max(speed);
+            }
+            speed
+        }).collect();
+        let overlaps = self.bubbles.iter().map(|a| self.bubbles.iter().map(|b| overlap(&a.grid, &b.grid)).collect()).collect();
+        let (_, width, height) = self.size;
+        let crowded = (0..self.size.0 * width * height)
+            .filter(|&i| self.bubbles.iter().filter(|bubble| bubble.grid[i] > 0.0).count() >= self.options.crowded)
+            .map(|i| (i / (width * height), i / height % width, i % height))
+            .collect();
+        self.windows.push(SpatialWindow {
+            start,
+            blocks,
+            shapes,
+            speeds,
+            overlaps,
+            crowded
+        });
+        for bubble in self.bubbles.iter_mut() {
+            bubble.grid.iter_mut().for_each(|n| *n = 0.0);
+        }
+    }
+
+    /// This method returns report of added blocks.
+    pub fn report(mut self) -> SpatialReport {
+        if self.blocks > self.window_start {
+            self.finish_window();
+        }
+        let static_distance = self.options.static_distance;
+        let bubbles = self.bubbles.into_iter().map(|bubble| {
+            let mut summary = bubble.summary;
+            if let Some((min, max)) = bubble.bounds {
+                summary.travel = min.distance(max);
+                summary.is_static = summary.travel < static_distance;
+            }
+            summary
+        }).collect();
+
+        SpatialReport {
+            sampling_rate: self.sampling_rate,
+            windows: self.windows,
+            bubbles
+        }
+    }
+}
+
+/// This function reads Floaout and analyzes Bubble fields.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::analysis::spatial::{spatial_oao, SpatialOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///
+///     let report = spatial_oao(&mut reader, &SpatialOptions::default())?;
+///     for bubble in &report.bubbles {
+///         if bubble.is_static {
+///             println!("{} never moves.", bubble.name);
+///         }
+///         if bubble.empty_while_active != 0 {
+///             println!("{} is not in Bubble field while it sounds.", bubble.name);
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn spatial_oao<R: Read + Seek>(reader: &mut BufReader<R>, options: &SpatialOptions) -> Result<SpatialReport> {
+    let oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    let names = bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.clone()).collect();
+    let mut meter = SpatialMeter::new(names, oao.bub_field_size, oao.sampling_rate, options)?;
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        meter.add_oao_block(&oao_block);
+    }
+
+    Ok(meter.report())
+}
\ No newline at end of file
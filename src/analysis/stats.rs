@@ -0,0 +1,268 @@
+//! Signal statistics of each Bubble
+//!
+//! Statistics are peak, RMS, DC offset, clipped samples, NaN and infinite samples, silent ranges and active duration.
+//! NaN and infinite samples are counted, and the other statistics are computed without them.
+
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::json::Json;
+use std::io::{BufReader, Read, Result, Seek};
+use std::ops::Range;
+
+/// Options of statistics
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatsOptions {
+    /// Samples whose absolute value is this or more are clipped.
+    pub clip_level: f64,
+    /// Samples under this level in dBFS are silent.
+    pub silence_threshold: f64,
+    /// Silent ranges shorter than this in seconds aren't reported.
+    pub min_silence: f64
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            clip_level: 1.0,
+            silence_threshold: -60.0,
+            min_silence: 0.5
+        }
+    }
+}
+
+/// Statistics of a Bubble
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BubbleStats {
+    /// Name of Bubble
+    pub name: String,
+    /// The largest absolute value
+    pub peak: f64,
+    /// Root mean square
+    pub rms: f64,
+    /// Mean
+    pub dc_offset: f64,
+    /// Number of clipped samples
+    pub clipped: u64,
+    /// Number of NaN samples
+    pub nan: u64,
+    /// Number of infinite samples
+    pub infinite: u64,
+    /// Ranges of silent blocks
+    pub silence: Vec<Range<u64>>,
+    /// Number of blocks which aren't silent
+    pub active_blocks: u64
+}
+
+impl BubbleStats {
+    /// This method returns whether QA should check the Bubble.
+    pub fn has_problem(&self) -> bool {
+        self.clipped != 0 || self.nan != 0 || self.infinite != 0
+    }
+
+    fn to_json(&self, sampling_rate: u32) -> Json {
+        let silence = self.silence.iter().map(|range| Json::Array(vec![(range.start as f64).into(), (range.end as f64).into()])).collect();
+        Json::Object(vec![
+            ("name".into(), self.name.as_str().into()),
+            ("peak".into(), self.peak.into()),
+            ("rms".into(), self.rms.into()),
+            ("dc_offset".into(), self.dc_offset.into()),
+            ("clipped".into(), (self.clipped as f64).into()),
+            ("nan".into(), (self.nan as f64).into()),
+            ("infinite".into(), (self.infinite as f64).into()),
+            ("silence".into(), Json::Array(silence)),
+            ("active_duration".into(), (self.active_blocks as f64 / sampling_rate as f64).into())
+        ])
+    }
+}
+
+/// Statistics of Bubble or Floaout
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatsReport {
+    /// Sampling rate
+    pub sampling_rate: u32,
+    /// Number of blocks
+    pub blocks: u64,
+    /// Statistics of each Bubble
+    pub bubbles: Vec<BubbleStats>
+}
+
+impl StatsReport {
+    /// This method writes report in JSON.
+    /// Silent ranges are in blocks, and active duration is in seconds.
+    pub fn to_json(&self) -> String {
+        Json::Object(vec![
+            ("sampling_rate".into(), (self.sampling_rate as f64).into()),
+            ("blocks".into(), (self.blocks as f64).into()),
+            ("bubbles".into(), Json::Array(self.bubbles.iter().map(|stats| stats.to_json(self.sampling_rate)).collect()))
+        ]).to_string()
+    }
+}
+
+/// Sums of a Bubble
+#[derive(Clone, Debug, Default)]
+struct Accumulator {
+    stats: BubbleStats,
+    finite: u64,
+    sum: f64,
+    sum_of_squares: f64,
+    silence_start: Option<u64>
+}
+
+/// This structure computes statistics of blocks which are added one by one.
+///
+/// # Examples
+/// ```
+/// use floaout::analysis::stats::{StatsMeter, StatsOptions};
+///
+/// let mut meter = StatsMeter::new(vec!["Piano".into()], 4, &StatsOptions::default());
+/// for n in [0.5, -1.0, f64::NAN, 0.0] {
+///     meter.add(&[(n, true)]);
+/// }
+/// let report = meter.report();
+/// let stats = &report.bubbles[0];
+///
+/// assert_eq!(stats.peak, 1.0);
+/// assert_eq!(stats.dc_offset, -0.5 / 3.0);
+/// assert_eq!((stats.clipped, stats.nan), (1, 1));
+/// assert_eq!(stats.active_blocks, 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StatsMeter {
+    options: StatsOptions,
+    sampling_rate: u32,
+    blocks: u64,
+    threshold: f64,
+    bubbles: Vec<Accumulator>
+}
+
+impl StatsMeter {
+    /// This method returns meter of Bubbles which have the names.
+    pub fn new(names: Vec<String>, sampling_rate: u32, options: &StatsOptions) -> Self {
+        Self {
+            options: *options,
+            sampling_rate,
+            blocks: 0,
+            threshold: 10f64.powf(options.silence_threshold / 20.0),
+            bubbles: names.into_iter().map(|name| Accumulator {
+                stats: BubbleStats {
+                    name,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }).collect()
+        }
+    }
+
+    /// This method adds a block which is a sample and whether Bubble field has a value of each Bubble.
+    /// A block whose Bubble field is all 0 is silent.
+    pub fn add(&mut self, block: &[(f64, bool)]) {
+        let i = self.blocks;
+        for (bubble, &(n, audible)) in self.bubbles.iter_mut().zip(block) {
+            let stats = &mut bubble.stats;
+            let silent = if n.is_nan() {
+                stats.nan += 1;
+                false
+            } else if n.is_infinite() {
+                stats.infinite += 1;
+                false
+            } else {
+                bubble.finite += 1;
+                bubble.sum += n;
+                bubble.sum_of_squares += n * n;
+                stats.peak = stats.peak.max(n.abs());
+                if n.abs() >= self.options.clip_level {
+                    stats.clipped += 1;
+                }
+                n.abs() < self.threshold || !audible
+            };
+            if silent {
+                bubble.silence_start.get_or_insert(i);
+            } else {
+                stats.active_blocks += 1;
+                if let Some(start) = bubble.silence_start.take() {
+                    Self::push_silence(&mut stats.silence, start..i, &self.options, self.sampling_rate);
+                }
+            }
+        }
+        self.blocks += 1;
+    }
+
+    /// This method adds a block of Floaout.
+    pub fn add_oao_block(&mut self, oao_block: &FloaoutBlock) {
+        let block: Vec<(f64, bool)> = oao_block.0.iter().map(|bub_block| (bub_block.wav_block.into(), bub_block.bub_field.peak() != 0)).collect();
+        self.add(&block);
+    }
+
+    fn push_silence(silence: &mut Vec<Range<u64>>, range: Range<u64>, options: &StatsOptions, sampling_rate: u32) {
+        if (range.end - range.start) as f64 >= options.min_silence * sampling_rate as f64 {
+            silence.push(range);
+        }
+    }
+
+    /// This method returns report of added blocks.
+    pub fn report(self) -> StatsReport {
+        let (options, sampling_rate, blocks) = (self.options, self.sampling_rate, self.blocks);
+        let bubbles = self.bubbles.into_iter().map(|mut bubble| {
+            if let Some(start) = bubble.silence_start {
+                Self::push_silence(&mut bubble.stats.silence, start..blocks, &options, sampling_rate);
+            }
+            if bubble.finite != 0 {
+                bubble.stats.rms = (bubble.sum_of_squares / bubble.finite as f64).sqrt();
+                bubble.stats.dc_offset = bubble.sum / bubble.finite as f64;
+            }
+            bubble.stats
+        }).collect();
+
+        StatsReport {
+            sampling_rate,
+            blocks,
+            bubbles
+        }
+    }
+}
+
+/// This function reads Bubble and returns its statistics.
+pub fn stats_bub<R: Read + Seek>(reader: &mut BufReader<R>, options: &StatsOptions) -> Result<StatsReport> {
+    let bub: Bubble = reader.read_details()?;
+    let mut meter = StatsMeter::new(vec![bub.name.clone()], bub.sampling_rate, options);
+    for _ in 0..bub.blocks {
+        let bub_block: BubbleBlock = reader.read_block(&bub)?;
+        meter.add(&[(bub_block.wav_block.into(), bub_block.bub_field.peak() != 0)]);
+    }
+
+    Ok(meter.report())
+}
+
+/// This function reads Floaout and returns statistics of each Bubble.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::{File, write};
+/// use floaout::analysis::stats::{stats_oao, StatsOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///
+///     let report = stats_oao(&mut reader, &StatsOptions::default())?;
+///     for stats in report.bubbles.iter().filter(|stats| stats.has_problem()) {
+///         println!("{} has clipped, NaN or infinite samples.", stats.name);
+///     }
+///     write("foo.json", report.to_json())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn stats_oao<R: Read + Seek>(reader: &mut BufReader<R>, options: &StatsOptions) -> Result<StatsReport> {
+    let oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    let names = bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.clone()).collect();
+    let mut meter = StatsMeter::new(names, oao.sampling_rate, options);
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        meter.add_oao_block(&oao_block);
+    }
+
+    Ok(meter.report())
+}
\ No newline at end of file
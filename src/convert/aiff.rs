@@ -0,0 +1,127 @@
+//! Conversion between `Aiff` and `Bubble` or `Floaout`
+//!
+//! AIFF is converted in the same way as Wav.
+
+use crate::convert::wav::{bub_to_pcm, pcm_to_bub, pcm_to_oao, FieldHandling};
+use crate::format::{BubbleField, BubbleFieldSize};
+use crate::format::aiff::{Aiff, AiffBlock, Compression};
+use crate::format::bub::Bubble;
+use crate::format::oao::Floaout;
+use crate::format::wav::Wav;
+use crate::io::read::{ReadBlock, ReadFmt};
+use crate::io::write::{WriteBlock, WriteFmt};
+use crate::render::layout::SpeakerLayout;
+use std::convert::TryInto;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This function reads details of AIFF and returns `Wav` which has same format.
+fn read_aiff<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<(Aiff, Wav)> {
+    let aiff: Aiff = reader.read_details()?;
+    let wav = aiff.to_wav().map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    Ok((aiff, wav))
+}
+
+/// This function reads mono AIFF and writes Bubble block by block.
+/// See `wav_to_bub` for details.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::aiff::aiff_to_bub;
+/// use floaout::format::BubbleField;
+/// use floaout::format::bub::Bubble;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.aiff")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.bub")?);
+///     let bub = Bubble {
+///         bub_field_size: (0u8, 0u8, 0u8).into(),
+///         ..Default::default()
+///     };
+///
+///     aiff_to_bub(&mut reader, &mut writer, &bub, |_| vec![vec![vec![255]]].into())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn aiff_to_bub<R, W, F>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bub: &Bubble, bub_field: F) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    F: FnMut(u64) -> BubbleField
+{
+    let (aiff, wav) = read_aiff(reader)?;
+
+    pcm_to_bub(&wav, writer, bub, bub_field, || reader.read_block(&aiff).map(|aiff_block: AiffBlock| aiff_block.0.into()))
+}
+
+/// This function reads multichannel AIFF and writes Floaout block by block.
+/// AIFF has no channel mask, so the layout comes from the number of channels if `layout` is `None`.
+/// See `wav_to_oao` for details.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::aiff::aiff_to_oao;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("surround.aiff")?);
+///     let mut writer = io::BufWriter::new(File::create("surround.oao")?);
+///
+///     aiff_to_oao(&mut reader, &mut writer, None, (2u8, 2u8, 1u8).into())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn aiff_to_oao<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, layout: Option<&SpeakerLayout>, bub_field_size: BubbleFieldSize) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let (aiff, wav) = read_aiff(reader)?;
+
+    pcm_to_oao(&wav, writer, layout, bub_field_size, || reader.read_block(&aiff).map(|aiff_block: AiffBlock| aiff_block.0.into()))
+}
+
+/// This function reads Bubble and writes AIFF block by block.
+/// If compression is not `None`, it's AIFF-C.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::aiff::bub_to_aiff;
+/// use floaout::convert::wav::FieldHandling;
+/// use floaout::format::aiff::Compression;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.bub")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.aiff")?);
+///
+///     // 24 bits AIFF
+///     bub_to_aiff(&mut reader, &mut writer, FieldHandling::Drop, Compression::None, 24)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn bub_to_aiff<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, field_handling: FieldHandling, compression: Compression, bits_per_sample: u16) -> Result<Aiff>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let bub: Bubble = reader.read_details()?;
+    let sample_frames = bub.blocks.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "AIFF only accepts no more than the largest value of u32 sample frames."))?;
+    let aiff = Aiff::from_format(field_handling.channels(&bub)?, bub.sampling_rate, compression, bits_per_sample, sample_frames)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    writer.write_details(&aiff)?;
+    bub_to_pcm(reader, &bub, field_handling, |wav_block| writer.write_block(&aiff, AiffBlock(wav_block.into())))?;
+    // Sound Data Chunk is padded to even size.
+    if aiff.data_size % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+
+    Ok(aiff)
+}
\ No newline at end of file
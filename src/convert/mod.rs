@@ -0,0 +1,43 @@
+//! Conversion
+//!
+//! This module contains conversions between formats including audio.
+//! Every conversion reads and writes block by block, so it doesn't load whole file.
+
+pub mod aiff;
+pub mod mux;
+pub mod resample;
+pub mod transcode;
+pub mod wav;
+
+use crate::format::{BubbleField, BubbleFieldSize};
+use crate::format::bub::Bubble;
+use crate::io::write::WriteFmt;
+use std::convert::TryInto;
+use std::io::{BufWriter, Error, ErrorKind, Result, Seek, SeekFrom, Write};
+
+/// This function writes details of Bubble again at `start`, and comes back to the end.
+/// It's used to write `overall` after blocks are written.
+pub(crate) fn rewrite_bub_details<W: Write + Seek>(writer: &mut BufWriter<W>, start: u64, bub: &Bubble) -> Result<()> {
+    let end = writer.stream_position()?;
+    writer.seek(SeekFrom::Start(start))?;
+    writer.write_details(bub)?;
+    writer.seek(SeekFrom::Start(end))?;
+
+    Ok(())
+}
+
+/// This function returns size of name in bytes.
+pub(crate) fn name_size(name: &str) -> Result<u8> {
+    name.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Name is longer than 255 bytes."))
+}
+
+/// This function checks whether Bubble field has the size.
+pub(crate) fn check_bub_field(bub_field: &BubbleField, bub_field_size: BubbleFieldSize) -> Result<()> {
+    let (length, width, height) = bub_field_size.try_into().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let values = bub_field.values();
+    if values.len() == length && values.iter().all(|plane| plane.len() == width && plane.iter().all(|column| column.len() == height)) {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::InvalidInput, "Bubble field doesn't match Bubble field size."))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+//! Mux of `Bubble` into `Floaout` and demux of `Floaout` into `Bubble`
+
+use crate::convert::{check_bub_field, name_size, rewrite_bub_details};
+use crate::format::{BubbleField, BubbleFieldSize, Sample};
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::convert::TryInto;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This enum is how Bubbles which have different blocks are handled.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlocksPolicy {
+    /// All Bubbles must have same blocks.
+    #[default]
+    Exact,
+    /// Shorter Bubbles are padded with silence to the longest one.
+    Pad,
+    /// Longer Bubbles are trimmed to the shortest one.
+    Trim
+}
+
+/// Options of `mux`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MuxOptions {
+    /// Song ID of Floaout
+    pub song_id: u64,
+    /// How different blocks are handled.
+    pub blocks_policy: BlocksPolicy,
+    /// If this is `Some`, every Bubble field is resampled to this size.
+    /// If this is `None`, all Bubbles must have same Bubble field size.
+    pub bub_field_size: Option<BubbleFieldSize>
+}
+
+/// This function reads Bubbles and writes Floaout which contains them block by block.
+///
+/// Sampling rate and bits per sample of all Bubbles must be same.
+/// Bubble ID, name and color of each Bubble are written in `BubblesInFloaout` in order of `readers`.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::mux::{mux, BlocksPolicy, MuxOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut readers = vec![
+///         io::BufReader::new(File::open("piano.bub")?),
+///         io::BufReader::new(File::open("vocal.bub")?)
+///     ];
+///     let mut writer = io::BufWriter::new(File::create("foo.oao")?);
+///     let options = MuxOptions {
+///         blocks_policy: BlocksPolicy::Pad,
+///         bub_field_size: Some((1u8, 1u8, 1u8).into()),
+///         ..Default::default()
+///     };
+///
+///     mux(&mut readers, &mut writer, &options)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn mux<R, W>(readers: &mut [BufReader<R>], writer: &mut BufWriter<W>, options: &MuxOptions) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let mut bubs = Vec::with_capacity(readers.len());
+    for reader in readers.iter_mut() {
+        let bub: Bubble = reader.read_details()?;
+        bubs.push(bub);
+    }
+    let first = bubs.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "There is no Bubble to mux."))?;
+    if bubs.iter().any(|bub| bub.sampling_rate != first.sampling_rate) {
+        return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate of Bubbles are different."));
+    }
+    if bubs.iter().any(|bub| bub.bits_per_sample != first.bits_per_sample) {
+        return Err(Error::new(ErrorKind::InvalidInput, "Bits per sample of Bubbles are different."));
+    }
+    let bub_field_size = match options.bub_field_size {
+        Some(bub_field_size) => bub_field_size,
+        None => {
+            if bubs.iter().any(|bub| bub.bub_field_size != first.bub_field_size) {
+                return Err(Error::new(ErrorKind::InvalidInput, "Bubble field size of Bubbles are different."));
+            }
+            first.bub_field_size
+        }
+    };
+    let blocks = match options.blocks_policy {
+        BlocksPolicy::Exact => {
+            if bubs.iter().any(|bub| bub.blocks != first.blocks) {
+                return Err(Error::new(ErrorKind::InvalidInput, "Blocks of Bubbles are different."));
+            }
+            first.blocks
+        },
+        BlocksPolicy::Pad => bubs.iter().map(|bub| bub.blocks).max().unwrap_or(0),
+        BlocksPolicy::Trim => bubs.iter().map(|bub| bub.blocks).min().unwrap_or(0)
+    };
+    let oao = Floaout {
+        song_id: options.song_id,
+        bub_field_size,
+        bubbles: bubs.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Floaout only accepts no more than 65535 Bubbles."))?,
+        blocks,
+        sampling_rate: first.sampling_rate,
+        bits_per_sample: first.bits_per_sample,
+        ..Default::default()
+    };
+    let mut bubs_in_oao = Vec::with_capacity(bubs.len());
+    for bub in &bubs {
+        bubs_in_oao.push(
+            BubbleInFloaout {
+                bubble_id: bub.bubble_id,
+                name_size: name_size(&bub.name)?,
+                name: bub.name.clone(),
+                color: bub.color
+            }
+        );
+    }
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(bubs_in_oao))?;
+
+    let silence = BubbleBlock::from_wav_block_and_bub_field(
+        Sample::from_f64_and_bits_per_sample(0.0, oao.bits_per_sample).into(),
+        BubbleField::from_bub_field_size(bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+    );
+    for i in 0..blocks {
+        let mut bub_blocks = Vec::with_capacity(bubs.len());
+        for (reader, bub) in readers.iter_mut().zip(&bubs) {
+            if i < bub.blocks {
+                let mut bub_block: BubbleBlock = reader.read_block(bub)?;
+                if bub.bub_field_size != bub_field_size {
+                    bub_block.bub_field = bub_block.bub_field.resample(bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+                }
+                check_bub_field(&bub_block.bub_field, bub_field_size)?;
+                bub_blocks.push(bub_block);
+            } else {
+                bub_blocks.push(silence.clone());
+            }
+        }
+        writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+    }
+
+    Ok(oao)
+}
+
+/// This function reads Floaout and writes each Bubble in it block by block.
+///
+/// `writers` must be as many as Bubbles in Floaout, and each Bubble is written in order of `BubblesInFloaout`.
+/// Bubble ID, name and color come from `BubbleInFloaout`, and `overall` is computed from written blocks.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::mux::demux;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///     let mut writers = vec![
+///         io::BufWriter::new(File::create("piano.bub")?),
+///         io::BufWriter::new(File::create("vocal.bub")?)
+///     ];
+///
+///     let bubs = demux(&mut reader, &mut writers)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn demux<R, W>(reader: &mut BufReader<R>, writers: &mut [BufWriter<W>]) -> Result<Vec<Bubble>>
+where
+    R: Read + Seek,
+    W: Write + Seek
+{
+    let oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    if writers.len() != bubs_in_oao.0.len() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Number of writers and Bubbles in Floaout are different."));
+    }
+    let overall = BubbleField::from_bub_field_size(oao.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let mut bubs = Vec::with_capacity(writers.len());
+    let mut starts = Vec::with_capacity(writers.len());
+    for (writer, bub_in_oao) in writers.iter_mut().zip(bubs_in_oao.0) {
+        let bub = Bubble {
+            bubble_id: bub_in_oao.bubble_id,
+            color: bub_in_oao.color,
+            name_size: name_size(&bub_in_oao.name)?,
+            name: bub_in_oao.name,
+            overall: overall.clone(),
+            ..Bubble::from(oao.clone())
+        };
+        starts.push(writer.stream_position()?);
+        writer.write_details(&bub)?;
+        bubs.push(bub);
+    }
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        for ((writer, bub), bub_block) in writers.iter_mut().zip(bubs.iter_mut()).zip(oao_block.0) {
+            bub.overall.max_assign(&bub_block.bub_field);
+            writer.write_block(&*bub, &bub_block)?;
+        }
+    }
+    for ((writer, bub), start) in writers.iter_mut().zip(&bubs).zip(starts) {
+        rewrite_bub_details(writer, start, bub)?;
+    }
+
+    Ok(bubs)
+}
\ No newline at end of file
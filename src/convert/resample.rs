@@ -0,0 +1,307 @@
+//! Sampling rate conversion of `Bubble` and `Floaout`
+//!
+//! Samples are converted by windowed-sinc polyphase filter, and Bubble fields are held or interpolated between blocks.
+
+use crate::convert::rewrite_bub_details;
+use crate::format::{BubbleField, Sample};
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// Zero crossings of sinc on each side
+const ZERO_CROSSINGS: usize = 32;
+/// Beta of Kaiser window
+const KAISER_BETA: f64 = 8.6;
+/// Cutoff frequency relative to the lower Nyquist frequency
+const ROLLOFF: f64 = 0.97;
+/// The largest number of phases
+const MAX_PHASES: u64 = 4096;
+
+/// This enum is how Bubble fields are made between blocks.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FieldInterpolation {
+    /// Bubble field of the previous input block is held.
+    /// When downsampling, it is the largest Bubble field of input blocks since the previous output block,
+    /// so short movements aren't skipped.
+    #[default]
+    Hold,
+    /// Each value of Bubble field is interpolated linearly between input blocks.
+    Linear
+}
+
+/// This structure is windowed-sinc polyphase filter which converts sampling rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resampler {
+    /// Output sampling rate divided by greatest common divisor
+    up: u64,
+    /// Input sampling rate divided by greatest common divisor
+    down: u64,
+    /// Coefficients of each phase
+    phases: Vec<Vec<f64>>
+}
+
+impl Resampler {
+    /// This method makes filter which converts sampling rate `from` to `to`.
+    /// Sampling rates whose ratio is too complex are not accepted.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::convert::resample::Resampler;
+    ///
+    /// let resampler = Resampler::new(44100, 48000).unwrap();
+    ///
+    /// assert_eq!(resampler.blocks(441), 480);
+    /// assert!(Resampler::new(44100, 0).is_err());
+    /// ```
+    pub fn new(from: u32, to: u32) -> Result<Self> {
+        if from == 0 || to == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate must be larger than 0."));
+        }
+        let gcd = gcd(from as u64, to as u64);
+        let (up, down) = (to as u64 / gcd, from as u64 / gcd);
+        if up > MAX_PHASES {
+            return Err(Error::new(ErrorKind::InvalidInput, "Ratio of sampling rates is too complex."));
+        }
+        let cutoff = if up == down { 1.0 } else { ROLLOFF * (up as f64 / down as f64).min(1.0) };
+        // Lower cutoff needs longer filter.
+        let half = (ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+        let phases = (0..up).map(|phase| {
+            let fraction = phase as f64 / up as f64;
+            let mut coefficients: Vec<f64> = (0..2 * half).map(|k| {
+                // Distance from output to input `i - half + 1 + k`
+                let d = fraction + half as f64 - 1.0 - k as f64;
+                cutoff * sinc(cutoff * d) * kaiser(d / half as f64)
+            }).collect();
+            // Gain of DC is 1.
+            let sum: f64 = coefficients.iter().sum();
+            coefficients.iter_mut().for_each(|c| *c /= sum);
+            coefficients
+        }).collect();
+
+        Ok(Self { up, down, phases })
+    }
+
+    /// This method returns number of output blocks from number of input blocks.
+    pub fn blocks(&self, blocks: u64) -> u64 {
+        ((blocks as u128 * self.up as u128).div_ceil(self.down as u128)) as u64
+    }
+
+    /// This function converts blocks which are read by `read` and passes them to `write`.
+    /// Each block has a sample and Bubble field of each channel.
+    fn process<F, G>(&self, blocks: u64, field_interpolation: FieldInterpolation, mut read: F, mut write: G) -> Result<u64>
+    where
+        F: FnMut() -> Result<Vec<BubbleBlock>>,
+        G: FnMut(Vec<(f64, BubbleField)>) -> Result<()>
+    {
+        let half = (self.phases[0].len() / 2) as i64;
+        let mut frames: VecDeque<Vec<BubbleBlock>> = VecDeque::new();
+        // Index of `frames[0]`
+        let mut base = 0i64;
+        let mut read_blocks = 0u64;
+        let out_blocks = self.blocks(blocks);
+        for n in 0..out_blocks {
+            let position = n as u128 * self.down as u128;
+            let i = (position / self.up as u128) as i64;
+            let phase = (position % self.up as u128) as usize;
+            // Input after `blocks` is silence.
+            while (read_blocks as i64) <= i + half && read_blocks < blocks {
+                frames.push_back(read()?);
+                read_blocks += 1;
+            }
+            while base < i - half + 1 && !frames.is_empty() {
+                frames.pop_front();
+                base += 1;
+            }
+            let coefficients = &self.phases[phase];
+            let current = &frames[(i - base) as usize];
+            let next = frames.get((i + 1 - base) as usize).unwrap_or(current);
+            let fraction = phase as f64 / self.up as f64;
+            // Input blocks after the previous output block are folded into this block.
+            let folded = if n == 0 {
+                0
+            } else {
+                ((n - 1) as u128 * self.down as u128 / self.up as u128) as i64 + 1
+            }.clamp(base, i);
+            let mut out = Vec::with_capacity(current.len());
+            for channel in 0..current.len() {
+                let mut sample = 0.0;
+                for (k, c) in coefficients.iter().enumerate() {
+                    let j = i - half + 1 + k as i64 - base;
+                    if j >= 0 {
+                        if let Some(frame) = frames.get(j as usize) {
+                            let n: f64 = frame[channel].wav_block.into();
+                            sample += c * n;
+                        }
+                    }
+                }
+                let bub_field = match field_interpolation {
+                    FieldInterpolation::Hold => {
+                        let mut bub_field = current[channel].bub_field.clone();
+                        for frame in frames.range((folded - base) as usize..(i - base) as usize) {
+                            bub_field.max_assign(&frame[channel].bub_field);
+                        }
+                        bub_field
+                    },
+                    FieldInterpolation::Linear => interpolate(&current[channel].bub_field, &next[channel].bub_field, fraction)
+                };
+                out.push((sample, bub_field));
+            }
+            write(out)?;
+        }
+
+        Ok(out_blocks)
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else if x.fract() == 0.0 {
+        // Exactly 0, so same sampling rate doesn't change samples.
+        0.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Kaiser window from -1 to 1
+fn kaiser(x: f64) -> f64 {
+    if x.abs() > 1.0 {
+        0.0
+    } else {
+        bessel_i0(KAISER_BETA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_BETA)
+    }
+}
+
+/// Modified Bessel function of the first kind of order 0
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+
+    sum
+}
+
+fn interpolate(a: &BubbleField, b: &BubbleField, fraction: f64) -> BubbleField {
+    let mut bub_field = a.clone();
+    for (n, &m) in bub_field.values_mut().iter_mut().flatten().flatten().zip(b.values().iter().flatten().flatten()) {
+        *n = (*n as f64 + (m as f64 - *n as f64) * fraction).round() as u8;
+    }
+
+    bub_field
+}
+
+/// This function reads Bubble and writes Bubble whose sampling rate is `sampling_rate` block by block.
+/// `overall` is computed again from written Bubble fields.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::resample::{resample_bub, FieldInterpolation};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("44100.bub")?);
+///     let mut writer = io::BufWriter::new(File::create("48000.bub")?);
+///
+///     resample_bub(&mut reader, &mut writer, 48000, FieldInterpolation::Linear)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn resample_bub<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, sampling_rate: u32, field_interpolation: FieldInterpolation) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek
+{
+    let read_bub: Bubble = reader.read_details()?;
+    let resampler = Resampler::new(read_bub.sampling_rate, sampling_rate)?;
+    let mut bub = Bubble {
+        blocks: resampler.blocks(read_bub.blocks),
+        sampling_rate,
+        overall: BubbleField::from_bub_field_size(read_bub.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+        ..read_bub.clone()
+    };
+    let start = writer.stream_position()?;
+    writer.write_details(&bub)?;
+    resampler.process(
+        read_bub.blocks,
+        field_interpolation,
+        || Ok(vec![reader.read_block(&read_bub)?]),
+        |out| {
+            for (sample, bub_field) in out {
+                bub.overall.max_assign(&bub_field);
+                let sample = Sample::from_f64_and_bits_per_sample(sample, bub.bits_per_sample);
+                writer.write_block(&bub, &BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field))?;
+            }
+            Ok(())
+        }
+    )?;
+    rewrite_bub_details(writer, start, &bub)?;
+
+    Ok(bub)
+}
+
+/// This function reads Floaout and writes Floaout whose sampling rate is `sampling_rate` block by block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::resample::{resample_oao, FieldInterpolation};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("44100.oao")?);
+///     let mut writer = io::BufWriter::new(File::create("48000.oao")?);
+///
+///     resample_oao(&mut reader, &mut writer, 48000, FieldInterpolation::Hold)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn resample_oao<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, sampling_rate: u32, field_interpolation: FieldInterpolation) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let read_oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let resampler = Resampler::new(read_oao.sampling_rate, sampling_rate)?;
+    let oao = Floaout {
+        blocks: resampler.blocks(read_oao.blocks),
+        sampling_rate,
+        ..read_oao.clone()
+    };
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    resampler.process(
+        read_oao.blocks,
+        field_interpolation,
+        || {
+            let oao_block: FloaoutBlock = reader.read_block(&read_oao)?;
+            Ok(oao_block.0)
+        },
+        |out| {
+            let bub_blocks = out.into_iter().map(|(sample, bub_field)| {
+                let sample = Sample::from_f64_and_bits_per_sample(sample, oao.bits_per_sample);
+                BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field)
+            }).collect::<Vec<BubbleBlock>>();
+            writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))
+        }
+    )?;
+
+    Ok(oao)
+}
\ No newline at end of file
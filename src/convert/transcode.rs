@@ -0,0 +1,206 @@
+//! Conversion of bits per sample
+//!
+//! This module rewrites samples of `Bubble`, `Floaout` and `Wav` as 32 or 64 bits float.
+
+use crate::format::Sample;
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::format::wav::{SampleFormat, Wav, WavBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// Statistics of samples which are narrowed from 64 bits to 32 bits.
+/// When samples are widened, only `samples` is counted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct NarrowingStats {
+    /// Number of converted samples
+    pub samples: u64,
+    /// Number of finite samples which become infinite because they are out of range of 32 bits float.
+    pub out_of_range: u64,
+    /// Number of samples which are not same after conversion.
+    pub precision_loss: u64,
+    /// Number of samples which are not 0, but become 0 or subnormal.
+    pub underflow: u64,
+    /// The largest absolute error of finite samples
+    pub max_error: f64
+}
+
+impl NarrowingStats {
+    /// This method converts the sample into bits per sample and counts it.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::convert::transcode::NarrowingStats;
+    /// use floaout::format::Sample;
+    ///
+    /// let mut stats = NarrowingStats::default();
+    /// stats.convert(Sample::Float64(0.5), 32);
+    /// stats.convert(Sample::Float64(0.1), 32);
+    /// stats.convert(Sample::Float64(1e300), 32);
+    ///
+    /// assert_eq!(stats.samples, 3);
+    /// assert_eq!(stats.precision_loss, 2);
+    /// assert_eq!(stats.out_of_range, 1);
+    /// ```
+    pub fn convert(&mut self, sample: Sample, bits_per_sample: u16) -> Sample {
+        self.samples += 1;
+        match (sample, bits_per_sample) {
+            (Sample::Float64(n), 32) => {
+                let narrowed = n as f32;
+                if narrowed as f64 != n && !n.is_nan() {
+                    self.precision_loss += 1;
+                }
+                if n.is_finite() {
+                    if narrowed.is_infinite() {
+                        self.out_of_range += 1;
+                    } else {
+                        self.max_error = self.max_error.max((narrowed as f64 - n).abs());
+                        if n != 0.0 && !narrowed.is_normal() {
+                            self.underflow += 1;
+                        }
+                    }
+                }
+                Sample::Float32(narrowed)
+            },
+            _ => Sample::from_f64_and_bits_per_sample(sample.into(), bits_per_sample)
+        }
+    }
+
+    /// This method returns true if any sample is changed by narrowing.
+    pub fn is_lossy(&self) -> bool {
+        self.precision_loss != 0
+    }
+}
+
+fn check_bits_per_sample(bits_per_sample: u16) -> Result<()> {
+    match bits_per_sample {
+        32 | 64 => Ok(()),
+        _ => Err(Error::new(ErrorKind::InvalidInput, "Bits per sample must be 32 or 64."))
+    }
+}
+
+/// This function reads Bubble and writes Bubble whose bits per sample is `bits_per_sample` block by block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::transcode::transcode_bub;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("64.bub")?);
+///     let mut writer = io::BufWriter::new(File::create("32.bub")?);
+///
+///     let (_, stats) = transcode_bub(&mut reader, &mut writer, 32)?;
+///     if stats.out_of_range != 0 {
+///         println!("{} samples are out of range.", stats.out_of_range);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn transcode_bub<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bits_per_sample: u16) -> Result<(Bubble, NarrowingStats)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    check_bits_per_sample(bits_per_sample)?;
+    let read_bub: Bubble = reader.read_details()?;
+    let bub = Bubble {
+        bits_per_sample,
+        ..read_bub.clone()
+    };
+    let mut stats = NarrowingStats::default();
+    writer.write_details(&bub)?;
+    for _ in 0..bub.blocks {
+        let mut bub_block: BubbleBlock = reader.read_block(&read_bub)?;
+        bub_block.wav_block = stats.convert(bub_block.wav_block.into(), bits_per_sample).into();
+        writer.write_block(&bub, &bub_block)?;
+    }
+
+    Ok((bub, stats))
+}
+
+/// This function reads Floaout and writes Floaout whose bits per sample is `bits_per_sample` block by block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::transcode::transcode_oao;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("32.oao")?);
+///     let mut writer = io::BufWriter::new(File::create("64.oao")?);
+///
+///     transcode_oao(&mut reader, &mut writer, 64)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn transcode_oao<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bits_per_sample: u16) -> Result<(Floaout, NarrowingStats)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    check_bits_per_sample(bits_per_sample)?;
+    let read_oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let oao = Floaout {
+        bits_per_sample,
+        ..read_oao.clone()
+    };
+    let mut stats = NarrowingStats::default();
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    for _ in 0..oao.blocks {
+        let mut oao_block: FloaoutBlock = reader.read_block(&read_oao)?;
+        for bub_block in oao_block.0.iter_mut() {
+            bub_block.wav_block = stats.convert(bub_block.wav_block.into(), bits_per_sample).into();
+        }
+        writer.write_block(&oao, &oao_block)?;
+    }
+
+    Ok((oao, stats))
+}
+
+/// This function reads Wav and writes IEEE float Wav whose bits per sample is `bits_per_sample` block by block.
+/// Channel mask is kept, and other chunks are not written.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::transcode::transcode_wav;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("64.wav")?);
+///     let mut writer = io::BufWriter::new(File::create("32.wav")?);
+///
+///     let (_, stats) = transcode_wav(&mut reader, &mut writer, 32)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn transcode_wav<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bits_per_sample: u16) -> Result<(Wav, NarrowingStats)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    check_bits_per_sample(bits_per_sample)?;
+    let read_wav: Wav = reader.read_details()?;
+    let wav = Wav {
+        channel_mask: read_wav.channel_mask,
+        ..Wav::from_sample_format(read_wav.channels, read_wav.sampling_rate, SampleFormat::Float, bits_per_sample, read_wav.blocks())
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+    };
+    let mut stats = NarrowingStats::default();
+    writer.write_details(&wav)?;
+    for _ in 0..read_wav.blocks() * read_wav.channels as u64 {
+        let wav_block: WavBlock = reader.read_block(&read_wav)?;
+        writer.write_block(&wav, WavBlock::from(stats.convert(wav_block.into(), bits_per_sample)))?;
+    }
+
+    Ok((wav, stats))
+}
\ No newline at end of file
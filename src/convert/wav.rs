@@ -0,0 +1,366 @@
+//! Conversion between `Bubble` and `Wav`
+
+use crate::convert::{check_bub_field, name_size, rewrite_bub_details};
+use crate::format::{BubbleField, BubbleFieldSize, Color, FieldPosition, Sample};
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::format::wav::{SampleFormat, Wav, WavBlock};
+use crate::io::read::{ReadBlock, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use crate::motion::trajectory::Trajectory;
+use crate::render::Renderer;
+use crate::render::layout::SpeakerLayout;
+use std::convert::TryFrom;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This function reads mono Wav and writes Bubble block by block.
+///
+/// `bub` gives details such as name, color and Bubble field size.
+/// Blocks, sampling rate, name size and overall are set from Wav and Bubble fields.
+/// If bits per sample of `bub` isn't 32 or 64, it's 64 for 32 bits integer Wav and same as Wav for the others.
+/// `bub_field` returns Bubble field of each block from the index of block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::wav::wav_to_bub;
+/// use floaout::format::BubbleField;
+/// use floaout::format::bub::Bubble;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.wav")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.bub")?);
+///     let bub = Bubble {
+///         bub_field_size: (0u8, 1u8, 0u8).into(),
+///         name: "foo".into(),
+///         ..Default::default()
+///     };
+///
+///     // move from left to right every 44100 blocks
+///     let left: BubbleField = vec![vec![vec![255], vec![0]]].into();
+///     let right: BubbleField = vec![vec![vec![0], vec![255]]].into();
+///     wav_to_bub(&mut reader, &mut writer, &bub, |i| if i / 44100 % 2 == 0 { left.clone() } else { right.clone() })?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn wav_to_bub<R, W, F>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bub: &Bubble, bub_field: F) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+    F: FnMut(u64) -> BubbleField
+{
+    let wav: Wav = reader.read_details()?;
+
+    pcm_to_bub(&wav, writer, bub, bub_field, || reader.read_block(&wav))
+}
+
+/// This function writes Bubble from samples which are read by `read`.
+/// `wav` is details of the samples.
+pub(crate) fn pcm_to_bub<W, F, G>(wav: &Wav, writer: &mut BufWriter<W>, bub: &Bubble, mut bub_field: F, mut read: G) -> Result<Bubble>
+where
+    W: Write + Seek,
+    F: FnMut(u64) -> BubbleField,
+    G: FnMut() -> Result<WavBlock>
+{
+    let from_wav = Bubble::try_from(*wav).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let bits_per_sample = match bub.bits_per_sample {
+        32 | 64 => bub.bits_per_sample,
+        _ => bits_per_sample_from_wav(wav)
+    };
+    let mut bub = Bubble {
+        blocks: from_wav.blocks,
+        sampling_rate: from_wav.sampling_rate,
+        bits_per_sample,
+        name_size: name_size(&bub.name)?,
+        overall: BubbleField::from_bub_field_size(bub.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?,
+        ..bub.clone()
+    };
+    let start = writer.stream_position()?;
+    writer.write_details(&bub)?;
+    for i in 0..bub.blocks {
+        let wav_block = read()?;
+        let bub_field = bub_field(i);
+        check_bub_field(&bub_field, bub.bub_field_size)?;
+        bub.overall.max_assign(&bub_field);
+        let sample = Sample::from_f64_and_bits_per_sample(wav_block.into(), bub.bits_per_sample);
+        writer.write_block(&bub, &BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field))?;
+    }
+    rewrite_bub_details(writer, start, &bub)?;
+
+    Ok(bub)
+}
+
+/// This function returns bits per sample of Bubble which keeps samples of Wav.
+fn bits_per_sample_from_wav(wav: &Wav) -> u16 {
+    match (wav.sample_format(), wav.bits_per_sample) {
+        (Some(SampleFormat::Float), 64) | (Some(SampleFormat::Int), 32) => 64,
+        _ => 32
+    }
+}
+
+/// This function returns color of `i`th Bubble in `n` Bubbles.
+/// Colors go around the hue circle, so each Bubble has distinct color.
+fn distinct_color(i: usize, n: usize) -> Color {
+    let hue = i as f64 / n.max(1) as f64 * 6.0;
+    let x = ((1.0 - (hue % 2.0 - 1.0).abs()) * 255.0).round() as u8;
+    match hue as usize {
+        0 => (255, x, 0),
+        1 => (x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, x)
+    }.into()
+}
+
+/// This function reads multichannel Wav and writes Floaout block by block.
+///
+/// Each channel becomes a Bubble which stays at the position of its speaker, and has the speaker name and distinct color.
+/// If `layout` is `None`, the layout comes from channel mask of Wav, or from the number of channels if there is no mask.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::wav::wav_to_oao;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("surround.wav")?);
+///     let mut writer = io::BufWriter::new(File::create("surround.oao")?);
+///
+///     // Layout from channel mask
+///     let oao = wav_to_oao(&mut reader, &mut writer, None, (2u8, 2u8, 1u8).into())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn wav_to_oao<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, layout: Option<&SpeakerLayout>, bub_field_size: BubbleFieldSize) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let wav: Wav = reader.read_details()?;
+
+    pcm_to_oao(&wav, writer, layout, bub_field_size, || reader.read_block(&wav))
+}
+
+/// This function writes Floaout from samples which are read by `read`.
+/// `wav` is details of the samples.
+pub(crate) fn pcm_to_oao<W, F>(wav: &Wav, writer: &mut BufWriter<W>, layout: Option<&SpeakerLayout>, bub_field_size: BubbleFieldSize, mut read: F) -> Result<Floaout>
+where
+    W: Write,
+    F: FnMut() -> Result<WavBlock>
+{
+    let layout = match layout {
+        Some(layout) => layout.clone(),
+        None => {
+            let layout = if wav.channel_mask != 0 {
+                SpeakerLayout::from_channel_mask(wav.channel_mask)
+            } else {
+                SpeakerLayout::from_channels(wav.channels)
+            };
+            layout.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Layout of Wav is unknown."))?
+        }
+    };
+    if layout.speakers.len() != wav.channels as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "Number of speakers and channels of Wav are different."));
+    }
+    let oao = Floaout {
+        bub_field_size,
+        bubbles: wav.channels,
+        blocks: wav.blocks(),
+        sampling_rate: wav.sampling_rate,
+        bits_per_sample: bits_per_sample_from_wav(wav),
+        ..Default::default()
+    };
+    let mut bubs_in_oao = Vec::with_capacity(layout.speakers.len());
+    let mut bub_fields = Vec::with_capacity(layout.speakers.len());
+    for (i, speaker) in layout.speakers.iter().enumerate() {
+        bubs_in_oao.push(
+            BubbleInFloaout {
+                bubble_id: 0,
+                name_size: name_size(&speaker.name)?,
+                name: speaker.name.clone(),
+                color: distinct_color(i, layout.speakers.len())
+            }
+        );
+        let position = speaker.position.to_field_position();
+        bub_fields.push(BubbleField::from_position(position, bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?);
+    }
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(bubs_in_oao))?;
+    for _ in 0..oao.blocks {
+        let mut bub_blocks = Vec::with_capacity(bub_fields.len());
+        // A frame has a sample of each channel.
+        for bub_field in &bub_fields {
+            let wav_block = read()?;
+            let sample = Sample::from_f64_and_bits_per_sample(wav_block.into(), oao.bits_per_sample);
+            bub_blocks.push(BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field.clone()));
+        }
+        writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+    }
+
+    Ok(oao)
+}
+
+/// This function reads mono Wav and writes Bubble which stays at the position.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::wav::wav_to_bub_at;
+/// use floaout::format::bub::Bubble;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.wav")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.bub")?);
+///     let bub = Bubble {
+///         bub_field_size: (2u8, 2u8, 1u8).into(),
+///         ..Default::default()
+///     };
+///
+///     // front center
+///     wav_to_bub_at(&mut reader, &mut writer, &bub, (0.5, 1.0, 0.5).into())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn wav_to_bub_at<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bub: &Bubble, position: FieldPosition) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek
+{
+    let bub_field = BubbleField::from_position(position, bub.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+    wav_to_bub(reader, writer, bub, |_| bub_field.clone())
+}
+
+/// This function reads mono Wav and writes Bubble which moves along the trajectory.
+/// Bubble field of each block is made from the keyframe at the time of the block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::wav::wav_to_bub_along;
+/// use floaout::format::bub::Bubble;
+/// use floaout::motion::trajectory::{Interpolation, Keyframe, Trajectory};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.wav")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.bub")?);
+///     let bub = Bubble {
+///         bub_field_size: (2u8, 2u8, 1u8).into(),
+///         ..Default::default()
+///     };
+///     let keyframe = |x: f64| Keyframe {
+///         position: (x, 1.0, 0.5).into(),
+///         ..Default::default()
+///     };
+///     // from left to right in 4 seconds
+///     let trajectory = Trajectory {
+///         keyframes: vec![(0.0, keyframe(0.0)), (4.0, keyframe(1.0))],
+///         interpolation: Interpolation::Spline
+///     };
+///
+///     wav_to_bub_along(&mut reader, &mut writer, &bub, &trajectory)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn wav_to_bub_along<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, bub: &Bubble, trajectory: &Trajectory) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek
+{
+    let wav: Wav = reader.read_details()?;
+    BubbleField::from_bub_field_size(bub.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    // Bubble field size is valid, so making Bubble field doesn't fail.
+    let bub_field = |i| trajectory.bub_field(i, wav.sampling_rate, bub.bub_field_size).unwrap_or_default();
+
+    pcm_to_bub(&wav, writer, bub, bub_field, || reader.read_block(&wav))
+}
+
+/// This enum is how Bubble field is handled when Bubble is converted to Wav.
+#[derive(Clone, Debug)]
+pub enum FieldHandling {
+    /// Bubble field is dropped, and Wav is mono.
+    Drop,
+    /// Bubble is rendered, and each channel of Wav is each speaker.
+    Render(Box<Renderer>)
+}
+
+impl FieldHandling {
+    /// This method returns number of channels of Bubble which is converted.
+    pub(crate) fn channels(&self, bub: &Bubble) -> Result<u16> {
+        match self {
+            FieldHandling::Drop => Ok(1),
+            FieldHandling::Render(renderer) => {
+                if renderer.sampling_rate() != bub.sampling_rate {
+                    return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate of Bubble and Renderer are different."));
+                }
+                Ok(renderer.layout().speakers.len() as u16)
+            }
+        }
+    }
+}
+
+/// This function reads Bubble and writes Wav block by block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::convert::wav::{bub_to_wav, FieldHandling};
+/// use floaout::format::wav::SampleFormat;
+/// use floaout::render::Renderer;
+/// use floaout::render::layout::SpeakerLayout;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.bub")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.wav")?);
+///     let renderer = Renderer::new(SpeakerLayout::stereo(), 44100)?;
+///
+///     // render to stereo 24 bits Wav
+///     bub_to_wav(&mut reader, &mut writer, FieldHandling::Render(Box::new(renderer)), SampleFormat::Int, 24)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn bub_to_wav<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, field_handling: FieldHandling, sample_format: SampleFormat, bits_per_sample: u16) -> Result<Wav>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let bub: Bubble = reader.read_details()?;
+    let wav = Wav::from_sample_format(field_handling.channels(&bub)?, bub.sampling_rate, sample_format, bits_per_sample, bub.blocks)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    writer.write_details(&wav)?;
+    bub_to_pcm(reader, &bub, field_handling, |wav_block| writer.write_block(&wav, wav_block))?;
+
+    Ok(wav)
+}
+
+/// This function reads blocks of Bubble and passes samples of each channel to `write`.
+pub(crate) fn bub_to_pcm<R, F>(reader: &mut BufReader<R>, bub: &Bubble, mut field_handling: FieldHandling, mut write: F) -> Result<()>
+where
+    R: Read,
+    F: FnMut(WavBlock) -> Result<()>
+{
+    for _ in 0..bub.blocks {
+        let bub_block: BubbleBlock = reader.read_block(bub)?;
+        match &mut field_handling {
+            FieldHandling::Drop => write(bub_block.wav_block)?,
+            FieldHandling::Render(renderer) => {
+                for n in renderer.render_block(&FloaoutBlock::from(vec![bub_block])) {
+                    write(WavBlock::from(n))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+//! Concatenation of `Floaout`
+//!
+//! Floaouts are joined end to end, and Bubbles which are same are put together.
+
+use crate::edit::silent_bub_block;
+use crate::format::{BubbleField, Sample};
+use crate::format::bub::BubbleBlock;
+use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::convert::TryInto;
+use std::f64::consts::FRAC_PI_2;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This enum is how Bubbles in different Floaouts are considered as same.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Matching {
+    /// Bubbles which have same Bubble ID are same.
+    /// Bubbles whose Bubble ID is 0 are matched by name.
+    #[default]
+    Id,
+    /// Bubbles which have same name are same.
+    Name
+}
+
+impl Matching {
+    fn matches(self, a: &BubbleInFloaout, b: &BubbleInFloaout) -> bool {
+        match self {
+            Matching::Id if a.bubble_id != 0 || b.bubble_id != 0 => a.bubble_id == b.bubble_id,
+            _ => a.name == b.name
+        }
+    }
+}
+
+/// Options of `concat`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConcatOptions {
+    /// How Bubbles are matched.
+    pub matching: Matching,
+    /// Blocks of crossfade at each boundary.
+    /// Samples are faded by equal power, and Bubble fields are interpolated linearly.
+    pub crossfade: u64
+}
+
+/// This function returns index of each Bubble of each Floaout in concatenated Floaout, and Bubbles in concatenated Floaout.
+fn unify(tables: &[BubblesInFloaout], matching: Matching) -> (Vec<Vec<usize>>, Vec<BubbleInFloaout>) {
+    let mut unified: Vec<BubbleInFloaout> = Vec::new();
+    let indexes = tables.iter().map(|table| {
+        let mut used = Vec::with_capacity(table.0.len());
+        for bub_in_oao in &table.0 {
+            // Same Bubble isn't used twice in a Floaout.
+            let index = unified.iter().enumerate()
+                .position(|(i, u)| !used.contains(&i) && matching.matches(u, bub_in_oao))
+                .unwrap_or_else(|| {
+                    unified.push(bub_in_oao.clone());
+                    unified.len() - 1
+                });
+            used.push(index);
+        }
+        used
+    }).collect();
+
+    (indexes, unified)
+}
+
+fn crossfade_field(a: &BubbleField, b: &BubbleField, t: f64) -> BubbleField {
+    let mut bub_field = a.clone();
+    for (n, &m) in bub_field.values_mut().iter_mut().flatten().flatten().zip(b.values().iter().flatten().flatten()) {
+        *n = (*n as f64 + (m as f64 - *n as f64) * t).round() as u8;
+    }
+
+    bub_field
+}
+
+/// This function reads Floaouts and writes Floaout which joins them end to end block by block.
+///
+/// Bubbles of all Floaouts are in `BubblesInFloaout` of written Floaout, and Bubbles which are not in a Floaout are silent in that part.
+/// Song ID comes from the first Floaout.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::concat::{concat, ConcatOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut readers = vec![
+///         io::BufReader::new(File::open("track1.oao")?),
+///         io::BufReader::new(File::open("track2.oao")?)
+///     ];
+///     let mut writer = io::BufWriter::new(File::create("album.oao")?);
+///     let options = ConcatOptions {
+///         // 0.5 seconds
+///         crossfade: 24000,
+///         ..Default::default()
+///     };
+///
+///     concat(&mut readers, &mut writer, &options)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn concat<R, W>(readers: &mut [BufReader<R>], writer: &mut BufWriter<W>, options: &ConcatOptions) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let mut oaos = Vec::with_capacity(readers.len());
+    let mut tables = Vec::with_capacity(readers.len());
+    for reader in readers.iter_mut() {
+        let oao: Floaout = reader.read_details()?;
+        tables.push(reader.read_bubs_details(&oao)?);
+        oaos.push(oao);
+    }
+    let first: &Floaout = oaos.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "There is no Floaout to concatenate."))?;
+    if oaos.iter().any(|oao| oao.sampling_rate != first.sampling_rate || oao.bits_per_sample != first.bits_per_sample || oao.bub_field_size != first.bub_field_size) {
+        return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate, bits per sample or Bubble field size of Floaouts are different."));
+    }
+    let crossfade = options.crossfade;
+    let last = oaos.len() - 1;
+    for (i, oao) in oaos.iter().enumerate() {
+        // Crossfades at both ends must not overlap.
+        let fades = (i != 0) as u64 + (i != last) as u64;
+        if oao.blocks < crossfade * fades {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Floaout {} is shorter than crossfades.", i)));
+        }
+    }
+    let (indexes, unified) = unify(&tables, options.matching);
+    let oao = Floaout {
+        bubbles: unified.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Floaout only accepts no more than 65535 Bubbles."))?,
+        blocks: oaos.iter().map(|oao| oao.blocks).sum::<u64>() - crossfade * last as u64,
+        ..first.clone()
+    };
+    let silence = silent_bub_block(oao.bits_per_sample, oao.bub_field_size)?;
+    // Bubble blocks in the order of concatenated Floaout
+    let unify_block = |i: usize, oao_block: FloaoutBlock| {
+        let mut bub_blocks = vec![None; unified.len()];
+        for (bub_block, &index) in oao_block.0.into_iter().zip(&indexes[i]) {
+            bub_blocks[index] = Some(bub_block);
+        }
+        bub_blocks
+    };
+
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(unified.clone()))?;
+    for i in 0..oaos.len() {
+        let start = if i == 0 { 0 } else { crossfade };
+        let end = if i == last { oaos[i].blocks } else { oaos[i].blocks - crossfade };
+        for _ in start..end {
+            let oao_block: FloaoutBlock = readers[i].read_block(&oaos[i])?;
+            let bub_blocks = unify_block(i, oao_block).into_iter().map(|bub_block| bub_block.unwrap_or_else(|| silence.clone())).collect::<Vec<BubbleBlock>>();
+            writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+        }
+        if i == last {
+            break;
+        }
+        for k in 0..crossfade {
+            let a: FloaoutBlock = readers[i].read_block(&oaos[i])?;
+            let b: FloaoutBlock = readers[i + 1].read_block(&oaos[i + 1])?;
+            let t = (k as f64 + 0.5) / crossfade as f64;
+            let (gain_a, gain_b) = ((t * FRAC_PI_2).cos(), (t * FRAC_PI_2).sin());
+            let bub_blocks = unify_block(i, a).into_iter().zip(unify_block(i + 1, b)).map(|pair| {
+                let (sample, bub_field) = match pair {
+                    (Some(a), Some(b)) => {
+                        let (a_sample, b_sample): (f64, f64) = (a.wav_block.into(), b.wav_block.into());
+                        (a_sample * gain_a + b_sample * gain_b, crossfade_field(&a.bub_field, &b.bub_field, t))
+                    },
+                    // Bubble field of Bubble which is only on one side is kept.
+                    (Some(a), None) => (Into::<f64>::into(a.wav_block) * gain_a, a.bub_field),
+                    (None, Some(b)) => (Into::<f64>::into(b.wav_block) * gain_b, b.bub_field),
+                    (None, None) => return silence.clone()
+                };
+                BubbleBlock::from_wav_block_and_bub_field(Sample::from_f64_and_bits_per_sample(sample, oao.bits_per_sample).into(), bub_field)
+            }).collect::<Vec<BubbleBlock>>();
+            writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+        }
+    }
+
+    Ok(oao)
+}
\ No newline at end of file
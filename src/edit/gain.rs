@@ -0,0 +1,228 @@
+//! Gain, mute, solo, fades and automation of each Bubble in `Floaout`
+
+use crate::format::Sample;
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This enum is shape of fade.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FadeCurve {
+    /// Amplitude changes linearly.
+    #[default]
+    Linear,
+    /// Power is constant when the same fades are crossed.
+    EqualPower,
+    /// Level changes linearly in dB from -60 dB, and starts from silence.
+    Exponential,
+    /// Amplitude changes slowly at both ends.
+    SCurve
+}
+
+impl FadeCurve {
+    /// This method returns gain of fade in at `t` which is from 0.0 to 1.0.
+    /// Gain of fade out is `gain(1.0 - t)`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::gain::FadeCurve;
+    ///
+    /// assert_eq!(FadeCurve::Linear.gain(0.25), 0.25);
+    /// assert_eq!(FadeCurve::Exponential.gain(0.0), 0.0);
+    /// assert!((FadeCurve::Exponential.gain(2.0 / 3.0) - 0.1).abs() < 1e-12);
+    /// assert_eq!(FadeCurve::SCurve.gain(1.0), 1.0);
+    /// ```
+    pub fn gain(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+            FadeCurve::Exponential => {
+                if t == 0.0 {
+                    0.0
+                } else {
+                    10f64.powf(3.0 * (t - 1.0))
+                }
+            },
+            FadeCurve::SCurve => (1.0 - (t * PI).cos()) / 2.0
+        }
+    }
+}
+
+/// Fade in or fade out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fade {
+    /// Length of fade in blocks
+    pub blocks: u64,
+    /// Shape of fade
+    pub curve: FadeCurve
+}
+
+/// Gain of a Bubble
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BubbleGain {
+    /// Static gain in dB
+    pub gain: f64,
+    /// If this is true, the Bubble is silent.
+    pub mute: bool,
+    /// If any Bubble is soloed, Bubbles which are not soloed are silent.
+    pub solo: bool,
+    /// Fade at the beginning
+    pub fade_in: Option<Fade>,
+    /// Fade at the end
+    pub fade_out: Option<Fade>,
+    /// Breakpoints of gain automation as pairs of time in seconds and gain in dB, which are sorted by time.
+    /// Gain is interpolated linearly in dB between breakpoints, and held before the first and after the last.
+    pub automation: Vec<(f64, f64)>
+}
+
+impl BubbleGain {
+    /// This method returns gain in dB of automation at the time in seconds.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::gain::BubbleGain;
+    ///
+    /// let bub_gain = BubbleGain {
+    ///     automation: vec![(1.0, 0.0), (2.0, -10.0)],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(bub_gain.automation_at(0.0), 0.0);
+    /// assert_eq!(bub_gain.automation_at(1.5), -5.0);
+    /// assert_eq!(bub_gain.automation_at(3.0), -10.0);
+    /// ```
+    pub fn automation_at(&self, time: f64) -> f64 {
+        let i = self.automation.partition_point(|&(t, _)| t <= time);
+        match (i.checked_sub(1).and_then(|i| self.automation.get(i)), self.automation.get(i)) {
+            (Some(&(t0, db0)), Some(&(t1, db1))) => db0 + (db1 - db0) * (time - t0) / (t1 - t0),
+            (Some(&(_, db)), None) | (None, Some(&(_, db))) => db,
+            (None, None) => 0.0
+        }
+    }
+
+    /// This method returns linear gain of the block, except for mute and solo.
+    pub fn gain_at(&self, block: u64, blocks: u64, sampling_rate: u32) -> f64 {
+        let db = self.gain + self.automation_at(block as f64 / sampling_rate as f64);
+        let mut gain = 10f64.powf(db / 20.0);
+        if let Some(fade) = self.fade_in {
+            if block < fade.blocks {
+                gain *= fade.curve.gain(block as f64 / fade.blocks as f64);
+            }
+        }
+        if let Some(fade) = self.fade_out {
+            // The last block is silent.
+            let rest = blocks.saturating_sub(block + 1);
+            if rest < fade.blocks {
+                gain *= fade.curve.gain(rest as f64 / fade.blocks as f64);
+            }
+        }
+
+        gain
+    }
+}
+
+/// This structure applies gain of each Bubble to Floaout.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GainStage {
+    /// Gain of each Bubble in the order of `BubblesInFloaout`
+    pub bubbles: Vec<BubbleGain>
+}
+
+impl GainStage {
+    /// This method returns stage which doesn't change any Bubble.
+    pub fn new(bubbles: usize) -> Self {
+        Self {
+            bubbles: vec![BubbleGain::default(); bubbles]
+        }
+    }
+
+    /// This method applies gain to the block whose index is `block` in the Floaout.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::gain::GainStage;
+    /// use floaout::format::bub::BubbleBlock;
+    /// use floaout::format::oao::{Floaout, FloaoutBlock};
+    ///
+    /// let oao = Floaout {
+    ///     bubbles: 2,
+    ///     blocks: 1,
+    ///     sampling_rate: 48000,
+    ///     bits_per_sample: 64,
+    ///     ..Default::default()
+    /// };
+    /// let mut stage = GainStage::new(2);
+    /// stage.bubbles[0].gain = -20.0;
+    /// stage.bubbles[1].mute = true;
+    /// let bub_block = BubbleBlock::from_wav_block_and_bub_field(1.0f64.into(), vec![vec![vec![255]]].into());
+    /// let mut oao_block = FloaoutBlock::from(vec![bub_block.clone(), bub_block]);
+    ///
+    /// stage.process_block(&oao, 0, &mut oao_block);
+    ///
+    /// let samples: Vec<f64> = oao_block.0.iter().map(|b| b.wav_block.into()).collect();
+    /// assert!((samples[0] - 0.1).abs() < 1e-12);
+    /// assert_eq!(samples[1], 0.0);
+    /// ```
+    pub fn process_block(&self, oao: &Floaout, block: u64, oao_block: &mut FloaoutBlock) {
+        let solo = self.bubbles.iter().any(|bub_gain| bub_gain.solo);
+        for (bub_block, bub_gain) in oao_block.0.iter_mut().zip(&self.bubbles) {
+            let gain = if bub_gain.mute || (solo && !bub_gain.solo) {
+                0.0
+            } else {
+                bub_gain.gain_at(block, oao.blocks, oao.sampling_rate)
+            };
+            let n: f64 = bub_block.wav_block.into();
+            bub_block.wav_block = Sample::from_f64_and_bits_per_sample(n * gain, oao.bits_per_sample).into();
+        }
+    }
+
+    /// This method reads Floaout, applies gain and writes Floaout block by block.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::io;
+    /// use std::fs::File;
+    /// use floaout::edit::gain::{Fade, FadeCurve, GainStage};
+    ///
+    /// fn main() -> io::Result<()> {
+    ///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+    ///     let mut writer = io::BufWriter::new(File::create("bar.oao")?);
+    ///     let mut stage = GainStage::new(2);
+    ///     stage.bubbles[0].fade_out = Some(Fade { blocks: 48000, curve: FadeCurve::EqualPower });
+    ///     stage.bubbles[1].automation = vec![(10.0, 0.0), (12.0, -6.0)];
+    ///
+    ///     stage.process(&mut reader, &mut writer)?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn process<R, W>(&self, reader: &mut BufReader<R>, writer: &mut BufWriter<W>) -> Result<Floaout>
+    where
+        R: Read + Seek,
+        W: Write
+    {
+        let oao: Floaout = reader.read_details()?;
+        let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+        if self.bubbles.len() != oao.bubbles as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "Number of gains and Bubbles in Floaout are different."));
+        }
+        for (i, bub_gain) in self.bubbles.iter().enumerate() {
+            let automation = &bub_gain.automation;
+            if automation.iter().any(|&(t, _)| !t.is_finite()) || automation.windows(2).any(|w| w[0].0 > w[1].0) {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("Breakpoints of automation of Bubble {} aren't sorted by finite time.", i)));
+            }
+        }
+        writer.write_details(&oao)?;
+        writer.write_bubs_details(&bubs_in_oao)?;
+        for block in 0..oao.blocks {
+            let mut oao_block: FloaoutBlock = reader.read_block(&oao)?;
+            self.process_block(&oao, block, &mut oao_block);
+            writer.write_block(&oao, &oao_block)?;
+        }
+
+        Ok(oao)
+    }
+}
\ No newline at end of file
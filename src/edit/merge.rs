@@ -0,0 +1,147 @@
+//! Merge of two `Floaout`
+//!
+//! Bubbles of both Floaouts are played at the same time, such as music and sound effects rendered separately.
+
+use crate::edit::silent_bub_block;
+use crate::format::{BubbleFieldSize, Sample};
+use crate::format::bub::BubbleBlock;
+use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::convert::TryInto;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This enum is how Bubbles which have same Bubble ID in both Floaouts are handled.
+/// Bubble ID 0 is not considered as same.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Both Bubbles are written.
+    #[default]
+    KeepBoth,
+    /// Samples are summed into the Bubble of the first Floaout, and the larger value of each cell of Bubble fields is kept.
+    Sum,
+    /// Merge fails.
+    Fail
+}
+
+/// Options of `merge`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeOptions {
+    /// Blocks which the second Floaout is delayed by.
+    pub offset: u64,
+    /// How Bubbles which have same Bubble ID are handled.
+    pub duplicate_policy: DuplicatePolicy,
+    /// If this is `Some`, every Bubble field is resampled to this size.
+    /// If this is `None`, the larger size of each axis is used.
+    pub bub_field_size: Option<BubbleFieldSize>
+}
+
+/// This function reads two Floaouts and writes Floaout which contains Bubbles of both block by block.
+///
+/// Sampling rate and bits per sample of Floaouts must be same.
+/// Song ID comes from the first Floaout, and blocks are until the end of the longer one.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::merge::{merge, DuplicatePolicy, MergeOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut music = io::BufReader::new(File::open("music.oao")?);
+///     let mut fx = io::BufReader::new(File::open("fx.oao")?);
+///     let mut writer = io::BufWriter::new(File::create("foo.oao")?);
+///     let options = MergeOptions {
+///         // 2 seconds
+///         offset: 96000,
+///         duplicate_policy: DuplicatePolicy::Sum,
+///         ..Default::default()
+///     };
+///
+///     merge(&mut music, &mut fx, &mut writer, &options)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn merge<R, W>(a: &mut BufReader<R>, b: &mut BufReader<R>, writer: &mut BufWriter<W>, options: &MergeOptions) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let a_oao: Floaout = a.read_details()?;
+    let a_bubs_in_oao: BubblesInFloaout = a.read_bubs_details(&a_oao)?;
+    let b_oao: Floaout = b.read_details()?;
+    let b_bubs_in_oao: BubblesInFloaout = b.read_bubs_details(&b_oao)?;
+    if a_oao.sampling_rate != b_oao.sampling_rate || a_oao.bits_per_sample != b_oao.bits_per_sample {
+        return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate or bits per sample of Floaouts are different."));
+    }
+    let bub_field_size = options.bub_field_size.unwrap_or_else(|| {
+        let (a_size, b_size) = (a_oao.bub_field_size, b_oao.bub_field_size);
+        (a_size.length.max(b_size.length), a_size.width.max(b_size.width), a_size.height.max(b_size.height)).into()
+    });
+
+    // Index of each Bubble of the second Floaout in merged Floaout
+    let mut bubs_in_oao: Vec<BubbleInFloaout> = a_bubs_in_oao.0.clone();
+    let mut b_indexes = Vec::with_capacity(b_bubs_in_oao.0.len());
+    for bub_in_oao in &b_bubs_in_oao.0 {
+        let duplicate = (bub_in_oao.bubble_id != 0).then(|| {
+            a_bubs_in_oao.0.iter().position(|a_bub_in_oao| a_bub_in_oao.bubble_id == bub_in_oao.bubble_id)
+        }).flatten();
+        let index = match (duplicate, options.duplicate_policy) {
+            (Some(_), DuplicatePolicy::Fail) => {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("Bubble ID {} is in both Floaouts.", bub_in_oao.bubble_id)));
+            },
+            (Some(index), DuplicatePolicy::Sum) => index,
+            _ => {
+                bubs_in_oao.push(bub_in_oao.clone());
+                bubs_in_oao.len() - 1
+            }
+        };
+        b_indexes.push(index);
+    }
+
+    let oao = Floaout {
+        bub_field_size,
+        bubbles: bubs_in_oao.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Floaout only accepts no more than 65535 Bubbles."))?,
+        blocks: a_oao.blocks.max(options.offset + b_oao.blocks),
+        ..a_oao.clone()
+    };
+    let silence = silent_bub_block(oao.bits_per_sample, bub_field_size)?;
+    let resample = |mut bub_block: BubbleBlock, from: BubbleFieldSize| -> Result<BubbleBlock> {
+        if from != bub_field_size {
+            bub_block.bub_field = bub_block.bub_field.resample(bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        }
+        Ok(bub_block)
+    };
+
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(bubs_in_oao))?;
+    for i in 0..oao.blocks {
+        let mut bub_blocks: Vec<Option<BubbleBlock>> = vec![None; oao.bubbles as usize];
+        if i < a_oao.blocks {
+            let a_block: FloaoutBlock = a.read_block(&a_oao)?;
+            for (index, bub_block) in a_block.0.into_iter().enumerate() {
+                bub_blocks[index] = Some(resample(bub_block, a_oao.bub_field_size)?);
+            }
+        }
+        if i >= options.offset && i - options.offset < b_oao.blocks {
+            let b_block: FloaoutBlock = b.read_block(&b_oao)?;
+            for (bub_block, &index) in b_block.0.into_iter().zip(&b_indexes) {
+                let bub_block = resample(bub_block, b_oao.bub_field_size)?;
+                bub_blocks[index] = Some(match bub_blocks[index].take() {
+                    Some(mut summed) => {
+                        let (n, m): (f64, f64) = (summed.wav_block.into(), bub_block.wav_block.into());
+                        summed.wav_block = Sample::from_f64_and_bits_per_sample(n + m, oao.bits_per_sample).into();
+                        summed.bub_field.max_assign(&bub_block.bub_field);
+                        summed
+                    },
+                    None => bub_block
+                });
+            }
+        }
+        let bub_blocks = bub_blocks.into_iter().map(|bub_block| bub_block.unwrap_or_else(|| silence.clone())).collect::<Vec<BubbleBlock>>();
+        writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+    }
+
+    Ok(oao)
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+//! Editing
+//!
+//! This module contains editing of `Bubble` and `Floaout` files.
+//! Every edit reads and writes block by block, so it doesn't load whole file.
+
+pub mod concat;
+pub mod gain;
+pub mod merge;
+pub mod normalize;
+pub mod segment;
+pub mod stems;
+
+use crate::format::{BubbleField, BubbleFieldSize, Sample};
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::Floaout;
+use std::convert::TryInto;
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+/// This function returns size of Bubble block in bytes.
+pub(crate) fn bub_block_size(bits_per_sample: u16, bub_field_size: BubbleFieldSize) -> Result<u64> {
+    let (length, width, height): (usize, usize, usize) = bub_field_size.try_into().map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    Ok(bits_per_sample as u64 / 8 + (length * width * height) as u64)
+}
+
+/// This function moves reader to the block whose index is `block`.
+/// `start` is position of the first block.
+pub(crate) fn seek_block<R: Read + Seek>(reader: &mut BufReader<R>, start: u64, block_size: u64, block: u64) -> Result<()> {
+    reader.seek(SeekFrom::Start(start + block * block_size))?;
+
+    Ok(())
+}
+
+/// This function returns silent Bubble block.
+pub(crate) fn silent_bub_block(bits_per_sample: u16, bub_field_size: BubbleFieldSize) -> Result<BubbleBlock> {
+    Ok(
+        BubbleBlock::from_wav_block_and_bub_field(
+            Sample::from_f64_and_bits_per_sample(0.0, bits_per_sample).into(),
+            BubbleField::from_bub_field_size(bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+        )
+    )
+}
+
+/// This function checks whether blocks of two Bubbles can be in one Bubble.
+pub(crate) fn check_bub_compatible(a: &Bubble, b: &Bubble) -> Result<()> {
+    if a.sampling_rate != b.sampling_rate || a.bits_per_sample != b.bits_per_sample || a.bub_field_size != b.bub_field_size {
+        Err(Error::new(ErrorKind::InvalidInput, "Sampling rate, bits per sample or Bubble field size of Bubbles are different."))
+    } else {
+        Ok(())
+    }
+}
+
+/// This function checks whether blocks of two Floaouts can be in one Floaout.
+pub(crate) fn check_oao_compatible(a: &Floaout, b: &Floaout) -> Result<()> {
+    if a.sampling_rate != b.sampling_rate || a.bits_per_sample != b.bits_per_sample || a.bub_field_size != b.bub_field_size {
+        Err(Error::new(ErrorKind::InvalidInput, "Sampling rate, bits per sample or Bubble field size of Floaouts are different."))
+    } else if a.bubbles != b.bubbles {
+        Err(Error::new(ErrorKind::InvalidInput, "Number of Bubbles in Floaouts are different."))
+    } else {
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,321 @@
+//! Loudness normalization of `Bubble` and `Floaout`
+//!
+//! Files are read twice. Loudness is measured first, and gain is applied second.
+
+use crate::analysis::loudness::{LoudnessMeter, TruePeak};
+use crate::format::Sample;
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::collections::VecDeque;
+use std::io::{BufReader, BufWriter, Read, Result, Seek, SeekFrom, Write};
+
+/// This enum is how gain is decided for Bubbles in Floaout.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GainMode {
+    /// All Bubbles have same gain, which is decided by the sum of samples of all Bubbles.
+    #[default]
+    Global,
+    /// Each Bubble is normalized by itself.
+    PerBubble
+}
+
+/// Lookahead limiter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limiter {
+    /// Lookahead in seconds. Gain starts to go down this time before a peak.
+    pub lookahead: f64,
+    /// Release in seconds. Gain goes back with this time constant.
+    pub release: f64
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self {
+            lookahead: 0.005,
+            release: 0.05
+        }
+    }
+}
+
+/// Options of normalization
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NormalizeOptions {
+    /// Target integrated loudness in LUFS
+    pub target: f64,
+    /// Ceiling of peak in dBTP
+    pub ceiling: f64,
+    /// How gain is decided for Bubbles in Floaout.
+    pub mode: GainMode,
+    /// If this is `None`, gain is lowered until true peak is under the ceiling, so the target may not be reached.
+    /// If this is `Some`, the target is reached and the limiter keeps true peak under the ceiling.
+    pub limiter: Option<Limiter>
+}
+
+impl Default for NormalizeOptions {
+    /// EBU R128: -23 LUFS and -1 dBTP
+    fn default() -> Self {
+        Self {
+            target: -23.0,
+            ceiling: -1.0,
+            mode: GainMode::default(),
+            limiter: None
+        }
+    }
+}
+
+impl NormalizeOptions {
+    /// This method returns limiters of channels, or a limiter which is linked to all channels.
+    fn limiters(&self, channels: usize, sampling_rate: u32) -> Vec<LimiterState> {
+        match self.limiter {
+            Some(limiter) => (0..channels).map(|_| LimiterState::new(&limiter, self.ceiling, sampling_rate)).collect(),
+            None => Vec::new()
+        }
+    }
+
+    /// This method returns gain in dB from measured loudness.
+    /// Silence isn't changed.
+    fn gain(&self, meter: &LoudnessMeter) -> f64 {
+        let integrated = meter.integrated();
+        if !integrated.is_finite() {
+            return 0.0;
+        }
+        let gain = self.target - integrated;
+        match self.limiter {
+            Some(_) => gain,
+            None => gain.min(self.ceiling - meter.true_peak())
+        }
+    }
+}
+
+/// State of lookahead limiter.
+/// Gain is the moving average of the release of the minimum required gain in the lookahead,
+/// so gain is always under the required gain of the delayed sample.
+/// Required gain is decided by true peak, which is delayed by the oversampling detector.
+struct LimiterState {
+    true_peak: TruePeak,
+    // Interpolated peak before the sample
+    last: f64,
+    lookahead: usize,
+    ceiling: f64,
+    release: f64,
+    index: usize,
+    // Increasing required gains with their index
+    mins: VecDeque<(usize, f64)>,
+    released: f64,
+    ring: Vec<f64>,
+    pos: usize,
+    sum: f64
+}
+
+impl LimiterState {
+    fn new(limiter: &Limiter, ceiling: f64, sampling_rate: u32) -> Self {
+        let lookahead = (limiter.lookahead * sampling_rate as f64).round().max(0.0) as usize;
+        let release = (limiter.release * sampling_rate as f64).max(1.0);
+        Self {
+            true_peak: TruePeak::new(sampling_rate),
+            last: 0.0,
+            lookahead,
+            ceiling: 10f64.powf(ceiling / 20.0),
+            release: 1.0 - (-1.0 / release).exp(),
+            index: 0,
+            mins: VecDeque::new(),
+            released: 1.0,
+            ring: vec![1.0; lookahead + 1],
+            pos: 0,
+            sum: (lookahead + 1) as f64
+        }
+    }
+
+    /// This method returns how many samples the gain is later than the sample.
+    fn delay(&self) -> usize {
+        self.lookahead + TruePeak::DELAY
+    }
+
+    /// This method takes a sample and returns gain of the sample which was taken `delay()` samples ago.
+    fn gain(&mut self, sample: f64) -> f64 {
+        // Peaks between the previous sample and the next sample
+        let interpolated = self.true_peak.interpolate(sample);
+        let peak = interpolated.max(self.last);
+        self.last = interpolated;
+        let required = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+        while self.mins.back().is_some_and(|&(_, g)| g >= required) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((self.index, required));
+        while self.mins.front().is_some_and(|&(i, _)| i + self.lookahead < self.index) {
+            self.mins.pop_front();
+        }
+        self.index += 1;
+        let hold = self.mins.front().map_or(1.0, |&(_, g)| g);
+        self.released = hold.min(self.released + (1.0 - self.released) * self.release);
+        self.sum += self.released - self.ring[self.pos];
+        self.ring[self.pos] = self.released;
+        self.pos = (self.pos + 1) % self.ring.len();
+
+        (self.sum / self.ring.len() as f64).min(1.0)
+    }
+}
+
+/// This function scales samples of each channel by the gain in dB, and writes them through the limiters.
+/// If there is one limiter, it's decided by the sum of all channels. If there is no limiter, samples are only scaled.
+fn apply<F, G>(blocks: u64, bits_per_sample: u16, gains: &[f64], mut limiters: Vec<LimiterState>, mut read: F, mut write: G) -> Result<()>
+where
+    F: FnMut() -> Result<Vec<BubbleBlock>>,
+    G: FnMut(Vec<BubbleBlock>) -> Result<()>
+{
+    let gains: Vec<f64> = gains.iter().map(|db| 10f64.powf(db / 20.0)).collect();
+    let scale = |mut bub_blocks: Vec<BubbleBlock>, gains: &[f64]| {
+        for (bub_block, gain) in bub_blocks.iter_mut().zip(gains) {
+            let n: f64 = bub_block.wav_block.into();
+            bub_block.wav_block = Sample::from_f64_and_bits_per_sample(n * gain, bits_per_sample).into();
+        }
+        bub_blocks
+    };
+    if limiters.is_empty() {
+        for _ in 0..blocks {
+            write(scale(read()?, &gains))?;
+        }
+        return Ok(());
+    }
+    let linked = limiters.len() == 1;
+    let delay = limiters.first().map_or(0, |limiter| limiter.delay()) as u64;
+    let mut delayed: VecDeque<Vec<BubbleBlock>> = VecDeque::with_capacity(delay as usize + 1);
+    for i in 0..blocks + delay {
+        let samples: Vec<f64> = if i < blocks {
+            let bub_blocks = scale(read()?, &gains);
+            let samples = bub_blocks.iter().map(|bub_block| bub_block.wav_block.into());
+            let samples = if linked { vec![samples.sum()] } else { samples.collect() };
+            delayed.push_back(bub_blocks);
+            samples
+        } else {
+            vec![0.0; limiters.len()]
+        };
+        let limits: Vec<f64> = limiters.iter_mut().zip(samples).map(|(limiter, n)| limiter.gain(n)).collect();
+        if i >= delay {
+            if let Some(bub_blocks) = delayed.pop_front() {
+                let limits = if linked { vec![limits[0]; bub_blocks.len()] } else { limits };
+                write(scale(bub_blocks, &limits))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// This function reads Bubble twice and writes Bubble whose loudness is the target.
+/// This returns written Bubble and applied gain in dB.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::normalize::{normalize_bub, Limiter, NormalizeOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.bub")?);
+///     let mut writer = io::BufWriter::new(File::create("bar.bub")?);
+///     let options = NormalizeOptions {
+///         target: -16.0,
+///         limiter: Some(Limiter::default()),
+///         ..Default::default()
+///     };
+///
+///     let (_, gain) = normalize_bub(&mut reader, &mut writer, &options)?;
+///     println!("{} dB", gain);
+///
+///     Ok(())
+/// }
+/// ```
+pub fn normalize_bub<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, options: &NormalizeOptions) -> Result<(Bubble, f64)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let start = reader.stream_position()?;
+    let bub: Bubble = reader.read_details()?;
+    let mut meter = LoudnessMeter::mono(bub.sampling_rate)?;
+    for _ in 0..bub.blocks {
+        let bub_block: BubbleBlock = reader.read_block(&bub)?;
+        meter.add_frame(&[bub_block.wav_block.into()]);
+    }
+    let gain = options.gain(&meter);
+
+    reader.seek(SeekFrom::Start(start))?;
+    let bub: Bubble = reader.read_details()?;
+    writer.write_details(&bub)?;
+    apply(
+        bub.blocks, bub.bits_per_sample, &[gain], options.limiters(1, bub.sampling_rate),
+        || Ok(vec![reader.read_block(&bub)?]),
+        |bub_blocks| bub_blocks.iter().try_for_each(|bub_block| writer.write_block(&bub, bub_block))
+    )?;
+
+    Ok((bub, gain))
+}
+
+/// This function reads Floaout twice and writes Floaout whose loudness is the target.
+/// This returns written Floaout and applied gain in dB of each Bubble.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::normalize::{normalize_oao, GainMode, NormalizeOptions};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///     let mut writer = io::BufWriter::new(File::create("bar.oao")?);
+///     let options = NormalizeOptions {
+///         mode: GainMode::PerBubble,
+///         ..Default::default()
+///     };
+///
+///     normalize_oao(&mut reader, &mut writer, &options)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn normalize_oao<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, options: &NormalizeOptions) -> Result<(Floaout, Vec<f64>)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let start = reader.stream_position()?;
+    let oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    let linked = options.mode == GainMode::Global;
+    let mut meters = vec![LoudnessMeter::mono(oao.sampling_rate)?; if linked { 1 } else { oao.bubbles as usize }];
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        let samples = oao_block.0.iter().map(|bub_block| bub_block.wav_block.into());
+        if linked {
+            meters[0].add_frame(&[samples.sum()]);
+        } else {
+            for (meter, n) in meters.iter_mut().zip(samples) {
+                meter.add_frame(&[n]);
+            }
+        }
+    }
+    let gains: Vec<f64> = if linked {
+        vec![options.gain(&meters[0]); oao.bubbles as usize]
+    } else {
+        meters.iter().map(|meter| options.gain(meter)).collect()
+    };
+
+    reader.seek(SeekFrom::Start(start))?;
+    let oao: Floaout = reader.read_details()?;
+    let bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    apply(
+        oao.blocks, oao.bits_per_sample, &gains, options.limiters(meters.len(), oao.sampling_rate),
+        || {
+            let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+            Ok(oao_block.0)
+        },
+        |bub_blocks| writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))
+    )?;
+
+    Ok((oao, gains))
+}
\ No newline at end of file
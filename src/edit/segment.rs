@@ -0,0 +1,242 @@
+//! Trim, cut, insert and splice blocks
+//!
+//! An edit is a list of segments which are written in order.
+//! `blocks` and `overall` are the only details which depend on blocks.
+//! CRC-32C of Floaout is computed again by the writer, so edited Floaout has valid checksums.
+
+use crate::convert::rewrite_bub_details;
+use crate::edit::{bub_block_size, check_bub_compatible, check_oao_compatible, seek_block, silent_bub_block};
+use crate::format::BubbleField;
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+use std::ops::Range;
+
+/// This enum is a part of edited file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Segment {
+    /// Blocks of the input in the range.
+    /// End is clamped to blocks of the input, so `start..u64::MAX` means blocks from `start` to the end.
+    Blocks(usize, Range<u64>),
+    /// Silent blocks
+    Silence(u64)
+}
+
+impl Segment {
+    /// This method returns segments which extract blocks in the range of input 0.
+    pub fn trim(range: Range<u64>) -> Vec<Segment> {
+        vec![Segment::Blocks(0, range)]
+    }
+
+    /// This method returns segments which delete blocks in the range of input 0.
+    /// End is clamped to start, so nothing is deleted if end is before start.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::segment::Segment;
+    ///
+    /// assert_eq!(Segment::cut(5..3), vec![Segment::Blocks(0, 0..5), Segment::Blocks(0, 5..u64::MAX)]);
+    /// ```
+    pub fn cut(range: Range<u64>) -> Vec<Segment> {
+        let end = range.end.max(range.start);
+        vec![Segment::Blocks(0, 0..range.start), Segment::Blocks(0, end..u64::MAX)]
+    }
+
+    /// This method returns segments which insert silent blocks at the block of input 0.
+    pub fn insert_silence(at: u64, blocks: u64) -> Vec<Segment> {
+        vec![Segment::Blocks(0, 0..at), Segment::Silence(blocks), Segment::Blocks(0, at..u64::MAX)]
+    }
+
+    /// This method returns segments which insert all blocks of input 1 at the block of input 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::segment::Segment;
+    ///
+    /// assert_eq!(
+    ///     Segment::splice(3),
+    ///     vec![Segment::Blocks(0, 0..3), Segment::Blocks(1, 0..u64::MAX), Segment::Blocks(0, 3..u64::MAX)]
+    /// );
+    /// ```
+    pub fn splice(at: u64) -> Vec<Segment> {
+        vec![Segment::Blocks(0, 0..at), Segment::Blocks(1, 0..u64::MAX), Segment::Blocks(0, at..u64::MAX)]
+    }
+}
+
+/// Segment whose range is clamped.
+enum Part {
+    Blocks(usize, u64, u64),
+    Silence(u64)
+}
+
+/// This function clamps segments by blocks of each input, and returns them with total blocks.
+fn parts(segments: &[Segment], blocks: &[u64]) -> Result<(Vec<Part>, u64)> {
+    let mut parts = Vec::with_capacity(segments.len());
+    let mut total = 0u64;
+    for segment in segments {
+        let part = match segment {
+            Segment::Blocks(input, range) => {
+                let input_blocks = *blocks.get(*input).ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("There is no input {}.", input)))?;
+                let end = range.end.min(input_blocks);
+                if range.start > end {
+                    return Err(Error::new(ErrorKind::InvalidInput, format!("Range {}..{} is out of input {}.", range.start, range.end, input)));
+                }
+                total += end - range.start;
+                Part::Blocks(*input, range.start, end)
+            },
+            Segment::Silence(n) => {
+                total += n;
+                Part::Silence(*n)
+            }
+        };
+        parts.push(part);
+    }
+
+    Ok((parts, total))
+}
+
+/// This function reads Bubbles and writes Bubble made of the segments block by block.
+/// Details except for `blocks` and `overall` come from input 0.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::segment::{edit_bub, Segment};
+///
+/// fn main() -> io::Result<()> {
+///     let mut readers = vec![
+///         io::BufReader::new(File::open("foo.bub")?),
+///         io::BufReader::new(File::open("bar.bub")?)
+///     ];
+///     let mut writer = io::BufWriter::new(File::create("foobar.bub")?);
+///
+///     // bar.bub is inserted at block 44100 of foo.bub
+///     edit_bub(&mut readers, &mut writer, &Segment::splice(44100))?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn edit_bub<R, W>(readers: &mut [BufReader<R>], writer: &mut BufWriter<W>, segments: &[Segment]) -> Result<Bubble>
+where
+    R: Read + Seek,
+    W: Write + Seek
+{
+    let mut bubs = Vec::with_capacity(readers.len());
+    let mut starts = Vec::with_capacity(readers.len());
+    for reader in readers.iter_mut() {
+        let bub: Bubble = reader.read_details()?;
+        if let Some(first) = bubs.first() {
+            check_bub_compatible(first, &bub)?;
+        }
+        bubs.push(bub);
+        starts.push(reader.stream_position()?);
+    }
+    let first = bubs.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "There is no Bubble to edit."))?;
+    let blocks: Vec<u64> = bubs.iter().map(|bub| bub.blocks).collect();
+    let (parts, total) = parts(segments, &blocks)?;
+    let mut bub = Bubble {
+        blocks: total,
+        overall: BubbleField::from_bub_field_size(first.bub_field_size).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+        ..first.clone()
+    };
+    let block_size = bub_block_size(bub.bits_per_sample, bub.bub_field_size)?;
+    let silence = silent_bub_block(bub.bits_per_sample, bub.bub_field_size)?;
+    let start = writer.stream_position()?;
+    writer.write_details(&bub)?;
+    for part in parts {
+        match part {
+            Part::Blocks(input, from, to) => {
+                let reader = &mut readers[input];
+                seek_block(reader, starts[input], block_size, from)?;
+                for _ in from..to {
+                    let bub_block: BubbleBlock = reader.read_block(&bubs[input])?;
+                    bub.overall.max_assign(&bub_block.bub_field);
+                    writer.write_block(&bub, &bub_block)?;
+                }
+            },
+            Part::Silence(n) => {
+                for _ in 0..n {
+                    writer.write_block(&bub, &silence)?;
+                }
+            }
+        }
+    }
+    rewrite_bub_details(writer, start, &bub)?;
+
+    Ok(bub)
+}
+
+/// This function reads Floaouts and writes Floaout made of the segments block by block.
+/// Details except for `blocks`, and Bubbles in Floaout come from input 0.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::segment::{edit_oao, Segment};
+///
+/// fn main() -> io::Result<()> {
+///     let mut readers = vec![io::BufReader::new(File::open("foo.oao")?)];
+///     let mut writer = io::BufWriter::new(File::create("bar.oao")?);
+///
+///     // delete from 1 second to 2 seconds
+///     edit_oao(&mut readers, &mut writer, &Segment::cut(48000..96000))?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn edit_oao<R, W>(readers: &mut [BufReader<R>], writer: &mut BufWriter<W>, segments: &[Segment]) -> Result<Floaout>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let mut oaos = Vec::with_capacity(readers.len());
+    let mut starts = Vec::with_capacity(readers.len());
+    let mut bubs_in_oao = BubblesInFloaout::default();
+    for (i, reader) in readers.iter_mut().enumerate() {
+        let oao: Floaout = reader.read_details()?;
+        let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+        if let Some(first) = oaos.first() {
+            check_oao_compatible(first, &oao)?;
+        }
+        if i == 0 {
+            bubs_in_oao = read_bubs_in_oao;
+        }
+        oaos.push(oao);
+        starts.push(reader.stream_position()?);
+    }
+    let first = oaos.first().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "There is no Floaout to edit."))?;
+    let blocks: Vec<u64> = oaos.iter().map(|oao| oao.blocks).collect();
+    let (parts, total) = parts(segments, &blocks)?;
+    let oao = Floaout {
+        blocks: total,
+        ..first.clone()
+    };
+    // Each block ends with CRC-32C.
+    let block_size = bub_block_size(oao.bits_per_sample, oao.bub_field_size)? * oao.bubbles as u64 + 4;
+    let silence = FloaoutBlock::from(vec![silent_bub_block(oao.bits_per_sample, oao.bub_field_size)?; oao.bubbles as usize]);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    for part in parts {
+        match part {
+            Part::Blocks(input, from, to) => {
+                let reader = &mut readers[input];
+                seek_block(reader, starts[input], block_size, from)?;
+                for _ in from..to {
+                    let oao_block: FloaoutBlock = reader.read_block(&oaos[input])?;
+                    writer.write_block(&oao, &oao_block)?;
+                }
+            },
+            Part::Silence(n) => {
+                for _ in 0..n {
+                    writer.write_block(&oao, &silence)?;
+                }
+            }
+        }
+    }
+
+    Ok(oao)
+}
\ No newline at end of file
@@ -0,0 +1,117 @@
+//! Remove, reorder and rename Bubbles in `Floaout`
+//!
+//! `bubbles` and name sizes are the only details which depend on Bubbles in Floaout.
+//! CRC-32C of details, Bubbles in Floaout and blocks is computed again by the writer.
+
+use crate::convert::name_size;
+use crate::format::Color;
+use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteBubsIn, WriteFmt};
+use std::convert::TryInto;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// A Bubble of rewritten Floaout
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stem {
+    /// Index of the Bubble in the input Floaout
+    pub source: usize,
+    /// If this is `Some`, the Bubble is renamed.
+    pub name: Option<String>,
+    /// If this is `Some`, color of the Bubble is changed.
+    pub color: Option<Color>
+}
+
+impl Stem {
+    /// This method returns stem which keeps the Bubble as it is.
+    pub fn new(source: usize) -> Self {
+        Self {
+            source,
+            ..Default::default()
+        }
+    }
+
+    /// This method returns stems which keep all Bubbles except for `removed` in order.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::edit::stems::Stem;
+    ///
+    /// assert_eq!(Stem::remove(4, &[1, 2]), vec![Stem::new(0), Stem::new(3)]);
+    /// ```
+    pub fn remove(bubbles: usize, removed: &[usize]) -> Vec<Stem> {
+        (0..bubbles).filter(|i| !removed.contains(i)).map(Stem::new).collect()
+    }
+
+    /// This method returns stems which reorder Bubbles.
+    /// `order` is indexes of the input Floaout in order of rewritten Floaout.
+    pub fn reorder(order: &[usize]) -> Vec<Stem> {
+        order.iter().copied().map(Stem::new).collect()
+    }
+}
+
+/// This function reads Floaout and writes Floaout which contains the stems block by block.
+///
+/// Only one block is in memory at a time.
+/// A Bubble of the input can be in stems more than once, and Bubble ID is kept.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::edit::stems::{rewrite_stems, Stem};
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///     let mut writer = io::BufWriter::new(File::create("bar.oao")?);
+///     // Bubble 2 is the first and renamed, and Bubble 1 is removed.
+///     let stems = vec![
+///         Stem {
+///             source: 2,
+///             name: Some("Lead Vocal".into()),
+///             color: Some((255, 0, 0).into())
+///         },
+///         Stem::new(0)
+///     ];
+///
+///     rewrite_stems(&mut reader, &mut writer, &stems)?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn rewrite_stems<R, W>(reader: &mut BufReader<R>, writer: &mut BufWriter<W>, stems: &[Stem]) -> Result<(Floaout, BubblesInFloaout)>
+where
+    R: Read + Seek,
+    W: Write
+{
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let mut bubs_in_oao = Vec::with_capacity(stems.len());
+    for stem in stems {
+        let bub_in_oao = read_bubs_in_oao.0.get(stem.source).ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("There is no Bubble {}.", stem.source)))?;
+        let name = stem.name.clone().unwrap_or_else(|| bub_in_oao.name.clone());
+        bubs_in_oao.push(
+            BubbleInFloaout {
+                bubble_id: bub_in_oao.bubble_id,
+                name_size: name_size(&name)?,
+                name,
+                color: stem.color.unwrap_or(bub_in_oao.color)
+            }
+        );
+    }
+    let oao = Floaout {
+        bubbles: stems.len().try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Floaout only accepts no more than 65535 Bubbles."))?,
+        ..read_oao.clone()
+    };
+    let bubs_in_oao = BubblesInFloaout::from(bubs_in_oao);
+
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    for _ in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&read_oao)?;
+        let bub_blocks = stems.iter().map(|stem| oao_block.0[stem.source].clone()).collect::<Vec<_>>();
+        writer.write_block(&oao, &FloaoutBlock::from(bub_blocks))?;
+    }
+
+    Ok((oao, bubs_in_oao))
+}
\ No newline at end of file
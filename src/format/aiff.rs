@@ -0,0 +1,270 @@
+//! Structures related to `Aiff`
+//!
+//! AIFF is a standard audio format which stores samples in big-endian byte order.
+//! AIFF-C is an extension of AIFF which has compression type, such as IEEE float.
+
+use crate::format::Sample;
+use crate::format::wav::{SampleFormat, Wav};
+use std::fmt;
+
+/// Details of the AIFF or AIFF-C file.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Aiff {
+    // Form Chunk
+    /// Form Size is (file size - 8).
+    pub form_size: u32,
+    /// If this is true, form type is "AIFC". Otherwise it's "AIFF".
+    pub aifc: bool,
+    // Common Chunk
+    /// Channels
+    pub channels: u16,
+    /// Number of sample frames
+    pub sample_frames: u32,
+    /// Bits Per Sample
+    pub bits_per_sample: u16,
+    /// Sampling Rate
+    /// It's 80 bits extended float in a file, and rounded to integer.
+    pub sampling_rate: u32,
+    /// Compression Type of AIFF-C
+    /// It's always `None` in AIFF.
+    pub compression: Compression,
+    // Sound Data Chunk
+    /// Size of samples in Sound Data Chunk
+    pub data_size: u32,
+    // Other Chunk
+    /// Other Size is not exsists in a file.
+    /// However, this will help erasing other chunk.
+    pub other_size: u32
+}
+
+impl Aiff {
+    /// This method casts format to `Aiff` which has no other chunk.
+    /// If compression is not `None`, it's AIFF-C.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::aiff::{Aiff, Compression};
+    ///
+    /// let aiff = Aiff::from_format(2, 44100, Compression::None, 24, 4).unwrap();
+    /// assert!(!aiff.aifc);
+    /// assert_eq!(aiff.data_size, 24);
+    ///
+    /// let aiff = Aiff::from_format(1, 48000, Compression::Float32, 32, 4).unwrap();
+    /// assert!(aiff.aifc);
+    ///
+    /// assert!(Aiff::from_format(1, 48000, Compression::Float64, 32, 4).is_err());
+    /// ```
+    pub fn from_format(channels: u16, sampling_rate: u32, compression: Compression, bits_per_sample: u16, sample_frames: u32) -> Result<Self, &'static str> {
+        match (compression, bits_per_sample) {
+            (Compression::None, 8) | (Compression::None, 16) | (Compression::None, 24) | (Compression::None, 32) => (),
+            (Compression::Sowt, 16) | (Compression::Sowt, 24) | (Compression::Sowt, 32) => (),
+            (Compression::Float32, 32) | (Compression::Float64, 64) => (),
+            _ => return Err("Bits per sample doesn't match compression type.")
+        }
+        let data_size = (bits_per_sample / 8) as u64 * channels as u64 * sample_frames as u64;
+        let mut aiff = Self {
+            form_size: 0,
+            aifc: compression != Compression::None,
+            channels,
+            sample_frames,
+            bits_per_sample,
+            sampling_rate,
+            compression,
+            data_size: 0,
+            other_size: 0
+        };
+        // FORM type, COMM, FVER and SSND
+        let form_size = 4 + 8 + aiff.comm_size() as u64 + if aiff.aifc { 12 } else { 0 } + 16 + data_size + data_size % 2;
+        if form_size > u32::MAX as u64 {
+            Err("Aiff form size only accepts no more than the largest value of u32.")
+        } else {
+            aiff.form_size = form_size as u32;
+            aiff.data_size = data_size as u32;
+            Ok(aiff)
+        }
+    }
+
+    /// This method returns size of Common Chunk without its header.
+    pub fn comm_size(&self) -> u32 {
+        if self.aifc {
+            // Compression name is Pascal string whose size is even.
+            let name = self.compression.name().len() as u32;
+            22 + name + 1 + (name + 1) % 2
+        } else {
+            18
+        }
+    }
+
+    /// This method returns sample format.
+    pub fn sample_format(&self) -> SampleFormat {
+        match self.compression {
+            Compression::None | Compression::Sowt => SampleFormat::Int,
+            Compression::Float32 | Compression::Float64 => SampleFormat::Float
+        }
+    }
+
+    /// This method returns `Wav` which has same format.
+    /// It's used to convert AIFF in the same way as Wav.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::aiff::{Aiff, Compression};
+    /// use floaout::format::wav::SampleFormat;
+    ///
+    /// let aiff = Aiff::from_format(2, 44100, Compression::None, 16, 4).unwrap();
+    /// let wav = aiff.to_wav().unwrap();
+    ///
+    /// assert_eq!(wav.sample_format(), Some(SampleFormat::Int));
+    /// assert_eq!(wav.blocks(), 4);
+    /// ```
+    pub fn to_wav(&self) -> Result<Wav, &'static str> {
+        Wav::from_sample_format(self.channels, self.sampling_rate, self.sample_format(), self.bits_per_sample, self.sample_frames as u64)
+    }
+}
+
+/// This enum is compression type of AIFF-C.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Compression {
+    /// Big-endian integer ("NONE")
+    #[default]
+    None,
+    /// Little-endian integer ("sowt")
+    Sowt,
+    /// Big-endian 32 bits IEEE float ("fl32")
+    Float32,
+    /// Big-endian 64 bits IEEE float ("fl64")
+    Float64
+}
+
+impl Compression {
+    /// This method returns compression type from 4 characters.
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "NONE" => Some(Compression::None),
+            "sowt" => Some(Compression::Sowt),
+            "fl32" | "FL32" => Some(Compression::Float32),
+            "fl64" | "FL64" => Some(Compression::Float64),
+            _ => None
+        }
+    }
+
+    /// This method returns 4 characters of compression type.
+    pub fn id(self) -> &'static str {
+        match self {
+            Compression::None => "NONE",
+            Compression::Sowt => "sowt",
+            Compression::Float32 => "fl32",
+            Compression::Float64 => "fl64"
+        }
+    }
+
+    /// This method returns name of compression type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "not compressed",
+            Compression::Sowt => "",
+            Compression::Float32 => "32-bit floating point",
+            Compression::Float64 => "64-bit floating point"
+        }
+    }
+}
+
+/// This function converts 80 bits extended float to integer.
+///
+/// # Examples
+/// ```
+/// use floaout::format::aiff::{extended_to_u32, u32_to_extended};
+///
+/// let bytes = u32_to_extended(44100);
+///
+/// assert_eq!(bytes, [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+/// assert_eq!(extended_to_u32(bytes), 44100);
+/// ```
+pub fn extended_to_u32(bytes: [u8; 10]) -> u32 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff) as i32;
+    let mut mantissa = [0; 8];
+    mantissa.copy_from_slice(&bytes[2..]);
+    let mantissa = u64::from_be_bytes(mantissa);
+    // Negative value is 0.
+    if bytes[0] & 0x80 != 0 || mantissa == 0 {
+        return 0;
+    }
+    let n = mantissa as f64 * 2f64.powi(exponent - 16383 - 63);
+
+    n.round().min(u32::MAX as f64) as u32
+}
+
+/// This function converts integer to 80 bits extended float.
+pub fn u32_to_extended(n: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if n != 0 {
+        let shift = (n as u64).leading_zeros();
+        let exponent = (16383 + 63 - shift) as u16;
+        bytes[..2].copy_from_slice(&exponent.to_be_bytes());
+        bytes[2..].copy_from_slice(&((n as u64) << shift).to_be_bytes());
+    }
+
+    bytes
+}
+
+impl fmt::Display for Aiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(
+            f,
+            "\n File Size( - 8 ): {} Bytes\n        Form Type: {}\n         Channels: {} Channels\n    Sample Frames: {}\n  Bits Per Sample: {} Bits\n    Sampling Rate: {} Hz\n      Compression: {}\n  Sound Data Size: {} Bytes\n Other Chunk Size: {} Bytes\n",
+            self.form_size,
+            if self.aifc { "AIFC" } else { "AIFF" },
+            self.channels,
+            self.sample_frames,
+            self.bits_per_sample,
+            self.sampling_rate,
+            self.compression.id(),
+            self.data_size,
+            self.other_size
+        )
+    }
+}
+
+/// Block of AIFF
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct AiffBlock(pub Sample);
+
+impl From<f32> for AiffBlock {
+    fn from(sample: f32) -> Self {
+        AiffBlock(Sample::Float32(sample))
+    }
+}
+
+impl From<f64> for AiffBlock {
+    fn from(sample: f64) -> Self {
+        AiffBlock(Sample::Float64(sample))
+    }
+}
+
+impl From<Sample> for AiffBlock {
+    fn from(sample: Sample) -> Self {
+        AiffBlock(sample)
+    }
+}
+
+impl From<AiffBlock> for Sample {
+    fn from(aiff_block: AiffBlock) -> Self {
+        aiff_block.0
+    }
+}
+
+/// Blocks of AIFF
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct AiffBlocks(pub Box<[AiffBlock]>);
+
+impl From<Box<[AiffBlock]>> for AiffBlocks {
+    fn from(buf: Box<[AiffBlock]>) -> Self {
+        AiffBlocks(buf)
+    }
+}
+
+impl From<AiffBlocks> for Box<[AiffBlock]> {
+    fn from(aiff_blocks: AiffBlocks) -> Self {
+        aiff_blocks.0
+    }
+}
\ No newline at end of file
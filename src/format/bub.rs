@@ -110,6 +110,7 @@ impl TryInto<Wav> for Bubble {
                     data_rate: self.sampling_rate * (bytes_per_sample * 1) as u32,
                     data_block_size: bytes_per_sample * 1,
                     bits_per_sample: self.bits_per_sample,
+                    channel_mask: 0,
                     data_size: (riff_size - 36) as u32,
                     other_size: 0
                 }
@@ -1,7 +1,8 @@
 //! Format definitions
 //! 
-//! This module contains structures related to `Blower`, `Bubble`, `Floaout` and `Wav`.
+//! This module contains structures related to `Aiff`, `Blower`, `Bubble`, `Floaout` and `Wav`.
 
+pub mod aiff;
 pub mod bub;
 pub mod oao;
 pub mod wav;
@@ -24,6 +25,165 @@ impl Into<Vec<Vec<Vec<u8>>>> for BubbleField {
     }
 }
 
+impl BubbleField {
+    /// This method returns Bubble field whose values are all 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// let bub_field = BubbleField::from_bub_field_size((1u8, 0u8, 0u8).into()).unwrap();
+    ///
+    /// assert_eq!(bub_field, vec![vec![vec![0]], vec![vec![0]]].into());
+    /// ```
+    pub fn from_bub_field_size(bub_field_size: BubbleFieldSize) -> Result<Self, &'static str> {
+        let (length, width, height) = bub_field_size.try_into()?;
+
+        Ok(BubbleField(vec![vec![vec![0; height]; width]; length]))
+    }
+
+    /// This method returns Bubble field whose value is 255 only at the cell which includes the position.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// let bub_field = BubbleField::from_position((1.0, 0.0, 0.0).into(), (0u8, 1u8, 0u8).into()).unwrap();
+    ///
+    /// assert_eq!(bub_field, vec![vec![vec![0], vec![255]]].into());
+    /// ```
+    pub fn from_position(position: FieldPosition, bub_field_size: BubbleFieldSize) -> Result<Self, &'static str> {
+        let mut bub_field = Self::from_bub_field_size(bub_field_size)?;
+        let (l, w, h) = position.cell(bub_field_size)?;
+        bub_field.0[l][w][h] = 255;
+
+        Ok(bub_field)
+    }
+
+    /// This method takes the larger value of each cell. `overall` of Bubble is made by this.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// let mut overall: BubbleField = vec![vec![vec![0], vec![5]]].into();
+    /// overall.max_assign(&vec![vec![vec![3], vec![1]]].into());
+    ///
+    /// assert_eq!(overall, vec![vec![vec![3], vec![5]]].into());
+    /// ```
+    pub fn max_assign(&mut self, other: &BubbleField) {
+        for (a, b) in self.0.iter_mut().flatten().flatten().zip(other.0.iter().flatten().flatten()) {
+            *a = (*a).max(*b);
+        }
+    }
+
+    /// This method returns values indexed by `[length][width][height]`.
+    pub fn values(&self) -> &Vec<Vec<Vec<u8>>> {
+        &self.0
+    }
+
+    /// This method returns mutable values indexed by `[length][width][height]`.
+    pub fn values_mut(&mut self) -> &mut Vec<Vec<Vec<u8>>> {
+        &mut self.0
+    }
+
+    /// This method returns the largest value in Bubble field.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// let bub_field: BubbleField = vec![vec![vec![0], vec![2]], vec![vec![1], vec![3]]].into();
+    ///
+    /// assert_eq!(bub_field.peak(), 3);
+    /// ```
+    pub fn peak(&self) -> u8 {
+        self.0.iter().flatten().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// This method returns center of Bubble field weighted by each value.
+    /// If all values are 0, this returns `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// // length 2, width 2, height 1
+    /// let bub_field: BubbleField = vec![vec![vec![0], vec![0]], vec![vec![255], vec![255]]].into();
+    ///
+    /// assert_eq!(bub_field.centroid(), Some((0.5, 0.75, 0.5).into()));
+    /// ```
+    pub fn centroid(&self) -> Option<FieldPosition> {
+        let length = self.0.len() as f64;
+        let mut sum = (0.0, 0.0, 0.0, 0.0);
+        for (l, plane) in self.0.iter().enumerate() {
+            let width = plane.len() as f64;
+            for (w, column) in plane.iter().enumerate() {
+                let height = column.len() as f64;
+                for (h, &n) in column.iter().enumerate() {
+                    let n = n as f64;
+                    sum.0 += n * (w as f64 + 0.5) / width;
+                    sum.1 += n * (l as f64 + 0.5) / length;
+                    sum.2 += n * (h as f64 + 0.5) / height;
+                    sum.3 += n;
+                }
+            }
+        }
+        if sum.3 == 0.0 {
+            None
+        } else {
+            Some((sum.0 / sum.3, sum.1 / sum.3, sum.2 / sum.3).into())
+        }
+    }
+
+    /// This method returns Bubble field which has other size.
+    /// Each cell takes the largest value of cells it covers, so a Bubble isn't lost by shrinking.
+    /// Bubble field which has no cell, such as `BubbleField::default()`, becomes all 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    ///
+    /// let bub_field: BubbleField = vec![vec![vec![0], vec![0], vec![7], vec![0]]].into();
+    ///
+    /// assert_eq!(bub_field.resample((0u8, 1u8, 0u8).into()).unwrap(), vec![vec![vec![0], vec![7]]].into());
+    /// assert_eq!(bub_field.resample((0u8, 3u8, 0u8).into()).unwrap().values()[0][5], vec![7]);
+    /// assert_eq!(BubbleField::default().resample((0u8, 1u8, 0u8).into()).unwrap(), vec![vec![vec![0], vec![0]]].into());
+    /// ```
+    pub fn resample(&self, bub_field_size: BubbleFieldSize) -> Result<Self, &'static str> {
+        let mut bub_field = Self::from_bub_field_size(bub_field_size)?;
+        let (length, width, height) = bub_field_size.try_into()?;
+        // Range of cells from `i` in `to` cells to `from` cells
+        let range = |i: usize, to: usize, from: usize| {
+            if from == 0 {
+                return 0..0;
+            }
+            let start = i * from / to;
+            let end = ((i + 1) * from).div_ceil(to).clamp(start + 1, from);
+            start..end
+        };
+        let from_length = self.0.len();
+        for l in 0..length {
+            for from_l in range(l, length, from_length) {
+                let plane = &self.0[from_l];
+                for w in 0..width {
+                    for from_w in range(w, width, plane.len()) {
+                        let column = &plane[from_w];
+                        for h in 0..height {
+                            for from_h in range(h, height, column.len()) {
+                                let cell = &mut bub_field.0[l][w][h];
+                                *cell = (*cell).max(column[from_h]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(bub_field)
+    }
+}
+
 /// This structure is each size of Bubble field.
 #[derive(Clone, Copy, Debug, Default, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub struct BubbleFieldSize {
@@ -89,6 +249,99 @@ impl TryInto<(usize, usize, usize)> for BubbleFieldSize {
     }
 }
 
+/// This structure is position in Bubble field.
+/// Each value is normalized from 0.0 to 1.0, so it doesn't depend on `BubbleFieldSize`.
+/// ```txt
+/// x: width  (0.0 is left,   1.0 is right)
+/// y: length (0.0 is back,   1.0 is front)
+/// z: height (0.0 is bottom, 1.0 is top)
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct FieldPosition {
+    /// Position on width.
+    pub x: f64,
+    /// Position on length.
+    pub y: f64,
+    /// Position on height.
+    pub z: f64
+}
+
+impl FieldPosition {
+    /// This method returns center of Bubble field.
+    pub fn center() -> Self {
+        Self {
+            x: 0.5,
+            y: 0.5,
+            z: 0.5
+        }
+    }
+
+    /// This method returns index of the cell which includes this position.
+    /// Index is `(length, width, height)` as same as `BubbleField`.
+    /// Position out of the field is clamped.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::FieldPosition;
+    ///
+    /// let pos: FieldPosition = (0.0, 1.0, 0.6).into();
+    ///
+    /// assert_eq!(pos.cell((1u8, 2u8, 1u8).into()), Ok((1, 0, 1)));
+    /// ```
+    pub fn cell(self, bub_field_size: BubbleFieldSize) -> Result<(usize, usize, usize), &'static str> {
+        let (length, width, height) = bub_field_size.try_into()?;
+        let index = |n: f64, size: usize| ((n * size as f64).floor().max(0.0) as usize).min(size - 1);
+
+        Ok((index(self.y, length), index(self.x, width), index(self.z, height)))
+    }
+
+    /// This method returns center position of the cell.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::FieldPosition;
+    ///
+    /// let pos = FieldPosition::from_cell((1, 0, 0), (1u8, 1u8, 0u8).into()).unwrap();
+    ///
+    /// assert_eq!(pos, (0.25, 0.75, 0.5).into());
+    /// ```
+    pub fn from_cell((l, w, h): (usize, usize, usize), bub_field_size: BubbleFieldSize) -> Result<Self, &'static str> {
+        let (length, width, height) = bub_field_size.try_into()?;
+        if l >= length || w >= width || h >= height {
+            return Err("Cell is out of Bubble field.");
+        }
+
+        Ok(
+            Self {
+                x: (w as f64 + 0.5) / width as f64,
+                y: (l as f64 + 0.5) / length as f64,
+                z: (h as f64 + 0.5) / height as f64
+            }
+        )
+    }
+
+    /// This method returns distance between 2 positions.
+    pub fn distance(self, other: Self) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2)).sqrt()
+    }
+}
+
+impl From<(f64, f64, f64)> for FieldPosition {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Self {
+            x,
+            y,
+            z
+        }
+    }
+}
+
+impl From<FieldPosition> for (f64, f64, f64) {
+    fn from(pos: FieldPosition) -> Self {
+        (pos.x, pos.y, pos.z)
+    }
+}
+
 /// This structure is color of Bubble.
 #[derive(Clone, Copy, Debug, Default, Hash, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Color{
@@ -126,6 +379,24 @@ pub enum Sample {
     Float64(f64)
 }
 
+impl Sample {
+    /// This method casts `f64` to `Sample` which matches bits per sample.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::Sample;
+    ///
+    /// assert_eq!(Sample::from_f64_and_bits_per_sample(0.5, 32), Sample::Float32(0.5));
+    /// assert_eq!(Sample::from_f64_and_bits_per_sample(0.5, 64), Sample::Float64(0.5));
+    /// ```
+    pub fn from_f64_and_bits_per_sample(n: f64, bits_per_sample: u16) -> Self {
+        match bits_per_sample {
+            64 => Sample::Float64(n),
+            _ => Sample::Float32(n as f32)
+        }
+    }
+}
+
 impl From<f32> for Sample {
     fn from(sample: f32) -> Self {
         Sample::Float32(sample)
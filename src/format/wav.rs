@@ -26,6 +26,10 @@ pub struct Wav {
     pub data_block_size: u16,
     /// Bits Per Sample 
     pub bits_per_sample: u16,
+    /// Channel Mask of WAVE_FORMAT_EXTENSIBLE
+    /// If it's 0, there is no mask and channels are in the default order.
+    /// Otherwise, Format Chunk is written as WAVE_FORMAT_EXTENSIBLE.
+    pub channel_mask: u32,
     // Data Chunk
     /// Data Size
     pub data_size: u32,
@@ -59,6 +63,75 @@ impl Wav {
         }
     }
 
+    /// This method casts format to `Wav` of IEEE float which has no other chunk.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::wav::Wav;
+    ///
+    /// let wav = Wav::from_format(2, 44100, 32, 4).unwrap();
+    ///
+    /// assert_eq!(wav.data_size, 32);
+    /// assert_eq!(wav.blocks(), 4);
+    /// ```
+    pub fn from_format(channels: u16, sampling_rate: u32, bits_per_sample: u16, blocks: u64) -> Result<Self, &'static str> {
+        Self::from_sample_format(channels, sampling_rate, SampleFormat::Float, bits_per_sample, blocks)
+    }
+
+    /// This method casts format to `Wav` which has no other chunk.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::wav::{SampleFormat, Wav};
+    ///
+    /// let wav = Wav::from_sample_format(1, 44100, SampleFormat::Int, 24, 4).unwrap();
+    ///
+    /// assert_eq!(wav.format_tag, 1);
+    /// assert_eq!(wav.data_size, 12);
+    /// ```
+    pub fn from_sample_format(channels: u16, sampling_rate: u32, sample_format: SampleFormat, bits_per_sample: u16, blocks: u64) -> Result<Self, &'static str> {
+        match (sample_format, bits_per_sample) {
+            (SampleFormat::Int, 8) | (SampleFormat::Int, 16) | (SampleFormat::Int, 24) | (SampleFormat::Int, 32) => (),
+            (SampleFormat::Float, 32) | (SampleFormat::Float, 64) => (),
+            _ => return Err("Bits per sample doesn't match sample format.")
+        }
+        let data_block_size = bits_per_sample / 8 * channels;
+        let data_size = data_block_size as u64 * blocks;
+        if data_size + 36 > u32::MAX as u64 {
+            Err("Wav riff size only accepts no more than the largest value of u32.")
+        } else {
+            Ok(
+                Wav {
+                    riff_size: data_size as u32 + 36,
+                    format_size: 16,
+                    format_tag: match sample_format {
+                        SampleFormat::Int => 1,
+                        SampleFormat::Float => 3
+                    },
+                    channels,
+                    sampling_rate,
+                    data_rate: sampling_rate * data_block_size as u32,
+                    data_block_size,
+                    bits_per_sample,
+                    channel_mask: 0,
+                    data_size: data_size as u32,
+                    other_size: 0
+                }
+            )
+        }
+    }
+
+    /// This method returns sample format from format tag.
+    /// Format tag 1 is integer PCM and 3 is IEEE float.
+    /// Other format tags return `None`, and so does WAVE_FORMAT_EXTENSIBLE before its sub format is read.
+    pub fn sample_format(&self) -> Option<SampleFormat> {
+        match self.format_tag {
+            1 => Some(SampleFormat::Int),
+            3 => Some(SampleFormat::Float),
+            _ => None
+        }
+    }
+
     /// This method returns bytes per sample.
     /// 
     /// # Examples
@@ -100,11 +173,20 @@ impl Wav {
     }
 }
 
+/// This enum is type of samples in Wav.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SampleFormat {
+    /// Integer PCM (Format Tag is 1). Bits Per Sample is 8, 16, 24 or 32.
+    Int,
+    /// IEEE float (Format Tag is 3). Bits Per Sample is 32 or 64.
+    Float
+}
+
 impl fmt::Display for Wav {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         write!(
             f,
-            "\n File Size( - 8 ): {} Bytes\nFormat Chunk Size: {} Bytes\n      Format Tag: {}\n         Channels: {} Channels\n    Sampling Rate: {} Hz\n        Data Rate: {} kbps\n  Data Block Size: {} Bytes\n  Bits Per Sample: {} Bits\n   Wave Data Size: {} Bytes\n Other Chunk Size: {} Bytes\n",
+            "\n File Size( - 8 ): {} Bytes\nFormat Chunk Size: {} Bytes\n      Format Tag: {}\n         Channels: {} Channels\n    Sampling Rate: {} Hz\n        Data Rate: {} kbps\n  Data Block Size: {} Bytes\n  Bits Per Sample: {} Bits\n     Channel Mask: {:#x}\n   Wave Data Size: {} Bytes\n Other Chunk Size: {} Bytes\n",
             self.riff_size,
             self.format_size,
             self.format_tag,
@@ -113,6 +195,7 @@ impl fmt::Display for Wav {
             self.data_rate,
             self.data_block_size,
             self.bits_per_sample,
+            self.channel_mask,
             self.data_size,
             self.other_size
         )
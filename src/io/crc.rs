@@ -0,0 +1,95 @@
+//! CRC-32C of `Floaout`
+//!
+//! Details, each Bubble in Floaout and each block of Floaout end with CRC-32C (Castagnoli) of the bytes since the previous CRC.
+
+use crate::io::read::ReadBytes;
+use crate::io::write::WriteBytes;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+/// Reversed polynomial of CRC-32C
+const POLYNOMIAL: u32 = 0x82f6_3b78;
+/// CRC of each byte
+const TABLE: [u32; 256] = table();
+
+const fn table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+/// This function updates CRC-32C register by bytes.
+/// Register starts from `!0`, and CRC-32C is the inverted register.
+pub(crate) fn update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// This structure computes CRC-32C of bytes which are read or written through it.
+pub(crate) struct Crc<T> {
+    inner: T,
+    crc: u32
+}
+
+impl<T> Crc<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self { inner, crc: !0 }
+    }
+
+    /// This method returns CRC-32C of bytes until now.
+    pub(crate) fn crc(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl<R: Read> Crc<R> {
+    /// This method reads CRC-32C and checks whether it matches bytes which are read.
+    pub(crate) fn check(mut self, what: &str) -> Result<()> {
+        let crc = self.crc();
+        let read: u32 = self.inner.read_le_bytes()?;
+        if read == crc {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::InvalidData, format!("CRC-32C of {} is {:#010x}, but {:#010x} is computed.", what, read, crc)))
+        }
+    }
+}
+
+impl<W: Write> Crc<W> {
+    /// This method writes CRC-32C of bytes which are written.
+    pub(crate) fn finish(mut self) -> Result<()> {
+        let crc = self.crc();
+        self.inner.write_le_bytes(crc)
+    }
+}
+
+impl<R: Read> Read for Crc<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc = update(self.crc, &buf[..n]);
+
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Crc<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc = update(self.crc, &buf[..n]);
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
\ No newline at end of file
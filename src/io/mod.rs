@@ -2,5 +2,7 @@
 //! 
 //! This module contains traits about Read and Write bytes.
 
+mod crc;
 pub mod read;
+pub mod validate;
 pub mod write;
\ No newline at end of file
@@ -1,12 +1,14 @@
 //! Read formats
 
-use std::io::Seek;
+use std::io::{Seek, SeekFrom};
 use crate::format::{BubbleField, BubbleFieldSize, Color, Sample};
+use crate::format::aiff::{Aiff, AiffBlock, AiffBlocks, Compression, extended_to_u32};
 use crate::format::bub::{Bubble, BubbleBlock, BubbleBlocks};
 use crate::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
-use crate::format::wav::{Wav, WavBlock, WavBlocks};
+use crate::format::wav::{SampleFormat, Wav, WavBlock, WavBlocks};
+use crate::io::crc::Crc;
 use std::convert::TryInto;
-use std::io::{BufReader, Read, Result};
+use std::io::{BufReader, Error, ErrorKind, Read, Result};
 
 /// This trait reads bytes for inferring from variable to be assigned.
 pub trait ReadBytes<T>: Read {
@@ -218,6 +220,22 @@ impl<R: Read + ?Sized> ReadBytes<u64> for R {
     }
 }
 
+impl<R: Read + ?Sized> ReadBytes<u128> for R {
+    #[inline]
+    fn read_be_bytes(&mut self) -> Result<u128> {
+        let mut bytes = [0; 16];
+        self.read_exact(&mut bytes)?;
+        Ok(u128::from_be_bytes(bytes))
+    }
+
+    #[inline]
+    fn read_le_bytes(&mut self) -> Result<u128> {
+        let mut bytes = [0; 16];
+        self.read_exact(&mut bytes)?;
+        Ok(u128::from_le_bytes(bytes))
+    }
+}
+
 impl<R: Read + ?Sized> ReadBytes<(u64, u64)> for R {
     #[inline]
     fn read_be_bytes(&mut self) -> Result<(u64, u64)> {
@@ -392,6 +410,47 @@ pub trait ReadBlock<T, B>: Read {
     fn read_block(&mut self, _: T) -> Result<B>;
 }
 
+impl<R: Read + ?Sized> ReadBlock<&Aiff, AiffBlock> for R {
+    #[inline]
+    fn read_block(&mut self, aiff: &Aiff) -> Result<AiffBlock> {
+        let bits = aiff.bits_per_sample;
+        match (aiff.compression, bits) {
+            // Integer is normalized from -1.0 to 1.0.
+            (Compression::None, 8) => {
+                let n: u8 = self.read_be_bytes()?;
+                Ok(AiffBlock::from(n as i8 as f32 / 128.0))
+            },
+            (Compression::None, 16) | (Compression::Sowt, 16) => {
+                let mut bytes = [0; 2];
+                self.read_exact(&mut bytes)?;
+                let n = if aiff.compression == Compression::None { i16::from_be_bytes(bytes) } else { i16::from_le_bytes(bytes) };
+                Ok(AiffBlock::from(n as f32 / 32768.0))
+            },
+            (Compression::None, 24) | (Compression::Sowt, 24) => {
+                let mut bytes = [0; 4];
+                let n = if aiff.compression == Compression::None {
+                    self.read_exact(&mut bytes[..3])?;
+                    i32::from_be_bytes(bytes)
+                } else {
+                    self.read_exact(&mut bytes[1..])?;
+                    i32::from_le_bytes(bytes)
+                };
+                Ok(AiffBlock::from((n >> 8) as f32 / 8388608.0))
+            },
+            // f32 can't keep 32 bits integer.
+            (Compression::None, 32) | (Compression::Sowt, 32) => {
+                let mut bytes = [0; 4];
+                self.read_exact(&mut bytes)?;
+                let n = if aiff.compression == Compression::None { i32::from_be_bytes(bytes) } else { i32::from_le_bytes(bytes) };
+                Ok(AiffBlock::from(n as f64 / 2147483648.0))
+            },
+            (Compression::Float32, 32) => Ok(AiffBlock(self.read_be_bytes_for(4)?)),
+            (Compression::Float64, 64) => Ok(AiffBlock(self.read_be_bytes_for(8)?)),
+            _ => Err(Error::new(ErrorKind::InvalidData, "Bits per sample doesn't match compression type."))
+        }
+    }
+}
+
 impl<R: Read + ?Sized> ReadBlock<&Bubble, BubbleBlock> for R {
     #[inline]
     fn read_block(&mut self, bub: &Bubble) -> Result<BubbleBlock> {
@@ -413,10 +472,12 @@ impl<R: Read + ?Sized> ReadBlock<&Floaout, FloaoutBlock> for R {
     fn read_block(&mut self, oao: &Floaout) -> Result<FloaoutBlock> {
         let mut bub_block_vec = Vec::new();
         let bub = Bubble::from_bub_field_size_and_bits_per_sample(oao.bub_field_size, oao.bits_per_sample);
+        let mut reader = Crc::new(self);
         for _ in 0..oao.bubbles {
-            let bub_block = self.read_block(&bub)?;
+            let bub_block = reader.read_block(&bub)?;
             bub_block_vec.push(bub_block);
         }
+        reader.check("Floaout block")?;
 
         Ok(bub_block_vec.into())
     }
@@ -425,11 +486,30 @@ impl<R: Read + ?Sized> ReadBlock<&Floaout, FloaoutBlock> for R {
 impl<R: Read + ?Sized> ReadBlock<&Wav, WavBlock> for R {
     #[inline]
     fn read_block(&mut self, wav: &Wav) -> Result<WavBlock> {
-        match wav.bits_per_sample {
-            32 => Ok(WavBlock(self.read_le_bytes_for(4)?)),
-            64 => Ok(WavBlock(self.read_le_bytes_for(8)?)),
-            // This should be change to Error type.
-            _ => Ok(WavBlock(0.0.into()))
+        match (wav.sample_format(), wav.bits_per_sample) {
+            // Integer is normalized from -1.0 to 1.0.
+            (Some(SampleFormat::Int), 8) => {
+                let n: u8 = self.read_le_bytes()?;
+                Ok(WavBlock::from((n as f32 - 128.0) / 128.0))
+            },
+            (Some(SampleFormat::Int), 16) => {
+                let n: u16 = self.read_le_bytes()?;
+                Ok(WavBlock::from(n as i16 as f32 / 32768.0))
+            },
+            (Some(SampleFormat::Int), 24) => {
+                let mut bytes = [0; 4];
+                self.read_exact(&mut bytes[1..])?;
+                Ok(WavBlock::from((i32::from_le_bytes(bytes) >> 8) as f32 / 8388608.0))
+            },
+            // f32 can't keep 32 bits integer.
+            (Some(SampleFormat::Int), 32) => {
+                let n: u32 = self.read_le_bytes()?;
+                Ok(WavBlock::from(n as i32 as f64 / 2147483648.0))
+            },
+            // Samples of Bubble are read by `Wav` without format tag, so they are IEEE float.
+            (_, 32) => Ok(WavBlock(self.read_le_bytes_for(4)?)),
+            (_, 64) => Ok(WavBlock(self.read_le_bytes_for(8)?)),
+            (_, bits_per_sample) => Err(Error::new(ErrorKind::InvalidData, format!("{} bits per sample can't be read.", bits_per_sample)))
         }
     }
 }
@@ -479,6 +559,109 @@ pub trait ReadFmt<T, B>: Read {
     fn read_blocks(&mut self, _: &T) -> Result<B>;
 }
 
+impl<R: Read + Seek> ReadFmt<Aiff, AiffBlocks> for BufReader<R> {
+    #[inline]
+    fn read_details(&mut self) -> Result<Aiff> {
+        // Initialized
+        let mut aiff = Aiff::default();
+        let mut comm = false;
+        // Position of sound data
+        let mut data_position = None;
+        // Repeat until both Common Chunk and Sound Data Chunk are read.
+        loop {
+            let chunk_name: String = match self.read_be_bytes_for(4) {
+                Ok(chunk_name) => chunk_name,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Err(Error::new(ErrorKind::InvalidData, "Common Chunk or Sound Data Chunk is not found."))
+                },
+                Err(e) => return Err(e)
+            };
+            let chunk_size: u32 = self.read_be_bytes()?;
+            // Chunk is padded to even size.
+            let padded_size = chunk_size as i64 + chunk_size as i64 % 2;
+            // Allocate by chunk name.
+            match &*chunk_name {
+                // Form
+                "FORM" => {
+                    aiff.form_size = chunk_size;
+                    let form_type: String = self.read_be_bytes_for(4)?;
+                    aiff.aifc = match &*form_type {
+                        "AIFF" => false,
+                        "AIFC" => true,
+                        _ => return Err(Error::new(ErrorKind::InvalidData, "Form type is neither AIFF nor AIFC."))
+                    };
+                },
+                // Common
+                "COMM" => {
+                    let mut read_size = 18;
+                    if aiff.aifc {
+                        read_size += 4;
+                    }
+                    if padded_size < read_size {
+                        return Err(Error::new(ErrorKind::InvalidData, format!("Common Chunk of {} bytes is shorter than {} bytes.", chunk_size, read_size)));
+                    }
+                    aiff.channels = self.read_be_bytes()?;
+                    aiff.sample_frames = self.read_be_bytes()?;
+                    aiff.bits_per_sample = self.read_be_bytes()?;
+                    let mut extended = [0; 10];
+                    self.read_exact(&mut extended)?;
+                    aiff.sampling_rate = extended_to_u32(extended);
+                    if aiff.aifc {
+                        let id: String = self.read_be_bytes_for(4)?;
+                        aiff.compression = Compression::from_id(&id)
+                            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Compression type `{}` is not supported.", id)))?;
+                    }
+                    // Rest of chunk such as compression name
+                    self.seek_relative(padded_size - read_size)?;
+                    comm = true;
+                    // Go back to sound data which is before Common Chunk.
+                    if let Some(position) = data_position {
+                        self.seek(SeekFrom::Start(position))?;
+                        break
+                    }
+                },
+                // Format Version of AIFF-C
+                "FVER" => self.seek_relative(padded_size)?,
+                // Sound Data
+                "SSND" => {
+                    let offset: u32 = self.read_be_bytes()?;
+                    let _block_size: u32 = self.read_be_bytes()?;
+                    aiff.data_size = chunk_size.checked_sub(8)
+                        .and_then(|size| size.checked_sub(offset))
+                        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Offset {} is out of Sound Data Chunk of {} bytes.", offset, chunk_size)))?;
+                    self.seek_relative(offset as i64)?;
+                    if comm {
+                        break
+                    }
+                    // Common Chunk may follow sound data.
+                    data_position = Some(self.stream_position()?);
+                    self.seek_relative(padded_size - 8 - offset as i64)?;
+                },
+                // Other
+                _ => {
+                    // Add 8 and chunk_size bytes to other_size.
+                    aiff.other_size += 8 + padded_size as u32;
+                    self.seek_relative(padded_size)?;
+                },
+            }
+        }
+
+        Ok(aiff)
+    }
+
+    #[inline]
+    fn read_blocks(&mut self, aiff: &Aiff) -> Result<AiffBlocks> {
+        let samples = aiff.sample_frames as usize * aiff.channels as usize;
+        let mut aiff_block_vec = Vec::<AiffBlock>::with_capacity(samples);
+        for _ in 0..samples {
+            let aiff_block = self.read_block(aiff)?;
+            aiff_block_vec.push(aiff_block);
+        }
+
+        Ok(aiff_block_vec.into_boxed_slice().into())
+    }
+}
+
 impl<R: Read + Seek> ReadFmt<Bubble, BubbleBlocks> for BufReader<R> {
     #[inline]
     fn read_details(&mut self) -> Result<Bubble> {
@@ -487,6 +670,7 @@ impl<R: Read + Seek> ReadFmt<Bubble, BubbleBlocks> for BufReader<R> {
         // Bubble
         read_assert_eq(self, "bub")?;
         bub.version = self.read_le_bytes()?;
+        bub.bubble_id = self.read_le_bytes()?;
         // Bubble field size
         bub.bub_field_size = self.read_le_bytes()?;
         // Color
@@ -519,17 +703,24 @@ impl<R: Read + Seek> ReadFmt<Floaout, FloaoutBlocks> for BufReader<R> {
     fn read_details(&mut self) -> Result<Floaout> {
         // Initialized
         let mut oao = Floaout::default();
+        let mut reader = Crc::new(self);
         // Floaout
-        read_assert_eq(self, "oao")?;
-        oao.version = self.read_le_bytes()?;
-        oao.song_id = self.read_le_bytes()?;
+        read_assert_eq(&mut reader, "oao")?;
+        oao.version = reader.read_le_bytes()?;
+        oao.song_id = reader.read_le_bytes()?;
         // Bubble field size
-        oao.bub_field_size = self.read_le_bytes()?;
+        oao.bub_field_size = reader.read_le_bytes()?;
         // Format
-        oao.bubbles = self.read_le_bytes()?;
-        oao.blocks = self.read_le_bytes()?;
-        oao.sampling_rate = self.read_le_bytes()?;
-        oao.bits_per_sample = self.read_le_bytes()?;
+        oao.bubbles = reader.read_le_bytes()?;
+        oao.blocks = reader.read_le_bytes()?;
+        oao.sampling_rate = reader.read_le_bytes()?;
+        oao.bits_per_sample = reader.read_le_bytes()?;
+        // Title and Artist
+        oao.title_size = reader.read_le_bytes()?;
+        oao.title = reader.read_be_bytes_for(oao.title_size as usize)?;
+        oao.artist_size = reader.read_le_bytes()?;
+        oao.artist = reader.read_be_bytes_for(oao.artist_size as usize)?;
+        reader.check("Floaout details")?;
 
         Ok(oao)
     }
@@ -570,9 +761,39 @@ impl<R: Read + Seek> ReadFmt<Wav, WavBlocks> for BufReader<R> {
                     wav.data_rate = self.read_le_bytes()?;
                     wav.data_block_size = self.read_le_bytes()?;
                     wav.bits_per_sample = self.read_le_bytes()?;
+                    if wav.format_size > 16 {
+                        let mut extension_size = wav.format_size as i64 - 16;
+                        // WAVE_FORMAT_EXTENSIBLE
+                        if wav.format_tag == 0xfffe && extension_size >= 24 {
+                            let _cb_size: u16 = self.read_le_bytes()?;
+                            let _valid_bits_per_sample: u16 = self.read_le_bytes()?;
+                            wav.channel_mask = self.read_le_bytes()?;
+                            // The first 2 bytes of sub format GUID is format tag.
+                            wav.format_tag = self.read_le_bytes()?;
+                            self.seek_relative(14)?;
+                            extension_size -= 24;
+                        }
+                        self.seek_relative(extension_size)?;
+                        // Extension is considered as other chunk, so Format Chunk is written as 16 bytes.
+                        wav.other_size += wav.format_size - 16;
+                        wav.format_size = 16;
+                    }
                 },
                 // Data
                 "data" => {
+                    match (wav.sample_format(), wav.bits_per_sample) {
+                        (None, _) => return Err(Error::new(ErrorKind::InvalidData, format!("Format tag {:#x} isn't integer PCM or IEEE float.", wav.format_tag))),
+                        (Some(SampleFormat::Int), 8) | (Some(SampleFormat::Int), 16) | (Some(SampleFormat::Int), 24) | (Some(SampleFormat::Int), 32) => (),
+                        (Some(SampleFormat::Float), 32) | (Some(SampleFormat::Float), 64) => (),
+                        (Some(sample_format), bits_per_sample) => return Err(Error::new(ErrorKind::InvalidData, format!("{:?} samples of {} bits aren't supported.", sample_format, bits_per_sample)))
+                    }
+                    if wav.channels == 0 {
+                        return Err(Error::new(ErrorKind::InvalidData, "Channels is 0."));
+                    }
+                    // Bits per sample is at most 64, so this doesn't overflow.
+                    if wav.data_block_size as u32 != wav.channels as u32 * wav.bits_per_sample as u32 / 8 {
+                        return Err(Error::new(ErrorKind::InvalidData, format!("Data block size {} doesn't match {} channels of {} bits.", wav.data_block_size, wav.channels, wav.bits_per_sample)));
+                    }
                     wav.data_size = self.read_le_bytes()?;
                     break
                 },
@@ -632,14 +853,18 @@ impl<R: Read + Seek> ReadBubsIn<Floaout, BubblesInFloaout> for BufReader<R> {
         // Into Vec
         let mut vec_of_bub_in_oao: Vec<BubbleInFloaout> = Vec::new();
         for _ in 0..oao.bubbles {
-            let name_size: u8 = self.read_le_bytes()?;
+            let mut reader = Crc::new(&mut *self);
+            let bubble_id: u128 = reader.read_le_bytes()?;
+            let name_size: u8 = reader.read_le_bytes()?;
             vec_of_bub_in_oao.push(
                 BubbleInFloaout {
+                    bubble_id,
                     name_size,
-                    name: self.read_be_bytes_for(name_size as usize)?,
-                    color: self.read_le_bytes()?
+                    name: reader.read_be_bytes_for(name_size as usize)?,
+                    color: reader.read_le_bytes()?
                 }
             );
+            reader.check("Bubble in Floaout")?;
         }
 
         Ok(BubblesInFloaout::from(vec_of_bub_in_oao))
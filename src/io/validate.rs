@@ -0,0 +1,465 @@
+//! Structural validation of `Bubble` and `Floaout`
+//!
+//! Files are read as raw bytes, so sizes in details are never trusted before they are checked.
+//! Every problem is reported with its offset from the start of the file, and checking goes on after a problem whenever the rest can still be located.
+//!
+//! Details, each Bubble in Floaout and each block of `Floaout` end with CRC-32C.
+//! If CRC-32C of details or a Bubble in Floaout doesn't match the sizes of title, artist or name, sizes which match it are searched.
+//! `Bubble` has no CRC-32C, so its name size is compared with the byte length which the file length leaves for name.
+//! Every byte is a valid Bubble field value, so Bubble fields are checked through `overall`.
+
+use crate::io::crc::update;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{BufReader, Read, Result, Seek, SeekFrom};
+
+/// Supported version of `Bubble` and `Floaout`
+const VERSION: u8 = 0;
+/// Bubble field which has more cells than 2 to the power of this isn't sane.
+const MAX_FIELD_EXPONENT: u32 = 24;
+
+/// Problem of file
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Problem {
+    /// Offset in bytes from the start of the file
+    pub offset: u64,
+    /// What is wrong
+    pub message: String
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#010x}: {}", self.offset, self.message)
+    }
+}
+
+/// This structure reads bytes and keeps problems.
+struct Scanner<'a, R: Read + Seek> {
+    reader: &'a mut BufReader<R>,
+    offset: u64,
+    length: u64,
+    problems: Vec<Problem>,
+    /// Bytes since the previous CRC-32C, which are kept only for `Floaout`
+    section: Option<Vec<u8>>
+}
+
+impl<'a, R: Read + Seek> Scanner<'a, R> {
+    fn new(reader: &'a mut BufReader<R>) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        Ok(
+            Self {
+                reader,
+                offset: 0,
+                length: end.saturating_sub(start),
+                problems: Vec::new(),
+                section: None
+            }
+        )
+    }
+
+    fn problem(&mut self, offset: u64, message: String) {
+        self.problems.push(Problem { offset, message });
+    }
+
+    /// This method returns `None` and keeps a problem if the file ends before `size` bytes.
+    fn bytes(&mut self, size: usize, what: &str) -> Result<Option<Vec<u8>>> {
+        if self.length - self.offset < size as u64 {
+            let message = format!("File ends in {}. {} bytes are needed but there are {} bytes.", what, size, self.length - self.offset);
+            self.problem(self.offset, message);
+            return Ok(None);
+        }
+        let mut buf = vec![0; size];
+        self.reader.read_exact(&mut buf)?;
+        self.offset += size as u64;
+        if let Some(section) = &mut self.section {
+            section.extend_from_slice(&buf);
+        }
+
+        Ok(Some(buf))
+    }
+
+    /// This method returns at most `size` bytes without reading them.
+    fn peek(&mut self, size: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; size.min((self.length - self.offset) as usize)];
+        self.reader.read_exact(&mut buf)?;
+        self.reader.seek_relative(-(buf.len() as i64))?;
+
+        Ok(buf)
+    }
+
+    fn array<const N: usize>(&mut self, what: &str) -> Result<Option<[u8; N]>> {
+        Ok(self.bytes(N, what)?.map(|buf| buf.try_into().expect("buffer has N bytes")))
+    }
+
+    /// This method checks magic and version.
+    fn magic_and_version(&mut self, magic: &str) -> Result<Option<()>> {
+        let Some(buf) = self.bytes(magic.len(), "magic")? else { return Ok(None) };
+        if buf != magic.as_bytes() {
+            self.problem(0, format!("Magic is {:?}, not {:?}.", String::from_utf8_lossy(&buf), magic));
+        }
+        let offset = self.offset;
+        let Some([version]) = self.array("version")? else { return Ok(None) };
+        if version != VERSION {
+            self.problem(offset, format!("Version {} isn't supported.", version));
+        }
+
+        Ok(Some(()))
+    }
+
+    /// This method checks Bubble field size and returns number of cells.
+    fn bub_field_size(&mut self) -> Result<Option<Option<usize>>> {
+        let offset = self.offset;
+        let Some(exponents) = self.array::<3>("Bubble field size")? else { return Ok(None) };
+        let sum: u32 = exponents.iter().map(|&exponent| exponent as u32).sum();
+        if sum > MAX_FIELD_EXPONENT {
+            let message = format!("Bubble field size {}, {}, {} has 2^{} cells, which is more than 2^{}.", exponents[0], exponents[1], exponents[2], sum, MAX_FIELD_EXPONENT);
+            self.problem(offset, message);
+            return Ok(Some(None));
+        }
+
+        Ok(Some(Some(1 << sum)))
+    }
+
+    /// This method checks sampling rate and bits per sample, and returns bytes per sample.
+    fn format(&mut self) -> Result<Option<Option<usize>>> {
+        let offset = self.offset;
+        let Some(sampling_rate) = self.array("sampling rate")? else { return Ok(None) };
+        if u32::from_le_bytes(sampling_rate) == 0 {
+            self.problem(offset, "Sampling rate is 0.".into());
+        }
+        let offset = self.offset;
+        let Some(bits_per_sample) = self.array("bits per sample")? else { return Ok(None) };
+        let bits_per_sample = u16::from_le_bytes(bits_per_sample);
+        if bits_per_sample != 32 && bits_per_sample != 64 {
+            self.problem(offset, format!("Bits per sample is {}, not 32 or 64.", bits_per_sample));
+            return Ok(Some(None));
+        }
+
+        Ok(Some(Some(bits_per_sample as usize / 8)))
+    }
+
+    /// This method keeps a problem if the string isn't UTF-8 or has a control character.
+    fn string(&mut self, offset: u64, string: &[u8], what: &str) {
+        match std::str::from_utf8(string) {
+            Ok(string) => if let Some(i) = string.find(char::is_control) {
+                self.problem(offset + i as u64, format!("{} has a control character.", capitalize(what)));
+            },
+            Err(e) => self.problem(offset + e.valid_up_to() as u64, format!("{} isn't UTF-8.", capitalize(what)))
+        }
+    }
+
+    /// This method checks name size and name of Bubble.
+    /// `rest` is bytes after name, so name size is compared with the byte length which the file length leaves for name.
+    /// The byte length is taken only if name size doesn't give a name but it does.
+    fn bub_name(&mut self, rest: Option<u64>) -> Result<Option<()>> {
+        let offset = self.offset;
+        let Some([name_size]) = self.array("name size")? else { return Ok(None) };
+        let left = rest.and_then(|rest| (self.length - self.offset).checked_sub(rest)).filter(|&size| size <= u8::MAX as u64 && size != name_size as u64);
+        if let Some(size) = left {
+            if is_name(&self.peek(size as usize)?) && !is_name(&self.peek(name_size as usize)?) {
+                self.problem(offset, format!("Name size {} doesn't match name of {} bytes which file length leaves.", name_size, size));
+                self.bytes(size as usize, "name")?;
+                return Ok(Some(()));
+            }
+        }
+        let start = self.offset;
+        let Some(name) = self.bytes(name_size as usize, "name")? else { return Ok(None) };
+        self.string(start, &name, "name");
+
+        Ok(Some(()))
+    }
+
+    /// This method checks sizes and strings which are followed by `suffix` bytes and CRC-32C of the section.
+    /// Only if CRC-32C doesn't match the sizes or the strings aren't names, sizes which match it are searched,
+    /// so a size which doesn't match the byte length of its string is found.
+    fn strings_and_crc(&mut self, whats: &[&str], suffix: usize, section: &str) -> Result<Option<()>> {
+        let start = self.offset;
+        let prefix = self.section.replace(Vec::new()).unwrap_or_default();
+        let window = self.peek(whats.len() * 256 + suffix + 4)?;
+        let register = update(!0, &prefix);
+        let declared = declared_sizes(&window, whats.len());
+        let end = declared.iter().map(|size| 1 + size).sum::<usize>() + suffix;
+        let mut best = None;
+        if declared.len() == whats.len() && crc_matches(register, &window, 0, end) && mismatches(&window, &declared) == 0 {
+            best = Some((0, declared));
+        } else {
+            search(register, &window, 0, whats.len(), suffix, &mut Vec::with_capacity(whats.len()), &mut best);
+        }
+        if let Some((_, sizes)) = best {
+            let mut position = 0;
+            for (&size, what) in sizes.iter().zip(whats) {
+                let declared = window[position];
+                if declared as usize != size {
+                    self.problem(start + position as u64, format!("{} size {} doesn't match {} of {} bytes.", capitalize(what), declared, what, size));
+                }
+                self.string(start + position as u64 + 1, &window[position + 1..position + 1 + size], what);
+                position += 1 + size;
+            }
+            self.bytes(position + suffix + 4, section)?;
+            self.section = Some(Vec::new());
+            return Ok(Some(()));
+        }
+        // Sizes are taken as they are.
+        for what in whats {
+            let Some([size]) = self.array(&format!("{} size", what))? else { return Ok(None) };
+            let offset = self.offset;
+            let Some(string) = self.bytes(size as usize, what)? else { return Ok(None) };
+            self.string(offset, &string, what);
+        }
+        let Some(_) = self.bytes(suffix, section)? else { return Ok(None) };
+        let offset = self.offset;
+        let Some(_crc) = self.array::<4>("CRC-32C")? else { return Ok(None) };
+        self.problem(offset, format!("CRC-32C of {} doesn't match.", section));
+        self.section = Some(Vec::new());
+
+        Ok(Some(()))
+    }
+
+    /// This method checks file length and returns number of blocks which are in the file.
+    fn length(&mut self, blocks: u64, stride: u64) -> u64 {
+        let header = self.offset;
+        let expected = header as u128 + blocks as u128 * stride as u128;
+        let in_file = (self.length - header).checked_div(stride).map_or(blocks, |n| blocks.min(n));
+        if expected > self.length as u128 {
+            let offset = header + in_file * stride;
+            self.problem(offset, format!("File is truncated. {} blocks are expected but there are {} blocks.", blocks, in_file));
+        } else if expected < self.length as u128 {
+            let expected = expected as u64;
+            self.problem(expected, format!("There are {} bytes after the last block.", self.length - expected));
+        }
+
+        in_file
+    }
+
+    /// This method checks samples of blocks and returns maximum of each Bubble field cell.
+    /// If `crc` is true, each block ends with CRC-32C.
+    fn blocks(&mut self, blocks: u64, bubbles: usize, bytes_per_sample: usize, cells: usize, crc: bool) -> Result<Vec<u8>> {
+        let mut overall = vec![0; cells];
+        // Start offset and block of NaN or infinite samples of each Bubble
+        let mut runs: Vec<Option<(u64, u64)>> = vec![None; bubbles];
+        // Start offset and block of CRC-32C which doesn't match
+        let mut crc_run: Option<(u64, u64)> = None;
+        let mut buf = vec![0; bytes_per_sample + cells];
+        for block in 0..=blocks {
+            let block_offset = self.offset;
+            let mut register = !0;
+            for (bubble, run) in runs.iter_mut().enumerate() {
+                let offset = self.offset;
+                let finite = block == blocks || {
+                    self.reader.read_exact(&mut buf)?;
+                    self.offset += buf.len() as u64;
+                    register = update(register, &buf);
+                    for (max, &value) in overall.iter_mut().zip(&buf[bytes_per_sample..]) {
+                        *max = value.max(*max);
+                    }
+                    match bytes_per_sample {
+                        4 => f32::from_le_bytes(buf[..4].try_into().expect("4 bytes")).is_finite(),
+                        _ => f64::from_le_bytes(buf[..8].try_into().expect("8 bytes")).is_finite()
+                    }
+                };
+                match (finite, *run) {
+                    (false, None) => *run = Some((offset, block)),
+                    (true, Some((offset, start))) => {
+                        let who = if bubbles == 1 { String::new() } else { format!("Bubble {}: ", bubble) };
+                        self.problems.push(Problem { offset, message: format!("{}Samples of blocks {}..{} are NaN or infinite.", who, start, block) });
+                        *run = None;
+                    },
+                    _ => ()
+                }
+            }
+            if crc {
+                let matched = block == blocks || {
+                    let mut read = [0; 4];
+                    self.reader.read_exact(&mut read)?;
+                    self.offset += 4;
+                    u32::from_le_bytes(read) == !register
+                };
+                match (matched, crc_run) {
+                    (false, None) => crc_run = Some((block_offset, block)),
+                    (true, Some((offset, start))) => {
+                        self.problem(offset, format!("CRC-32C of blocks {}..{} doesn't match.", start, block));
+                        crc_run = None;
+                    },
+                    _ => ()
+                }
+            }
+        }
+
+        Ok(overall)
+    }
+
+    fn finish(mut self) -> Vec<Problem> {
+        self.problems.sort_by_key(|problem| problem.offset);
+
+        self.problems
+    }
+}
+
+fn capitalize(what: &str) -> String {
+    let mut chars = what.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+/// This function returns whether bytes are UTF-8 without control characters.
+fn is_name(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|name| !name.contains(char::is_control))
+}
+
+/// This function returns sizes of `strings` strings at the start of `window` as they are declared.
+fn declared_sizes(window: &[u8], strings: usize) -> Vec<usize> {
+    let mut sizes = Vec::with_capacity(strings);
+    let mut position = 0;
+    while sizes.len() < strings {
+        let Some(&size) = window.get(position) else { break };
+        sizes.push(size as usize);
+        position += 1 + size as usize;
+    }
+
+    sizes
+}
+
+/// This function returns whether CRC-32C at `end` of `window` matches bytes from `position` to `end`.
+/// `register` is CRC-32C register before `position`.
+fn crc_matches(register: u32, window: &[u8], position: usize, end: usize) -> bool {
+    window.get(end..end + 4).is_some_and(|crc| {
+        u32::from_le_bytes(crc.try_into().expect("4 bytes")) == !update(register, &window[position..end])
+    })
+}
+
+/// This function returns number of sizes which don't match and strings which aren't names at the start of `window`.
+fn mismatches(window: &[u8], sizes: &[usize]) -> usize {
+    let mut start = 0;
+    sizes.iter().map(|&size| {
+        let mismatches = (window[start] as usize != size) as usize + !is_name(&window[start + 1..start + 1 + size]) as usize;
+        start += 1 + size;
+        mismatches
+    }).sum()
+}
+
+/// This function searches sizes of `strings` strings from `position` of `window` which are followed by `suffix` bytes and CRC-32C.
+/// Each string starts with its size, and sizes in `window` are tried first.
+/// CRC-32C only fixes where the strings end, so sizes with the fewest sizes which don't match and strings which aren't names are kept in `best`.
+fn search(register: u32, window: &[u8], position: usize, strings: usize, suffix: usize, sizes: &mut Vec<usize>, best: &mut Option<(usize, Vec<usize>)>) {
+    if strings == 0 {
+        if crc_matches(register, window, position, position + suffix) {
+            let score = mismatches(window, sizes);
+            if best.as_ref().is_none_or(|(best, _)| score < *best) {
+                *best = Some((score, sizes.clone()));
+            }
+        }
+        return;
+    }
+    let Some(&declared) = window.get(position) else { return };
+    let register = update(register, &[declared]);
+    let start = position + 1;
+    for size in std::iter::once(declared as usize).chain((0..=u8::MAX as usize).filter(|&size| size != declared as usize)) {
+        let Some(string) = window.get(start..start + size) else { continue };
+        sizes.push(size);
+        search(update(register, string), window, start + size, strings - 1, suffix, sizes, best);
+        sizes.pop();
+    }
+}
+
+/// This function reads Bubble and returns all problems in it.
+/// An error is returned only when reading fails.
+/// Magic, version, name, sampling rate, bits per sample, Bubble field size, file length, samples and `overall` are checked.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::io::validate::validate_bub;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.bub")?);
+///
+///     for problem in validate_bub(&mut reader)? {
+///         println!("{}", problem);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn validate_bub<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<Vec<Problem>> {
+    let mut scanner = Scanner::new(reader)?;
+    let Some(()) = scanner.magic_and_version("bub")? else { return Ok(scanner.finish()) };
+    let Some(_bubble_id) = scanner.array::<16>("Bubble ID")? else { return Ok(scanner.finish()) };
+    let Some(cells) = scanner.bub_field_size()? else { return Ok(scanner.finish()) };
+    let Some(_color) = scanner.array::<3>("color")? else { return Ok(scanner.finish()) };
+    let Some(blocks) = scanner.array("blocks")? else { return Ok(scanner.finish()) };
+    let Some(bytes_per_sample) = scanner.format()? else { return Ok(scanner.finish()) };
+    let blocks = u64::from_le_bytes(blocks);
+    // Overall and blocks after name
+    let rest = cells.zip(bytes_per_sample).and_then(|(cells, bytes_per_sample)| {
+        blocks.checked_mul((bytes_per_sample + cells) as u64)?.checked_add(cells as u64)
+    });
+    let Some(()) = scanner.bub_name(rest)? else { return Ok(scanner.finish()) };
+    // The rest can't be located without Bubble field size and bits per sample.
+    let (Some(cells), Some(bytes_per_sample)) = (cells, bytes_per_sample) else { return Ok(scanner.finish()) };
+    let offset = scanner.offset;
+    let Some(overall) = scanner.bytes(cells, "overall")? else { return Ok(scanner.finish()) };
+
+    let in_file = scanner.length(blocks, (bytes_per_sample + cells) as u64);
+    let max = scanner.blocks(in_file, 1, bytes_per_sample, cells, false)?;
+    // Overall of truncated file can't be compared.
+    if in_file == blocks {
+        let differences: Vec<usize> = (0..cells).filter(|&i| overall[i] != max[i]).collect();
+        if let Some(&first) = differences.first() {
+            let message = format!("Overall doesn't match the maximum of Bubble fields in {} cells.", differences.len());
+            scanner.problem(offset + first as u64, message);
+        }
+    }
+
+    Ok(scanner.finish())
+}
+
+/// This function reads Floaout and returns all problems in it.
+/// An error is returned only when reading fails.
+/// Magic, version, sampling rate, bits per sample, Bubble field size, title, artist, Bubbles in Floaout, file length, samples and CRC-32C are checked.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::File;
+/// use floaout::io::validate::validate_oao;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+///
+///     let problems = validate_oao(&mut reader)?;
+///     if !problems.is_empty() {
+///         println!("foo.oao has {} problems. The first is {}.", problems.len(), problems[0]);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn validate_oao<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<Vec<Problem>> {
+    let mut scanner = Scanner::new(reader)?;
+    scanner.section = Some(Vec::new());
+    let Some(()) = scanner.magic_and_version("oao")? else { return Ok(scanner.finish()) };
+    let Some(_song_id) = scanner.array::<8>("song ID")? else { return Ok(scanner.finish()) };
+    let Some(cells) = scanner.bub_field_size()? else { return Ok(scanner.finish()) };
+    let Some(bubbles) = scanner.array("bubbles")? else { return Ok(scanner.finish()) };
+    let Some(blocks) = scanner.array("blocks")? else { return Ok(scanner.finish()) };
+    let Some(bytes_per_sample) = scanner.format()? else { return Ok(scanner.finish()) };
+    let Some(()) = scanner.strings_and_crc(&["title", "artist"], 0, "details")? else { return Ok(scanner.finish()) };
+    let bubbles = u16::from_le_bytes(bubbles) as usize;
+    for bubble in 0..bubbles {
+        let Some(_bubble_id) = scanner.array::<16>("Bubble ID")? else { return Ok(scanner.finish()) };
+        // Name is followed by color.
+        let Some(()) = scanner.strings_and_crc(&["name"], 3, &format!("Bubble {}", bubble))? else { return Ok(scanner.finish()) };
+    }
+    scanner.section = None;
+    // The rest can't be located without Bubble field size and bits per sample.
+    let (Some(cells), Some(bytes_per_sample)) = (cells, bytes_per_sample) else { return Ok(scanner.finish()) };
+
+    // Each block ends with CRC-32C.
+    let in_file = scanner.length(u64::from_le_bytes(blocks), (bubbles * (bytes_per_sample + cells) + 4) as u64);
+    scanner.blocks(in_file, bubbles, bytes_per_sample, cells, true)?;
+
+    Ok(scanner.finish())
+}
\ No newline at end of file
@@ -1,11 +1,13 @@
 //! Write formats
 
 use crate::format::{BubbleField, BubbleFieldSize, Color, Sample};
+use crate::format::aiff::{Aiff, AiffBlock, AiffBlocks, Compression, u32_to_extended};
 use crate::format::bub::{Bubble, BubbleBlock, BubbleBlocks};
 use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
-use crate::format::wav::{Wav, WavBlock, WavBlocks};
+use crate::format::wav::{SampleFormat, Wav, WavBlock, WavBlocks};
+use crate::io::crc::Crc;
 use std::convert::TryInto;
-use std::io::{BufWriter, Result, Write};
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
 
 /// This trait writes bytes for inferring from variable.
 pub trait WriteBytes<T>: Write {
@@ -205,6 +207,18 @@ impl<W: Write + ?Sized> WriteBytes<u64> for W {
     }
 }
 
+impl<W: Write + ?Sized> WriteBytes<u128> for W {
+    #[inline]
+    fn write_be_bytes(&mut self, n: u128) -> Result<()> {
+        self.write_all(&n.to_be_bytes())
+    }
+
+    #[inline]
+    fn write_le_bytes(&mut self, n: u128) -> Result<()> {
+        self.write_all(&n.to_le_bytes())
+    }
+}
+
 impl<W: Write + ?Sized> WriteBytes<(u64, u64)> for W {
     #[inline]
     fn write_be_bytes(&mut self, (n1, n2): (u64, u64)) -> Result<()> {
@@ -258,6 +272,32 @@ pub trait WriteBlock<T, B>: Write {
     fn write_block(&mut self, _: T, _: B) -> Result<()>;
 }
 
+impl<W: Write + ?Sized> WriteBlock<&Aiff, AiffBlock> for W {
+    #[inline]
+    fn write_block(&mut self, aiff: &Aiff, aiff_block: AiffBlock) -> Result<()> {
+        let sample = aiff_block.0;
+        let bits = aiff.bits_per_sample;
+        match (aiff.compression, bits) {
+            // Integer is clipped.
+            (Compression::None, 8) | (Compression::None, 16) | (Compression::None, 24) | (Compression::None, 32)
+            | (Compression::Sowt, 16) | (Compression::Sowt, 24) | (Compression::Sowt, 32) => {
+                let n: f64 = sample.into();
+                let max = 2f64.powi(bits as i32 - 1);
+                let n = (n * max).round().clamp(-max, max - 1.0) as i32;
+                let bytes = bits as usize / 8;
+                if aiff.compression == Compression::None {
+                    self.write_all(&n.to_be_bytes()[4 - bytes..])
+                } else {
+                    self.write_all(&n.to_le_bytes()[..bytes])
+                }
+            },
+            (Compression::Float32, 32) => self.write_be_bytes(Into::<f32>::into(sample)),
+            (Compression::Float64, 64) => self.write_be_bytes(Into::<f64>::into(sample)),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "Bits per sample doesn't match compression type."))
+        }
+    }
+}
+
 impl<W: Write + ?Sized> WriteBlock<&Bubble, &BubbleBlock> for W {
     #[inline]
     fn write_block(&mut self, bub: &Bubble, bub_block: &BubbleBlock) -> Result<()> {
@@ -269,20 +309,38 @@ impl<W: Write + ?Sized> WriteBlock<&Bubble, &BubbleBlock> for W {
 impl<W: Write + ?Sized> WriteBlock<&Floaout, &FloaoutBlock> for W {
     #[inline]
     fn write_block(&mut self, oao: &Floaout, oao_block: &FloaoutBlock) -> Result<()> {
-        let bub = Bubble::from(*oao);
+        let bub = Bubble::from_bub_field_size_and_bits_per_sample(oao.bub_field_size, oao.bits_per_sample);
+        let mut writer = Crc::new(self);
         for bub_block in &oao_block.0 {
-            self.write_block(&bub, &bub_block)?;
+            writer.write_block(&bub, bub_block)?;
         }
 
-        Ok(())
+        writer.finish()
     }
 }
 
 impl<W: Write + ?Sized> WriteBlock<&Wav, WavBlock> for W {
     #[inline]
-    fn write_block(&mut self, _wav: &Wav, wav_block: WavBlock) -> Result<()> {
+    fn write_block(&mut self, wav: &Wav, wav_block: WavBlock) -> Result<()> {
         let sample: Sample = wav_block.into();
-        self.write_le_bytes(sample)
+        match (wav.sample_format(), wav.bits_per_sample) {
+            // Integer is clipped.
+            (Some(SampleFormat::Int), bits) => {
+                let n: f64 = sample.into();
+                let max = 2f64.powi(bits as i32 - 1);
+                let n = (n * max).round().clamp(-max, max - 1.0) as i32;
+                match bits {
+                    8 => self.write_le_bytes((n + 128) as u8),
+                    16 => self.write_le_bytes(n as u16),
+                    24 => self.write_all(&n.to_le_bytes()[..3]),
+                    _ => self.write_le_bytes(n as u32)
+                }
+            },
+            (_, 32) => self.write_le_bytes(Into::<f32>::into(sample)),
+            (_, 64) => self.write_le_bytes(Into::<f64>::into(sample)),
+            // Sample decides the size.
+            _ => self.write_le_bytes(sample)
+        }
     }
 }
 
@@ -343,12 +401,69 @@ pub trait WriteFmt<T, B>: Write {
     fn write_blocks(&mut self, _: &T, _: B) -> Result<()>;
 }
 
+impl<W: Write> WriteFmt<Aiff, AiffBlocks> for BufWriter<W> {
+    #[inline]
+    fn write_details(&mut self, aiff: &Aiff) -> Result<()> {
+        let comm_size = aiff.comm_size();
+        // Form Chunk
+        self.write_be_bytes("FORM")?;
+        let fver_size = if aiff.aifc { 12 } else { 0 };
+        self.write_be_bytes(4 + 8 + comm_size + fver_size + 16 + aiff.data_size + aiff.data_size % 2)?;
+        self.write_be_bytes(if aiff.aifc { "AIFC" } else { "AIFF" })?;
+        // Format Version Chunk
+        if aiff.aifc {
+            self.write_be_bytes("FVER")?;
+            self.write_be_bytes(4u32)?;
+            // AIFF-C Version 1
+            self.write_be_bytes(0xa2805140u32)?;
+        }
+        // Common Chunk
+        self.write_be_bytes("COMM")?;
+        self.write_be_bytes(comm_size)?;
+        self.write_be_bytes(aiff.channels)?;
+        self.write_be_bytes(aiff.sample_frames)?;
+        self.write_be_bytes(aiff.bits_per_sample)?;
+        self.write_all(&u32_to_extended(aiff.sampling_rate))?;
+        if aiff.aifc {
+            let name = aiff.compression.name();
+            self.write_be_bytes(aiff.compression.id())?;
+            self.write_be_bytes(name.len() as u8)?;
+            self.write_be_bytes(name)?;
+            if name.len() & 1 == 0 {
+                self.write_be_bytes(0u8)?;
+            }
+        }
+        // Sound Data Chunk
+        self.write_be_bytes("SSND")?;
+        self.write_be_bytes(aiff.data_size + 8)?;
+        // Offset and Block Size
+        self.write_be_bytes(0u32)?;
+        self.write_be_bytes(0u32)?;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn write_blocks(&mut self, aiff: &Aiff, aiff_blocks: AiffBlocks) -> Result<()> {
+        for aiff_block in &*aiff_blocks.0 {
+            self.write_block(aiff, *aiff_block)?;
+        }
+        // Sound Data Chunk is padded to even size.
+        if aiff.data_size % 2 == 1 {
+            self.write_be_bytes(0u8)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<W: Write> WriteFmt<Bubble, BubbleBlocks> for BufWriter<W> {
     #[inline]
     fn write_details(&mut self, bub: &Bubble) -> Result<()> {
         // Bubble
         self.write_be_bytes("bub")?;
         self.write_le_bytes(bub.version)?;
+        self.write_le_bytes(bub.bubble_id)?;
         // Bubble field size
         self.write_le_bytes(bub.bub_field_size)?;
         // Color
@@ -377,19 +492,25 @@ impl<W: Write> WriteFmt<Bubble, BubbleBlocks> for BufWriter<W> {
 impl<W: Write> WriteFmt<Floaout, FloaoutBlocks> for BufWriter<W> {
     #[inline]
     fn write_details(&mut self, oao: &Floaout) -> Result<()> {
+        let mut writer = Crc::new(self);
         // Floaout
-        self.write_be_bytes("oao")?;
-        self.write_le_bytes(oao.version)?;
-        self.write_le_bytes(oao.song_id)?;
+        writer.write_be_bytes("oao")?;
+        writer.write_le_bytes(oao.version)?;
+        writer.write_le_bytes(oao.song_id)?;
         // Bubble field size
-        self.write_le_bytes(oao.bub_field_size)?;
+        writer.write_le_bytes(oao.bub_field_size)?;
         // Format
-        self.write_le_bytes(oao.bubbles)?;
-        self.write_le_bytes(oao.blocks)?;
-        self.write_le_bytes(oao.sampling_rate)?;
-        self.write_le_bytes(oao.bits_per_sample)?;
+        writer.write_le_bytes(oao.bubbles)?;
+        writer.write_le_bytes(oao.blocks)?;
+        writer.write_le_bytes(oao.sampling_rate)?;
+        writer.write_le_bytes(oao.bits_per_sample)?;
+        // Title and Artist
+        writer.write_le_bytes(oao.title_size)?;
+        writer.write_be_bytes(oao.title.clone())?;
+        writer.write_le_bytes(oao.artist_size)?;
+        writer.write_be_bytes(oao.artist.clone())?;
 
-        Ok(())
+        writer.finish()
     }
 
     #[inline]
@@ -405,19 +526,31 @@ impl<W: Write> WriteFmt<Floaout, FloaoutBlocks> for BufWriter<W> {
 impl<W: Write> WriteFmt<Wav, WavBlocks> for BufWriter<W> {
     #[inline]
     fn write_details(&mut self, wav: &Wav) -> Result<()> {
+        // WAVE_FORMAT_EXTENSIBLE has 24 bytes of extension.
+        let format_size = if wav.channel_mask != 0 { wav.format_size + 24 } else { wav.format_size };
         // Riff Chunk
         self.write_be_bytes("RIFF")?;
-        self.write_le_bytes(wav.data_size + 4 + 8 + wav.format_size + 8)?;
+        self.write_le_bytes(wav.data_size + 4 + 8 + format_size + 8)?;
         self.write_be_bytes("WAVE")?;
         // Format Chunk
         self.write_be_bytes("fmt ")?;
-        self.write_le_bytes(wav.format_size)?;
-        self.write_le_bytes(wav.format_tag)?;
+        self.write_le_bytes(format_size)?;
+        self.write_le_bytes(if wav.channel_mask != 0 { 0xfffe } else { wav.format_tag })?;
         self.write_le_bytes(wav.channels)?;
         self.write_le_bytes(wav.sampling_rate)?;
         self.write_le_bytes(wav.data_rate)?;
         self.write_le_bytes(wav.data_block_size)?;
         self.write_le_bytes(wav.bits_per_sample)?;
+        if wav.channel_mask != 0 {
+            // Extension Size
+            self.write_le_bytes(22u16)?;
+            // Valid Bits Per Sample
+            self.write_le_bytes(wav.bits_per_sample)?;
+            self.write_le_bytes(wav.channel_mask)?;
+            // Sub Format GUID whose first 2 bytes are format tag
+            self.write_le_bytes(wav.format_tag)?;
+            self.write_all(&[0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71])?;
+        }
         // Data Chunk
         self.write_be_bytes("data")?;
         self.write_le_bytes(wav.data_size)?;
@@ -463,11 +596,14 @@ impl<W: Write> WriteBubsIn<BubblesInFloaout> for BufWriter<W> {
     #[inline]
     fn write_bubs_details(&mut self, bubs_in_oao: &BubblesInFloaout) -> Result<()> {
         for bub_in_oao in &*bubs_in_oao.0 {
+            let mut writer = Crc::new(&mut *self);
+            writer.write_le_bytes(bub_in_oao.bubble_id)?;
             // Name of Bubble
-            self.write_le_bytes(bub_in_oao.name_size)?;
-            self.write_be_bytes(bub_in_oao.name.clone())?;
+            writer.write_le_bytes(bub_in_oao.name_size)?;
+            writer.write_be_bytes(bub_in_oao.name.clone())?;
             // Color
-            self.write_le_bytes(bub_in_oao.color)?;
+            writer.write_le_bytes(bub_in_oao.color)?;
+            writer.finish()?;
         }
 
         Ok(())
@@ -7,5 +7,11 @@
 // Enable to use seek_relative method.
 #![feature(bufreader_seek_relative)]
 
+pub mod analysis;
+pub mod convert;
+pub mod edit;
 pub mod format;
-pub mod io;
\ No newline at end of file
+pub mod io;
+mod json;
+pub mod motion;
+pub mod render;
\ No newline at end of file
@@ -0,0 +1,5 @@
+//! Motion
+//!
+//! This module contains structures which make Bubble field of each block from movement of a sound.
+
+pub mod trajectory;
\ No newline at end of file
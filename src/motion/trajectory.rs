@@ -0,0 +1,447 @@
+//! Structures related to `Trajectory`
+//!
+//! Trajectory is keyframes of position, size and intensity of a sound.
+//! Bubble field of each block is made from the keyframe at the time of the block.
+//!
+//! # Track
+//! Trajectory is exchanged with other tools as CSV or JSON.
+//! Each keyframe is time in seconds, position, radius as `size` and gain as `intensity`.
+//! ```txt
+//! time,x,y,z,radius,gain
+//! 0,0.5,0.5,0.5,0,1
+//! 2.5,0.8,0.5,0.5,0.1,0.5
+//! ```
+//! In CSV, values are separated by comma or whitespace, `#` starts a comment, and the header is optional.
+//! ```txt
+//! {"interpolation": "linear", "keyframes": [{"time": 0, "x": 0.5, "y": 0.5, "z": 0.5, "radius": 0, "gain": 1}]}
+//! ```
+//! In JSON, `interpolation` is `"linear"` or `"spline"` and optional.
+
+use crate::format::{BubbleField, BubbleFieldSize, FieldPosition};
+use crate::format::bub::{Bubble, BubbleBlock};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::json::Json;
+use std::io::{self, BufReader, Error, ErrorKind, Read, Seek};
+
+/// State of a sound at a time
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Keyframe {
+    /// Position in Bubble field
+    pub position: FieldPosition,
+    /// Radius of the sound in Bubble field.
+    /// Cells whose center is within the radius are filled. The cell which includes the position is always filled.
+    pub size: f64,
+    /// Intensity from 0.0 to 1.0. 1.0 is 255 in Bubble field.
+    pub intensity: f64
+}
+
+impl Default for Keyframe {
+    fn default() -> Self {
+        Self {
+            position: FieldPosition::center(),
+            size: 0.0,
+            intensity: 1.0
+        }
+    }
+}
+
+impl Keyframe {
+    /// This method returns Bubble field of the keyframe.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::motion::trajectory::Keyframe;
+    ///
+    /// let keyframe = Keyframe {
+    ///     position: (0.0, 0.5, 0.5).into(),
+    ///     size: 0.4,
+    ///     intensity: 0.5
+    /// };
+    ///
+    /// // width 4
+    /// let bub_field = keyframe.bub_field((0u8, 2u8, 0u8).into()).unwrap();
+    ///
+    /// assert_eq!(bub_field, vec![vec![vec![128], vec![128], vec![0], vec![0]]].into());
+    /// ```
+    pub fn bub_field(&self, bub_field_size: BubbleFieldSize) -> Result<BubbleField, &'static str> {
+        let mut bub_field = BubbleField::from_position(self.position, bub_field_size)?;
+        let value = (self.intensity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for (l, plane) in bub_field.values_mut().iter_mut().enumerate() {
+            for (w, column) in plane.iter_mut().enumerate() {
+                for (h, n) in column.iter_mut().enumerate() {
+                    let center = FieldPosition::from_cell((l, w, h), bub_field_size)?;
+                    *n = if *n != 0 || center.distance(self.position) <= self.size {
+                        value
+                    } else {
+                        0
+                    };
+                }
+            }
+        }
+
+        Ok(bub_field)
+    }
+
+    /// This method returns keyframe which has centroid of Bubble field as position,
+    /// the farthest center of cells which aren't 0 from the position as size, and peak as intensity.
+    /// If all values are 0, this returns `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::BubbleField;
+    /// use floaout::motion::trajectory::Keyframe;
+    ///
+    /// let bub_field: BubbleField = vec![vec![vec![0], vec![51], vec![51], vec![0]]].into();
+    /// let keyframe = Keyframe::from_bub_field(&bub_field).unwrap();
+    ///
+    /// assert_eq!(keyframe.position, (0.5, 0.5, 0.5).into());
+    /// assert_eq!(keyframe.size, 0.125);
+    /// assert_eq!(keyframe.intensity, 0.2);
+    /// ```
+    pub fn from_bub_field(bub_field: &BubbleField) -> Option<Self> {
+        let position = bub_field.centroid()?;
+        let values = bub_field.values();
+        let (length, width) = (values.len() as f64, values.first().map_or(0, Vec::len) as f64);
+        let mut size = 0.0f64;
+        for (l, plane) in values.iter().enumerate() {
+            for (w, column) in plane.iter().enumerate() {
+                let height = column.len() as f64;
+                for (h, _) in column.iter().enumerate().filter(|&(_, &n)| n != 0) {
+                    let center: FieldPosition = ((w as f64 + 0.5) / width, (l as f64 + 0.5) / length, (h as f64 + 0.5) / height).into();
+                    size = size.max(center.distance(position));
+                }
+            }
+        }
+
+        Some(
+            Self {
+                position,
+                size,
+                intensity: bub_field.peak() as f64 / 255.0
+            }
+        )
+    }
+
+    fn values(self) -> [f64; 5] {
+        [self.position.x, self.position.y, self.position.z, self.size, self.intensity]
+    }
+
+    fn from_values(values: [f64; 5]) -> Self {
+        Self {
+            position: (values[0], values[1], values[2]).into(),
+            size: values[3].max(0.0),
+            intensity: values[4].clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// This enum is how keyframes are interpolated.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Interpolation {
+    /// Values change linearly.
+    #[default]
+    Linear,
+    /// Values follow Catmull-Rom spline which passes through every keyframe.
+    Spline
+}
+
+/// Keyframes of `Keyframe`. Each is `(seconds, keyframe)` in the order of time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trajectory {
+    /// Keyframes
+    pub keyframes: Vec<(f64, Keyframe)>,
+    /// How keyframes are interpolated.
+    pub interpolation: Interpolation
+}
+
+impl Interpolation {
+    fn name(self) -> &'static str {
+        match self {
+            Interpolation::Linear => "linear",
+            Interpolation::Spline => "spline"
+        }
+    }
+}
+
+impl From<Vec<(f64, Keyframe)>> for Trajectory {
+    fn from(keyframes: Vec<(f64, Keyframe)>) -> Self {
+        Self {
+            keyframes,
+            ..Default::default()
+        }
+    }
+}
+
+impl Trajectory {
+    /// This method returns keyframe at the time interpolating keyframes.
+    /// Before the first keyframe and after the last keyframe, keyframe is held.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::motion::trajectory::{Interpolation, Keyframe, Trajectory};
+    ///
+    /// let keyframe = |x: f64| Keyframe {
+    ///     position: (x, 0.5, 0.5).into(),
+    ///     ..Default::default()
+    /// };
+    /// let mut trajectory = Trajectory::from(vec![(0.0, keyframe(0.0)), (1.0, keyframe(1.0)), (2.0, keyframe(0.0))]);
+    ///
+    /// assert_eq!(trajectory.keyframe_at(0.5).position.x, 0.5);
+    /// assert_eq!(trajectory.keyframe_at(3.0).position.x, 0.0);
+    ///
+    /// trajectory.interpolation = Interpolation::Spline;
+    /// assert_eq!(trajectory.keyframe_at(1.0).position.x, 1.0);
+    /// assert!(trajectory.keyframe_at(0.5).position.x > 0.5);
+    /// ```
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        let keyframes = &self.keyframes;
+        let i = match keyframes.iter().position(|&(t, _)| t > time) {
+            None => return keyframes.last().map(|&(_, keyframe)| keyframe).unwrap_or_default(),
+            Some(0) => return keyframes[0].1,
+            Some(i) => i
+        };
+        let ((t0, k0), (t1, k1)) = (keyframes[i - 1], keyframes[i]);
+        let t = (time - t0) / (t1 - t0);
+        let (p1, p2) = (k0.values(), k1.values());
+        let mut values = [0.0; 5];
+        match self.interpolation {
+            Interpolation::Linear => {
+                for (j, n) in values.iter_mut().enumerate() {
+                    *n = p1[j] + (p2[j] - p1[j]) * t;
+                }
+            },
+            Interpolation::Spline => {
+                // Ends are extended by repeating the first and the last keyframes.
+                let p0 = keyframes[i.saturating_sub(2)].1.values();
+                let p3 = keyframes[(i + 1).min(keyframes.len() - 1)].1.values();
+                let (t2, t3) = (t * t, t * t * t);
+                for (j, n) in values.iter_mut().enumerate() {
+                    *n = 0.5 * (
+                        2.0 * p1[j]
+                        + (p2[j] - p0[j]) * t
+                        + (2.0 * p0[j] - 5.0 * p1[j] + 4.0 * p2[j] - p3[j]) * t2
+                        + (3.0 * p1[j] - p0[j] - 3.0 * p2[j] + p3[j]) * t3
+                    );
+                }
+            }
+        }
+
+        Keyframe::from_values(values)
+    }
+
+    /// This method returns Bubble field of the block at the sampling rate.
+    pub fn bub_field(&self, block: u64, sampling_rate: u32, bub_field_size: BubbleFieldSize) -> Result<BubbleField, &'static str> {
+        self.keyframe_at(block as f64 / sampling_rate as f64).bub_field(bub_field_size)
+    }
+
+    /// This method parses track written in CSV.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::motion::trajectory::Trajectory;
+    ///
+    /// let trajectory = Trajectory::from_csv("
+    ///     time,x,y,z,radius,gain
+    ///     0.0, 0.5, 0.5, 0.5, 0.0, 1.0
+    ///     2.0, 0.8, 0.5, 0.5, 0.1, 0.5
+    /// ").unwrap();
+    ///
+    /// assert_eq!(trajectory.keyframes.len(), 2);
+    /// assert_eq!(trajectory.keyframes[1].1.size, 0.1);
+    /// ```
+    pub fn from_csv(s: &str) -> io::Result<Self> {
+        let mut keyframes: Vec<(f64, Keyframe)> = Vec::new();
+        let mut header = true;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, msg));
+            let tokens: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty()).collect();
+            // The first line may be names of columns.
+            if std::mem::take(&mut header) && tokens.iter().all(|token| token.parse::<f64>().is_err()) {
+                continue;
+            }
+            let values = tokens.iter()
+                .map(|token| token.parse::<f64>().map_err(|_| invalid("invalid number")))
+                .collect::<io::Result<Vec<f64>>>()?;
+            if values.len() != 6 {
+                return Err(invalid("keyframe needs 6 values"));
+            }
+            let keyframe = Self::keyframe_from_values(&values, keyframes.last()).map_err(invalid)?;
+            keyframes.push(keyframe);
+        }
+
+        Ok(keyframes.into())
+    }
+
+    /// This method writes track in CSV with the header.
+    pub fn to_csv(&self) -> String {
+        let mut s = String::from("time,x,y,z,radius,gain\n");
+        for (time, keyframe) in &self.keyframes {
+            let FieldPosition { x, y, z } = keyframe.position;
+            s += &format!("{},{},{},{},{},{}\n", time, x, y, z, keyframe.size, keyframe.intensity);
+        }
+
+        s
+    }
+
+    /// This method parses track written in JSON.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::motion::trajectory::{Interpolation, Trajectory};
+    ///
+    /// let trajectory = Trajectory::from_json(r#"{"interpolation": "spline", "keyframes": [
+    ///     {"time": 0, "x": 0.5, "y": 0.5, "z": 0.5, "radius": 0, "gain": 1},
+    ///     {"time": 2, "x": 0.8, "y": 0.5, "z": 0.5, "radius": 0.1, "gain": 0.5}
+    /// ]}"#).unwrap();
+    ///
+    /// assert_eq!(trajectory.interpolation, Interpolation::Spline);
+    /// assert_eq!(Trajectory::from_json(&trajectory.to_json()).unwrap(), trajectory);
+    /// ```
+    pub fn from_json(s: &str) -> io::Result<Self> {
+        let json = Json::parse(s)?;
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let interpolation = match json.get("interpolation") {
+            None => Interpolation::Linear,
+            Some(value) => match value.as_str() {
+                Some("linear") => Interpolation::Linear,
+                Some("spline") => Interpolation::Spline,
+                _ => return Err(invalid("`interpolation` must be \"linear\" or \"spline\".".into()))
+            }
+        };
+        let values = json.get("keyframes").and_then(Json::as_array).ok_or_else(|| invalid("`keyframes` must be array.".into()))?;
+        let mut keyframes: Vec<(f64, Keyframe)> = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            let number = |key: &str| value.get(key).and_then(Json::as_f64).ok_or_else(|| invalid(format!("keyframe {}: `{}` must be number.", i, key)));
+            let values = [number("time")?, number("x")?, number("y")?, number("z")?, number("radius")?, number("gain")?];
+            let keyframe = Self::keyframe_from_values(&values, keyframes.last()).map_err(|msg| invalid(format!("keyframe {}: {}", i, msg)))?;
+            keyframes.push(keyframe);
+        }
+
+        Ok(
+            Self {
+                keyframes,
+                interpolation
+            }
+        )
+    }
+
+    /// This method writes track in JSON.
+    pub fn to_json(&self) -> String {
+        let keyframes = self.keyframes.iter().map(|(time, keyframe)| {
+            let FieldPosition { x, y, z } = keyframe.position;
+            Json::Object(vec![
+                ("time".into(), (*time).into()),
+                ("x".into(), x.into()),
+                ("y".into(), y.into()),
+                ("z".into(), z.into()),
+                ("radius".into(), keyframe.size.into()),
+                ("gain".into(), keyframe.intensity.into())
+            ])
+        }).collect();
+
+        Json::Object(vec![
+            ("interpolation".into(), self.interpolation.name().into()),
+            ("keyframes".into(), Json::Array(keyframes))
+        ]).to_string()
+    }
+
+    /// This function checks `[time, x, y, z, radius, gain]` and returns keyframe.
+    fn keyframe_from_values(values: &[f64], last: Option<&(f64, Keyframe)>) -> Result<(f64, Keyframe), &'static str> {
+        if !values.iter().all(|n| n.is_finite()) {
+            return Err("value must be finite");
+        }
+        if let Some(&(time, _)) = last {
+            if values[0] <= time {
+                return Err("time must increase");
+            }
+        }
+        if values[4] < 0.0 {
+            return Err("radius must not be negative");
+        }
+        if !(0.0..=1.0).contains(&values[5]) {
+            return Err("gain must be from 0 to 1");
+        }
+
+        Ok((
+            values[0],
+            Keyframe {
+                position: (values[1], values[2], values[3]).into(),
+                size: values[4],
+                intensity: values[5]
+            }
+        ))
+    }
+
+    /// This method adds keyframe of a block.
+    /// Keyframe which is same as the previous two is merged, so held parts don't make many keyframes.
+    fn push_block(&mut self, time: f64, keyframe: Keyframe) {
+        let n = self.keyframes.len();
+        if n >= 2 && self.keyframes[n - 1].1 == keyframe && self.keyframes[n - 2].1 == keyframe {
+            self.keyframes[n - 1].0 = time;
+        } else {
+            self.keyframes.push((time, keyframe));
+        }
+    }
+}
+
+/// This function returns keyframe of a block.
+/// Silent Bubble field keeps the previous position with intensity 0.
+fn block_keyframe(bub_field: &BubbleField, last: Option<&(f64, Keyframe)>) -> Keyframe {
+    Keyframe::from_bub_field(bub_field).unwrap_or_else(|| {
+        Keyframe {
+            intensity: 0.0,
+            size: 0.0,
+            ..last.map(|&(_, keyframe)| keyframe).unwrap_or_default()
+        }
+    })
+}
+
+/// This function reads Bubble and returns trajectory made from Bubble field of each block.
+///
+/// # Examples
+/// ```no_run
+/// use std::io;
+/// use std::fs::{File, write};
+/// use floaout::motion::trajectory::extract_bub;
+///
+/// fn main() -> io::Result<()> {
+///     let mut reader = io::BufReader::new(File::open("foo.bub")?);
+///
+///     let trajectory = extract_bub(&mut reader)?;
+///     write("foo.csv", trajectory.to_csv())?;
+///
+///     Ok(())
+/// }
+/// ```
+pub fn extract_bub<R: Read + Seek>(reader: &mut BufReader<R>) -> io::Result<Trajectory> {
+    let bub: Bubble = reader.read_details()?;
+    let mut trajectory = Trajectory::default();
+    for i in 0..bub.blocks {
+        let bub_block: BubbleBlock = reader.read_block(&bub)?;
+        let keyframe = block_keyframe(&bub_block.bub_field, trajectory.keyframes.last());
+        trajectory.push_block(i as f64 / bub.sampling_rate as f64, keyframe);
+    }
+
+    Ok(trajectory)
+}
+
+/// This function reads Floaout and returns trajectory of each Bubble in order of `BubblesInFloaout`.
+pub fn extract_oao<R: Read + Seek>(reader: &mut BufReader<R>) -> io::Result<Vec<Trajectory>> {
+    let oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+    let mut trajectories = vec![Trajectory::default(); oao.bubbles as usize];
+    for i in 0..oao.blocks {
+        let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+        for (trajectory, bub_block) in trajectories.iter_mut().zip(&oao_block.0) {
+            let keyframe = block_keyframe(&bub_block.bub_field, trajectory.keyframes.last());
+            trajectory.push_block(i as f64 / oao.sampling_rate as f64, keyframe);
+        }
+    }
+
+    Ok(trajectories)
+}
\ No newline at end of file
@@ -0,0 +1,125 @@
+//! Structures related to distance of Bubble
+//!
+//! When a Bubble moves, gain and delay follow the distance between the listener and the centroid of Bubble field.
+//! Changing delay makes Doppler pitch shift.
+//! Distance is normalized by Bubble field, as same as `FieldPosition`.
+
+/// This enum is how gain decreases with distance.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub enum DistanceModel {
+    /// Gain is always 1.
+    #[default]
+    None,
+    /// Gain is `reference / (reference + rolloff * (distance - reference))`.
+    /// It's 1 within reference.
+    Inverse {
+        /// Distance where gain is 1.
+        reference: f64,
+        /// How fast gain decreases.
+        rolloff: f64
+    },
+    /// Gain is `1 - rolloff * (distance - reference) / (max - reference)`.
+    /// It's 1 within reference and it's held beyond max.
+    Linear {
+        /// Distance where gain is 1.
+        reference: f64,
+        /// Distance where gain stops decreasing.
+        max: f64,
+        /// How much gain decreases until max.
+        rolloff: f64
+    },
+    /// Points of `(distance, gain)` in the order of distance.
+    /// Gain is interpolated linearly and held beyond both ends.
+    Custom(Vec<(f64, f64)>)
+}
+
+impl DistanceModel {
+    /// This method returns gain at the distance.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::distance::DistanceModel;
+    ///
+    /// let inverse = DistanceModel::Inverse { reference: 0.25, rolloff: 1.0 };
+    /// assert_eq!(inverse.gain(0.1), 1.0);
+    /// assert_eq!(inverse.gain(0.5), 0.5);
+    ///
+    /// let custom = DistanceModel::Custom(vec![(0.0, 1.0), (1.0, 0.0)]);
+    /// assert_eq!(custom.gain(0.25), 0.75);
+    /// assert_eq!(custom.gain(2.0), 0.0);
+    /// ```
+    pub fn gain(&self, distance: f64) -> f64 {
+        match self {
+            DistanceModel::None => 1.0,
+            DistanceModel::Inverse { reference, rolloff } => {
+                let distance = distance.max(*reference);
+                reference / (reference + rolloff * (distance - reference))
+            },
+            DistanceModel::Linear { reference, max, rolloff } => {
+                let distance = distance.max(*reference).min(*max);
+                if max > reference {
+                    1.0 - rolloff * (distance - reference) / (max - reference)
+                } else {
+                    1.0
+                }
+            },
+            DistanceModel::Custom(points) => {
+                match points.iter().position(|&(d, _)| d > distance) {
+                    None => points.last().map(|&(_, gain)| gain).unwrap_or(1.0),
+                    Some(0) => points[0].1,
+                    Some(i) => {
+                        let ((d0, g0), (d1, g1)) = (points[i - 1], points[i]);
+                        g0 + (g1 - g0) * (distance - d0) / (d1 - d0)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// This structure is scale for Doppler.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Doppler {
+    /// Meters which is distance 1.0 in Bubble field.
+    pub meters: f64,
+    /// Speed of sound in meters per second
+    pub speed_of_sound: f64
+}
+
+impl Default for Doppler {
+    fn default() -> Self {
+        Self {
+            meters: 10.0,
+            speed_of_sound: 343.0
+        }
+    }
+}
+
+impl Doppler {
+    /// This method returns delay in samples at the distance.
+    pub fn delay(self, distance: f64, sampling_rate: u32) -> f64 {
+        distance * self.meters / self.speed_of_sound * sampling_rate as f64
+    }
+}
+
+/// This structure is setting of distance for each Bubble.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct BubbleMotion {
+    /// How gain decreases with distance.
+    pub distance_model: DistanceModel,
+    /// If this is `Some`, delay follows distance and makes Doppler pitch shift.
+    pub doppler: Option<Doppler>,
+    /// Time constant in seconds to smooth distance.
+    /// Bubble field moves by cell, so this avoids clicks.
+    pub smoothing: f64
+}
+
+impl Default for BubbleMotion {
+    fn default() -> Self {
+        Self {
+            distance_model: DistanceModel::None,
+            doppler: None,
+            smoothing: 0.02
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,226 @@
+//! Structures related to `StereoDownmix`
+//!
+//! Stereo downmix is a quick way to listen to Floaout without speaker layout.
+//! Each Bubble is panned by the balance of left and right in Bubble field.
+
+use crate::format::{BubbleField, Sample};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::format::wav::{Wav, WavBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteFmt};
+use std::f64::consts::FRAC_PI_2;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// This enum is pan law.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PanLaw {
+    /// Center is -3 dB.
+    ConstantPower,
+    /// Center is -4.5 dB. This is between constant power and linear.
+    Compromise,
+    /// Center is -6 dB.
+    Linear
+}
+
+impl PanLaw {
+    /// This method returns gains of left and right.
+    /// Balance is 0.0 at left and 1.0 at right.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::downmix::PanLaw;
+    ///
+    /// let (left, right) = PanLaw::Linear.gains(0.5);
+    /// assert_eq!((left, right), (0.5, 0.5));
+    ///
+    /// let (left, right) = PanLaw::ConstantPower.gains(0.0);
+    /// assert_eq!((left, right), (1.0, 0.0));
+    /// ```
+    pub fn gains(self, balance: f64) -> (f64, f64) {
+        let p = balance.clamp(0.0, 1.0);
+        match self {
+            PanLaw::ConstantPower => ((p * FRAC_PI_2).cos(), (p * FRAC_PI_2).sin()),
+            PanLaw::Compromise => (((1.0 - p) * (p * FRAC_PI_2).cos()).sqrt(), (p * (p * FRAC_PI_2).sin()).sqrt()),
+            PanLaw::Linear => (1.0 - p, p)
+        }
+    }
+}
+
+/// Peak limiter which has no latency.
+/// Gain goes down at once and comes back during release.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Limiter {
+    /// Threshold in dBFS
+    pub threshold: f64,
+    /// Release time in seconds
+    pub release: f64,
+    gain: f64
+}
+
+impl Limiter {
+    /// This method casts threshold in dBFS and release time in seconds to `Limiter`.
+    pub fn new(threshold: f64, release: f64) -> Self {
+        Self {
+            threshold,
+            release,
+            gain: 1.0
+        }
+    }
+
+    /// This method limits samples of all channels at the same time.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::downmix::Limiter;
+    ///
+    /// let mut limiter = Limiter::new(0.0, 0.1);
+    /// let mut frame = [2.0, -0.5];
+    /// limiter.process(&mut frame, 44100);
+    ///
+    /// assert_eq!(frame, [1.0, -0.25]);
+    /// ```
+    pub fn process(&mut self, frame: &mut [f64], sampling_rate: u32) {
+        let threshold = 10f64.powf(self.threshold / 20.0);
+        let release = 1.0 - (-1.0 / (self.release * sampling_rate as f64).max(1.0)).exp();
+        self.gain += (1.0 - self.gain) * release;
+        let peak = frame.iter().fold(0.0f64, |peak, n| peak.max(n.abs()));
+        if peak * self.gain > threshold {
+            self.gain = threshold / peak;
+        }
+        frame.iter_mut().for_each(|n| *n *= self.gain);
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(-0.3, 0.05)
+    }
+}
+
+/// This structure is setting of stereo downmix.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct StereoDownmix {
+    /// Pan law
+    pub pan_law: PanLaw,
+    /// Attenuation in dB at the top and bottom of Bubble field.
+    /// It's 0 at the middle of height.
+    pub height: f64,
+    /// Attenuation in dB at the back of Bubble field.
+    /// It's 0 in the front half.
+    pub depth: f64,
+    /// Limiter after Bubbles are summed
+    pub limiter: Option<Limiter>
+}
+
+impl Default for StereoDownmix {
+    fn default() -> Self {
+        Self {
+            pan_law: PanLaw::ConstantPower,
+            height: 0.0,
+            depth: 0.0,
+            limiter: None
+        }
+    }
+}
+
+impl StereoDownmix {
+    // Balance, height and depth of Bubble field from 0.0 to 1.0
+    // Each cell is at its center, as same as `BubbleField::centroid`.
+    fn balance(bub_field: &BubbleField) -> Option<(f64, f64, f64)> {
+        let field = bub_field.values();
+        let position = |i: usize, len: usize| (i as f64 + 0.5) / len as f64;
+        let mut sum = (0.0, 0.0, 0.0, 0.0);
+        for (l, plane) in field.iter().enumerate() {
+            for (w, column) in plane.iter().enumerate() {
+                for (h, &n) in column.iter().enumerate() {
+                    let n = n as f64;
+                    sum.0 += n * position(w, plane.len());
+                    sum.1 += n * (position(h, column.len()) - 0.5).abs() * 2.0;
+                    sum.2 += n * (0.5 - position(l, field.len())).max(0.0) * 2.0;
+                    sum.3 += n;
+                }
+            }
+        }
+        if sum.3 == 0.0 {
+            None
+        } else {
+            Some((sum.0 / sum.3, sum.1 / sum.3, sum.2 / sum.3))
+        }
+    }
+
+    /// This method downmixes Floaout block to left and right without limiter.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::bub::BubbleBlock;
+    /// use floaout::format::oao::FloaoutBlock;
+    /// use floaout::render::downmix::{PanLaw, StereoDownmix};
+    ///
+    /// let downmix = StereoDownmix {
+    ///     pan_law: PanLaw::Linear,
+    ///     ..Default::default()
+    /// };
+    /// // Bubble at the right quarter
+    /// let bub_block = BubbleBlock::from_wav_block_and_bub_field(1.0f32.into(), vec![vec![vec![0], vec![255]]].into());
+    ///
+    /// assert_eq!(downmix.downmix_block(&FloaoutBlock::from(vec![bub_block])), (0.25, 0.75));
+    /// ```
+    pub fn downmix_block(&self, oao_block: &FloaoutBlock) -> (f64, f64) {
+        oao_block.0.iter().fold((0.0, 0.0), |(left, right), bub_block| {
+            match Self::balance(&bub_block.bub_field) {
+                None => (left, right),
+                Some((balance, height, depth)) => {
+                    let sample: f64 = bub_block.wav_block.into();
+                    let db = -self.height.abs() * height - self.depth.abs() * depth;
+                    let sample = sample * bub_block.bub_field.peak() as f64 / 255.0 * 10f64.powf(db / 20.0);
+                    let (l, r) = self.pan_law.gains(balance);
+                    (left + sample * l, right + sample * r)
+                }
+            }
+        })
+    }
+
+    /// This method reads Floaout and writes stereo Wav.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::io;
+    /// use std::fs::File;
+    /// use floaout::render::downmix::{Limiter, StereoDownmix};
+    ///
+    /// fn main() -> io::Result<()> {
+    ///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+    ///     let mut writer = io::BufWriter::new(File::create("foo.wav")?);
+    ///     let downmix = StereoDownmix {
+    ///         limiter: Some(Limiter::default()),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     // downmix Floaout to stereo Wav
+    ///     downmix.downmix(&mut reader, &mut writer)?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn downmix<R: Read + Seek, W: Write>(&self, reader: &mut BufReader<R>, writer: &mut BufWriter<W>) -> Result<Wav> {
+        let oao: Floaout = reader.read_details()?;
+        let _: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+        let wav = Wav::from_format(2, oao.sampling_rate, oao.bits_per_sample, oao.blocks)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        writer.write_details(&wav)?;
+        let mut limiter = self.limiter;
+        for _ in 0..oao.blocks {
+            let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+            let (left, right) = self.downmix_block(&oao_block);
+            let mut frame = [left, right];
+            if let Some(limiter) = &mut limiter {
+                limiter.process(&mut frame, oao.sampling_rate);
+            }
+            for &n in &frame {
+                writer.write_block(&wav, WavBlock(Sample::from_f64_and_bits_per_sample(n, oao.bits_per_sample)))?;
+            }
+        }
+
+        Ok(wav)
+    }
+}
\ No newline at end of file
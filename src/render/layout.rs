@@ -0,0 +1,485 @@
+//! Structures related to `SpeakerLayout`
+//!
+//! Speaker layout describes a playback setup.
+//! It can be written in text or JSON.
+//!
+//! # Text
+//! Each line is one speaker. `#` starts a comment.
+//! ```txt
+//! # name  position                                  options
+//! L       polar <azimuth> <elevation> [<distance>]  [lfe] [delay=<ms>] [trim=<dB>]
+//! Top     field <x> <y> <z>                         [lfe] [delay=<ms>] [trim=<dB>]
+//! ```
+//!
+//! # JSON
+//! ```txt
+//! {"speakers": [
+//!     {"name": "L", "azimuth": 30, "elevation": 0, "distance": 1, "lfe": false, "delay": 0, "trim": 0},
+//!     {"name": "Top", "x": 0.5, "y": 0.5, "z": 1}
+//! ]}
+//! ```
+
+use crate::format::{BubbleFieldSize, FieldPosition};
+use crate::json::Json;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+
+/// Position of speaker.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum SpeakerPosition {
+    /// Position normalized in Bubble field.
+    Field(FieldPosition),
+    /// Position around center of Bubble field.
+    Polar {
+        /// Azimuth in degrees. 0 is front and positive is left.
+        azimuth: f64,
+        /// Elevation in degrees. Positive is upward.
+        elevation: f64,
+        /// Distance from center. 1.0 reaches the nearest side of Bubble field.
+        distance: f64
+    }
+}
+
+impl SpeakerPosition {
+    /// This method casts azimuth and elevation to `SpeakerPosition` whose distance is 1.0.
+    pub fn from_azimuth_and_elevation(azimuth: f64, elevation: f64) -> Self {
+        SpeakerPosition::Polar {
+            azimuth,
+            elevation,
+            distance: 1.0
+        }
+    }
+
+    /// This method returns position in Bubble field.
+    /// Position out of the field is clamped.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerPosition;
+    ///
+    /// let front = SpeakerPosition::from_azimuth_and_elevation(0.0, 0.0).to_field_position();
+    ///
+    /// assert_eq!(front, (0.5, 1.0, 0.5).into());
+    /// ```
+    pub fn to_field_position(self) -> FieldPosition {
+        match self {
+            SpeakerPosition::Field(pos) => pos,
+            SpeakerPosition::Polar { azimuth, elevation, distance } => {
+                let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+                let r = distance * 0.5;
+                let clamp = |n: f64| n.clamp(0.0, 1.0);
+                FieldPosition {
+                    x: clamp(0.5 - r * elevation.cos() * azimuth.sin()),
+                    y: clamp(0.5 + r * elevation.cos() * azimuth.cos()),
+                    z: clamp(0.5 + r * elevation.sin())
+                }
+            }
+        }
+    }
+}
+
+/// Details of speaker.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Speaker {
+    /// Name of speaker. It doesn't contain whitespace.
+    pub name: String,
+    /// Position of speaker
+    pub position: SpeakerPosition,
+    /// Whether this speaker is Low Frequency Effects.
+    pub lfe: bool,
+    /// Delay in milliseconds
+    pub delay: f64,
+    /// Trim in decibels
+    pub trim: f64
+}
+
+impl Speaker {
+    /// This method casts name and position to `Speaker`.
+    pub fn from_name_and_position(name: &str, position: SpeakerPosition) -> Self {
+        Self {
+            name: name.into(),
+            position,
+            lfe: false,
+            delay: 0.0,
+            trim: 0.0
+        }
+    }
+
+    /// This method returns index of the cell where this speaker is.
+    /// Index is `(length, width, height)` as same as `BubbleField`.
+    pub fn cell(&self, bub_field_size: BubbleFieldSize) -> std::result::Result<(usize, usize, usize), &'static str> {
+        self.position.to_field_position().cell(bub_field_size)
+    }
+}
+
+/// This structure is playback setup.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct SpeakerLayout {
+    /// Speakers in the order of channels.
+    pub speakers: Vec<Speaker>
+}
+
+impl SpeakerLayout {
+    fn from_polar(speakers: &[(&str, f64, f64)], lfe: &[&str]) -> Self {
+        Self {
+            speakers: speakers.iter().map(|&(name, azimuth, elevation)| {
+                let mut speaker = Speaker::from_name_and_position(name, SpeakerPosition::from_azimuth_and_elevation(azimuth, elevation));
+                speaker.lfe = lfe.contains(&name);
+                speaker
+            }).collect()
+        }
+    }
+
+    /// This method returns stereo layout. (ITU-R BS.2051 0+2+0)
+    pub fn stereo() -> Self {
+        Self::from_polar(&[("L", 30.0, 0.0), ("R", -30.0, 0.0)], &[])
+    }
+
+    /// This method returns 5.1 layout. (ITU-R BS.2051 0+5+0)
+    pub fn surround_5_1() -> Self {
+        Self::from_polar(
+            &[
+                ("L", 30.0, 0.0), ("R", -30.0, 0.0), ("C", 0.0, 0.0), ("LFE", 45.0, -30.0),
+                ("Ls", 110.0, 0.0), ("Rs", -110.0, 0.0)
+            ],
+            &["LFE"]
+        )
+    }
+
+    /// This method returns 7.1.4 layout. (ITU-R BS.2051 4+7+0)
+    pub fn surround_7_1_4() -> Self {
+        Self::from_polar(
+            &[
+                ("L", 30.0, 0.0), ("R", -30.0, 0.0), ("C", 0.0, 0.0), ("LFE", 45.0, -30.0),
+                ("Lss", 90.0, 0.0), ("Rss", -90.0, 0.0), ("Lrs", 135.0, 0.0), ("Rrs", -135.0, 0.0),
+                ("Ltf", 45.0, 30.0), ("Rtf", -45.0, 30.0), ("Ltb", 135.0, 30.0), ("Rtb", -135.0, 30.0)
+            ],
+            &["LFE"]
+        )
+    }
+
+    /// This method returns 22.2 layout. (ITU-R BS.2051 9+10+3)
+    pub fn surround_22_2() -> Self {
+        Self::from_polar(
+            &[
+                ("FL", 60.0, 0.0), ("FR", -60.0, 0.0), ("FC", 0.0, 0.0), ("LFE1", 45.0, -30.0),
+                ("BL", 135.0, 0.0), ("BR", -135.0, 0.0), ("FLc", 30.0, 0.0), ("FRc", -30.0, 0.0),
+                ("BC", 180.0, 0.0), ("LFE2", -45.0, -30.0), ("SiL", 90.0, 0.0), ("SiR", -90.0, 0.0),
+                ("TpFL", 45.0, 30.0), ("TpFR", -45.0, 30.0), ("TpFC", 0.0, 30.0), ("TpC", 0.0, 90.0),
+                ("TpBL", 135.0, 30.0), ("TpBR", -135.0, 30.0), ("TpSiL", 90.0, 30.0), ("TpSiR", -90.0, 30.0),
+                ("TpBC", 180.0, 30.0), ("BtFC", 0.0, -30.0), ("BtFL", 45.0, -30.0), ("BtFR", -45.0, -30.0)
+            ],
+            &["LFE1", "LFE2"]
+        )
+    }
+
+    /// This method returns layout from channel mask of WAVE_FORMAT_EXTENSIBLE.
+    /// Speakers are in the order of bits, which is the order of channels in Wav.
+    /// If there is no known bit, this returns `None`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// // 5.1 (side)
+    /// let layout = SpeakerLayout::from_channel_mask(0x60f).unwrap();
+    /// let names: Vec<&str> = layout.speakers.iter().map(|speaker| speaker.name.as_str()).collect();
+    ///
+    /// assert_eq!(names, vec!["L", "R", "C", "LFE", "Ls", "Rs"]);
+    /// ```
+    pub fn from_channel_mask(channel_mask: u32) -> Option<Self> {
+        // Surround is "Ls" and "Rs" unless there are both back and side.
+        let (back, side) = if channel_mask & 0x30 != 0 && channel_mask & 0x600 != 0 {
+            (("Lrs", "Rrs", 135.0), ("Lss", "Rss", 90.0))
+        } else {
+            (("Ls", "Rs", 110.0), ("Ls", "Rs", 110.0))
+        };
+        let bits = [
+            ("L", 30.0, 0.0), ("R", -30.0, 0.0), ("C", 0.0, 0.0), ("LFE", 45.0, -30.0),
+            (back.0, back.2, 0.0), (back.1, -back.2, 0.0), ("Lc", 15.0, 0.0), ("Rc", -15.0, 0.0),
+            ("Cs", 180.0, 0.0), (side.0, side.2, 0.0), (side.1, -side.2, 0.0), ("Tc", 0.0, 90.0),
+            ("Ltf", 45.0, 30.0), ("Ctf", 0.0, 30.0), ("Rtf", -45.0, 30.0),
+            ("Ltb", 135.0, 30.0), ("Ctb", 180.0, 30.0), ("Rtb", -135.0, 30.0)
+        ];
+        let speakers: Vec<(&str, f64, f64)> = bits.iter().enumerate()
+            .filter(|&(i, _)| channel_mask & (1 << i) != 0)
+            .map(|(_, &speaker)| speaker)
+            .collect();
+        if speakers.is_empty() {
+            None
+        } else {
+            Some(Self::from_polar(&speakers, &["LFE"]))
+        }
+    }
+
+    /// This method returns layout which is default for the number of channels in Wav.
+    /// Known numbers are 1 (mono), 2 (stereo), 3, 4 (quad), 5, 6 (5.1) and 8 (7.1).
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// assert_eq!(SpeakerLayout::from_channels(4).unwrap().speakers[2].name, "Ls");
+    /// assert!(SpeakerLayout::from_channels(7).is_none());
+    /// ```
+    pub fn from_channels(channels: u16) -> Option<Self> {
+        let channel_mask = match channels {
+            1 => 0x4,
+            2 => 0x3,
+            3 => 0x7,
+            4 => 0x33,
+            5 => 0x37,
+            6 => 0x3f,
+            8 => 0x63f,
+            _ => return None
+        };
+
+        Self::from_channel_mask(channel_mask)
+    }
+
+    /// This method returns built-in layout from name.
+    /// Names are "stereo", "quad", "5.1", "7.1", "7.1.4" and "22.2".
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let layout = SpeakerLayout::preset("7.1.4").unwrap();
+    ///
+    /// assert_eq!(layout.speakers.len(), 12);
+    /// ```
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "stereo" | "2.0" => Some(Self::stereo()),
+            "quad" | "4.0" => Self::from_channels(4),
+            "5.1" => Some(Self::surround_5_1()),
+            "7.1" => Self::from_channels(8),
+            "7.1.4" => Some(Self::surround_7_1_4()),
+            "22.2" => Some(Self::surround_22_2()),
+            _ => None
+        }
+    }
+
+    /// This method checks whether the layout is playable.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::{Speaker, SpeakerLayout, SpeakerPosition};
+    ///
+    /// let mut layout = SpeakerLayout::stereo();
+    /// assert!(layout.validate().is_ok());
+    ///
+    /// layout.speakers.push(Speaker::from_name_and_position("L", SpeakerPosition::from_azimuth_and_elevation(0.0, 0.0)));
+    /// assert!(layout.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(Error::new(ErrorKind::InvalidData, msg));
+        if self.speakers.is_empty() {
+            return invalid("Layout has no speaker.".into());
+        }
+        for (i, speaker) in self.speakers.iter().enumerate() {
+            if speaker.name.is_empty() || speaker.name.contains(char::is_whitespace) {
+                return invalid(format!("Speaker {} has invalid name {:?}.", i, speaker.name));
+            }
+            if self.speakers[..i].iter().any(|s| s.name == speaker.name) {
+                return invalid(format!("Speaker name {:?} is duplicated.", speaker.name));
+            }
+            match speaker.position {
+                SpeakerPosition::Field(FieldPosition { x, y, z }) => {
+                    if ![x, y, z].iter().all(|n| (0.0..=1.0).contains(n)) {
+                        return invalid(format!("Position of {:?} is out of Bubble field.", speaker.name));
+                    }
+                },
+                SpeakerPosition::Polar { azimuth, elevation, distance } => {
+                    if !azimuth.is_finite() || !(-90.0..=90.0).contains(&elevation) || distance <= 0.0 || !distance.is_finite() {
+                        return invalid(format!("Position of {:?} is invalid.", speaker.name));
+                    }
+                }
+            }
+            if !(speaker.delay >= 0.0 && speaker.delay.is_finite()) {
+                return invalid(format!("Delay of {:?} is invalid.", speaker.name));
+            }
+            if !speaker.trim.is_finite() {
+                return invalid(format!("Trim of {:?} is invalid.", speaker.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// This method returns index of the cell where each speaker is.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let cells = SpeakerLayout::stereo().cells((1u8, 1u8, 0u8).into()).unwrap();
+    ///
+    /// // Both are front, and L is left.
+    /// assert_eq!(cells, vec![(1, 0, 0), (1, 1, 0)]);
+    /// ```
+    pub fn cells(&self, bub_field_size: BubbleFieldSize) -> std::result::Result<Vec<(usize, usize, usize)>, &'static str> {
+        self.speakers.iter().map(|speaker| speaker.cell(bub_field_size)).collect()
+    }
+
+    /// This method parses layout written in text.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let layout = SpeakerLayout::from_text("
+    ///     L   polar 30 0 # front left
+    ///     R   polar -30 0
+    ///     Sub field 0.5 1 0 lfe delay=1.5 trim=-6
+    /// ").unwrap();
+    ///
+    /// assert_eq!(layout.speakers.len(), 3);
+    /// assert!(layout.speakers[2].lfe);
+    /// ```
+    pub fn from_text(s: &str) -> Result<Self> {
+        let mut speakers = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, msg));
+            let number = |token: Option<&str>| -> Result<f64> {
+                token.ok_or_else(|| invalid("missing number"))?.parse().map_err(|_| invalid("invalid number"))
+            };
+            let mut tokens = line.split_whitespace().peekable();
+            let name = tokens.next().expect("line is not empty");
+            let position = match tokens.next() {
+                Some("polar") => {
+                    let azimuth = number(tokens.next())?;
+                    let elevation = number(tokens.next())?;
+                    let distance = match tokens.peek() {
+                        Some(token) if token.parse::<f64>().is_ok() => number(tokens.next())?,
+                        _ => 1.0
+                    };
+                    SpeakerPosition::Polar { azimuth, elevation, distance }
+                },
+                Some("field") => {
+                    let x = number(tokens.next())?;
+                    let y = number(tokens.next())?;
+                    let z = number(tokens.next())?;
+                    SpeakerPosition::Field((x, y, z).into())
+                },
+                _ => return Err(invalid("position must be `polar` or `field`"))
+            };
+            let mut speaker = Speaker::from_name_and_position(name, position);
+            for token in tokens {
+                match token.split_once('=') {
+                    None if token == "lfe" => speaker.lfe = true,
+                    Some(("delay", n)) => speaker.delay = number(Some(n))?,
+                    Some(("trim", n)) => speaker.trim = number(Some(n))?,
+                    _ => return Err(invalid(&format!("unknown option `{}`", token)))
+                }
+            }
+            speakers.push(speaker);
+        }
+        let layout = Self { speakers };
+        layout.validate()?;
+
+        Ok(layout)
+    }
+
+    /// This method parses layout written in JSON.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let layout = SpeakerLayout::from_json(r#"{"speakers": [
+    ///     {"name": "L", "azimuth": 30, "elevation": 0},
+    ///     {"name": "R", "azimuth": -30, "elevation": 0}
+    /// ]}"#).unwrap();
+    ///
+    /// assert_eq!(layout, SpeakerLayout::stereo());
+    /// ```
+    pub fn from_json(s: &str) -> Result<Self> {
+        let json = Json::parse(s)?;
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let values = json.get("speakers").and_then(Json::as_array).ok_or_else(|| invalid("`speakers` must be array.".into()))?;
+        let mut speakers = Vec::new();
+        for (i, value) in values.iter().enumerate() {
+            let number = |key: &str, default: Option<f64>| match value.get(key) {
+                None => default.ok_or_else(|| invalid(format!("speaker {}: `{}` is missing.", i, key))),
+                Some(n) => n.as_f64().ok_or_else(|| invalid(format!("speaker {}: `{}` must be number.", i, key)))
+            };
+            let name = value.get("name").and_then(Json::as_str).ok_or_else(|| invalid(format!("speaker {}: `name` must be string.", i)))?;
+            let position = if value.get("azimuth").is_some() {
+                SpeakerPosition::Polar {
+                    azimuth: number("azimuth", None)?,
+                    elevation: number("elevation", Some(0.0))?,
+                    distance: number("distance", Some(1.0))?
+                }
+            } else {
+                SpeakerPosition::Field((number("x", None)?, number("y", None)?, number("z", None)?).into())
+            };
+            let mut speaker = Speaker::from_name_and_position(name, position);
+            speaker.lfe = match value.get("lfe") {
+                None => false,
+                Some(lfe) => lfe.as_bool().ok_or_else(|| invalid(format!("speaker {}: `lfe` must be bool.", i)))?
+            };
+            speaker.delay = number("delay", Some(0.0))?;
+            speaker.trim = number("trim", Some(0.0))?;
+            speakers.push(speaker);
+        }
+        let layout = Self { speakers };
+        layout.validate()?;
+
+        Ok(layout)
+    }
+
+    /// This method writes layout in JSON.
+    pub fn to_json(&self) -> String {
+        let speakers = self.speakers.iter().map(|speaker| {
+            let mut members = vec![("name".to_string(), speaker.name.as_str().into())];
+            match speaker.position {
+                SpeakerPosition::Field(FieldPosition { x, y, z }) => {
+                    members.push(("x".into(), x.into()));
+                    members.push(("y".into(), y.into()));
+                    members.push(("z".into(), z.into()));
+                },
+                SpeakerPosition::Polar { azimuth, elevation, distance } => {
+                    members.push(("azimuth".into(), azimuth.into()));
+                    members.push(("elevation".into(), elevation.into()));
+                    members.push(("distance".into(), distance.into()));
+                }
+            }
+            members.push(("lfe".into(), speaker.lfe.into()));
+            members.push(("delay".into(), speaker.delay.into()));
+            members.push(("trim".into(), speaker.trim.into()));
+            Json::Object(members)
+        }).collect();
+
+        Json::Object(vec![("speakers".into(), Json::Array(speakers))]).to_string()
+    }
+}
+
+/// This writes layout in text, which `SpeakerLayout::from_text` can parse.
+impl fmt::Display for SpeakerLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for speaker in &self.speakers {
+            write!(f, "{}", speaker.name)?;
+            match speaker.position {
+                SpeakerPosition::Field(FieldPosition { x, y, z }) => write!(f, " field {} {} {}", x, y, z)?,
+                SpeakerPosition::Polar { azimuth, elevation, distance } => write!(f, " polar {} {} {}", azimuth, elevation, distance)?
+            }
+            if speaker.lfe {
+                write!(f, " lfe")?;
+            }
+            if speaker.delay != 0.0 {
+                write!(f, " delay={}", speaker.delay)?;
+            }
+            if speaker.trim != 0.0 {
+                write!(f, " trim={}", speaker.trim)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,214 @@
+//! Structures related to `Listener`
+//!
+//! Listener can be put anywhere in Bubble field and can turn the head.
+//! Pose is static, or it follows a track such as head-tracking data.
+//!
+//! # Track
+//! Each line is one keyframe. Values are separated by comma or whitespace, and `#` starts a comment.
+//! ```txt
+//! # seconds, x, y, z, yaw, pitch, roll
+//! 0.0, 0.5, 0.5, 0.5, 0, 0, 0
+//! 2.0, 0.5, 0.8, 0.5, 90, 0, 0
+//! ```
+
+use crate::format::FieldPosition;
+use std::io::{Error, ErrorKind, Result};
+
+/// Position and head orientation of listener.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Pose {
+    /// Position in Bubble field
+    pub position: FieldPosition,
+    /// Yaw in degrees. Positive is turning left.
+    pub yaw: f64,
+    /// Pitch in degrees. Positive is looking up.
+    pub pitch: f64,
+    /// Roll in degrees. Positive is tilting right ear down.
+    pub roll: f64
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Self {
+            position: FieldPosition::center(),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0
+        }
+    }
+}
+
+impl Pose {
+    /// This method returns where the position is seen from the listener.
+    /// Return value is `([right, front, up], distance)`, and the first one is unit vector in head.
+    /// If the position is same as listener, the vector is `[0.0, 0.0, 0.0]`.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::listener::Pose;
+    ///
+    /// let pose = Pose {
+    ///     yaw: 90.0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // Front of Bubble field is right side of the listener who turns left.
+    /// let (direction, distance) = pose.relative((0.5, 1.0, 0.5).into());
+    ///
+    /// assert!((direction[0] - 1.0).abs() < 1e-9);
+    /// assert!((distance - 0.5).abs() < 1e-9);
+    /// ```
+    pub fn relative(&self, pos: FieldPosition) -> ([f64; 3], f64) {
+        let (right, front, up) = (pos.x - self.position.x, pos.y - self.position.y, pos.z - self.position.z);
+        let distance = (right * right + front * front + up * up).sqrt();
+        if distance == 0.0 {
+            return ([0.0; 3], 0.0);
+        }
+        let (yaw, pitch, roll) = (self.yaw.to_radians(), self.pitch.to_radians(), self.roll.to_radians());
+        // Yaw
+        let (right, front) = (right * yaw.cos() + front * yaw.sin(), front * yaw.cos() - right * yaw.sin());
+        // Pitch
+        let (front, up) = (front * pitch.cos() + up * pitch.sin(), up * pitch.cos() - front * pitch.sin());
+        // Roll
+        let (right, up) = (right * roll.cos() - up * roll.sin(), right * roll.sin() + up * roll.cos());
+
+        ([right / distance, front / distance, up / distance], distance)
+    }
+
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        // Turn the shorter way.
+        let angle = |a: f64, b: f64| a + ((b - a + 540.0).rem_euclid(360.0) - 180.0) * t;
+        Self {
+            position: (
+                lerp(self.position.x, other.position.x),
+                lerp(self.position.y, other.position.y),
+                lerp(self.position.z, other.position.z)
+            ).into(),
+            yaw: angle(self.yaw, other.yaw),
+            pitch: angle(self.pitch, other.pitch),
+            roll: angle(self.roll, other.roll)
+        }
+    }
+}
+
+/// Keyframes of `Pose`. Each is `(seconds, pose)` in the order of time.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct PoseTrack(pub Vec<(f64, Pose)>);
+
+impl From<Vec<(f64, Pose)>> for PoseTrack {
+    fn from(keyframes: Vec<(f64, Pose)>) -> Self {
+        PoseTrack(keyframes)
+    }
+}
+
+impl From<PoseTrack> for Vec<(f64, Pose)> {
+    fn from(track: PoseTrack) -> Self {
+        track.0
+    }
+}
+
+impl PoseTrack {
+    /// This method returns pose at the time interpolating keyframes linearly.
+    /// Before the first keyframe and after the last keyframe, pose is held.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::listener::{Pose, PoseTrack};
+    ///
+    /// let turned = Pose {
+    ///     yaw: 90.0,
+    ///     ..Default::default()
+    /// };
+    /// let track = PoseTrack::from(vec![(0.0, Pose::default()), (1.0, turned)]);
+    ///
+    /// assert_eq!(track.pose_at(0.5).yaw, 45.0);
+    /// assert_eq!(track.pose_at(2.0).yaw, 90.0);
+    /// ```
+    pub fn pose_at(&self, time: f64) -> Pose {
+        let keyframes = &self.0;
+        match keyframes.iter().position(|&(t, _)| t > time) {
+            None => keyframes.last().map(|&(_, pose)| pose).unwrap_or_default(),
+            Some(0) => keyframes[0].1,
+            Some(i) => {
+                let ((t0, pose0), (t1, pose1)) = (keyframes[i - 1], keyframes[i]);
+                pose0.interpolate(pose1, (time - t0) / (t1 - t0))
+            }
+        }
+    }
+
+    /// This method parses track written in text.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::listener::PoseTrack;
+    ///
+    /// let track = PoseTrack::from_text("
+    ///     0.0, 0.5, 0.5, 0.5, 0, 0, 0
+    ///     2.0, 0.5, 0.8, 0.5, 90, 0, 0
+    /// ").unwrap();
+    ///
+    /// assert_eq!(track.0.len(), 2);
+    /// ```
+    pub fn from_text(s: &str) -> Result<Self> {
+        let mut keyframes: Vec<(f64, Pose)> = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, msg));
+            let values = line.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|token| !token.is_empty())
+                .map(|token| token.parse::<f64>().map_err(|_| invalid("invalid number")))
+                .collect::<Result<Vec<f64>>>()?;
+            if values.len() != 7 {
+                return Err(invalid("keyframe needs 7 values"));
+            }
+            if !values.iter().all(|n| n.is_finite()) {
+                return Err(invalid("value must be finite"));
+            }
+            if let Some(&(last, _)) = keyframes.last() {
+                if values[0] <= last {
+                    return Err(invalid("time must increase"));
+                }
+            }
+            keyframes.push((
+                values[0],
+                Pose {
+                    position: (values[1], values[2], values[3]).into(),
+                    yaw: values[4],
+                    pitch: values[5],
+                    roll: values[6]
+                }
+            ));
+        }
+
+        Ok(keyframes.into())
+    }
+}
+
+/// This enum is how listener moves.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum Listener {
+    /// Listener doesn't move.
+    Static(Pose),
+    /// Listener follows keyframes.
+    Track(PoseTrack)
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Listener::Static(Pose::default())
+    }
+}
+
+impl Listener {
+    /// This method returns pose at the time in seconds.
+    pub fn pose_at(&self, time: f64) -> Pose {
+        match self {
+            Listener::Static(pose) => *pose,
+            Listener::Track(track) => track.pose_at(time)
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,335 @@
+//! Rendering
+//!
+//! This module contains structures related to playing Bubble field back on speakers.
+//!
+//! Directions are in degrees.
+//! Azimuth is 0 at front and positive to the left, elevation is positive upward.
+//! Bubble field is considered as a room whose center is the nominal listening position.
+
+pub mod distance;
+pub mod downmix;
+pub mod layout;
+pub mod listener;
+pub mod room;
+
+use crate::format::{FieldPosition, Sample};
+use crate::format::oao::{BubblesInFloaout, Floaout, FloaoutBlock};
+use crate::format::wav::{Wav, WavBlock};
+use crate::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use crate::io::write::{WriteBlock, WriteFmt};
+use self::distance::BubbleMotion;
+use self::layout::SpeakerLayout;
+use self::listener::{Listener, Pose};
+use self::room::{LateReverb, Room};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, Write};
+
+/// Delay line which can be read between samples.
+#[derive(Clone, Debug)]
+pub(crate) struct DelayLine {
+    buf: Vec<f64>,
+    pos: usize
+}
+
+impl DelayLine {
+    /// This method returns delay line which can delay up to `max_delay` samples.
+    pub(crate) fn new(max_delay: usize) -> Self {
+        Self {
+            buf: vec![0.0; max_delay + 2],
+            pos: 0
+        }
+    }
+
+    pub(crate) fn push(&mut self, n: f64) {
+        self.pos = (self.pos + 1) % self.buf.len();
+        self.buf[self.pos] = n;
+    }
+
+    /// This method reads the sample which was pushed `delay` samples ago.
+    /// Delay is clamped, and fraction is interpolated linearly.
+    pub(crate) fn read(&self, delay: f64) -> f64 {
+        let len = self.buf.len();
+        let delay = delay.clamp(0.0, (len - 2) as f64);
+        let (whole, fraction) = (delay.floor() as usize, delay.fract());
+        let a = self.buf[(self.pos + len - whole) % len];
+        let b = self.buf[(self.pos + len - whole - 1) % len];
+
+        a + (b - a) * fraction
+    }
+}
+
+// Delay in samples and gain of each speaker for each reflection
+type Reflections = Vec<(f64, Vec<f64>)>;
+
+// State of each Bubble
+#[derive(Clone, Debug, Default)]
+struct BubbleState {
+    // Last centroid of Bubble field
+    position: Option<FieldPosition>,
+    // Smoothed distance
+    distance: Option<f64>,
+    // For Doppler
+    delay_line: Option<DelayLine>,
+    // For early reflections
+    room_line: Option<DelayLine>,
+    // Reflections at the position and pose
+    reflections: Option<(FieldPosition, Pose, Reflections)>
+}
+
+/// This structure renders Floaout to speakers block by block.
+#[derive(Clone, Debug)]
+pub struct Renderer {
+    layout: SpeakerLayout,
+    sampling_rate: u32,
+    /// How listener moves.
+    pub listener: Listener,
+    /// Sharpness of panning. Larger value makes each Bubble sound from fewer speakers.
+    pub focus: f64,
+    /// Distance setting of each Bubble in the order of Bubbles in Floaout.
+    /// Bubbles which aren't included use default.
+    pub motions: Vec<BubbleMotion>,
+    bub_states: Vec<BubbleState>,
+    room: Option<(Room, LateReverb)>,
+    // Unit vector of each speaker. LFE is `None`.
+    directions: Vec<Option<[f64; 3]>>,
+    // Trim of each speaker
+    gains: Vec<f64>,
+    // Delay of each speaker in samples
+    delays: Vec<f64>,
+    delay_lines: Vec<DelayLine>,
+    // Number of rendered blocks
+    block: u64
+}
+
+impl Renderer {
+    /// This method casts layout and sampling rate to `Renderer`.
+    /// Listener is at center of Bubble field and faces front.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::format::bub::BubbleBlock;
+    /// use floaout::format::oao::FloaoutBlock;
+    /// use floaout::render::Renderer;
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// let mut renderer = Renderer::new(SpeakerLayout::stereo(), 44100).unwrap();
+    ///
+    /// // Bubble in the left half of the field
+    /// let bub_block = BubbleBlock::from_wav_block_and_bub_field(1.0f32.into(), vec![vec![vec![255], vec![0]]].into());
+    /// let speakers = renderer.render_block(&FloaoutBlock::from(vec![bub_block]));
+    ///
+    /// assert!(speakers[0] > speakers[1]);
+    /// ```
+    pub fn new(layout: SpeakerLayout, sampling_rate: u32) -> Result<Self> {
+        layout.validate()?;
+        let directions = layout.speakers.iter().map(|speaker| {
+            let (direction, distance) = Pose::default().relative(speaker.position.to_field_position());
+            if speaker.lfe || distance == 0.0 {
+                None
+            } else {
+                Some(direction)
+            }
+        }).collect();
+        let gains = layout.speakers.iter().map(|speaker| 10f64.powf(speaker.trim / 20.0)).collect();
+        let delays: Vec<f64> = layout.speakers.iter().map(|speaker| (speaker.delay * sampling_rate as f64 / 1000.0).round()).collect();
+        let delay_lines = delays.iter().map(|&delay| DelayLine::new(delay as usize)).collect();
+
+        Ok(
+            Self {
+                layout,
+                sampling_rate,
+                listener: Listener::default(),
+                focus: 8.0,
+                motions: Vec::new(),
+                bub_states: Vec::new(),
+                room: None,
+                directions,
+                gains,
+                delays,
+                delay_lines,
+                block: 0
+            }
+        )
+    }
+
+    /// This method returns playback setup.
+    pub fn layout(&self) -> &SpeakerLayout {
+        &self.layout
+    }
+
+    /// This method returns sampling rate.
+    pub fn sampling_rate(&self) -> u32 {
+        self.sampling_rate
+    }
+
+    /// This method returns room.
+    pub fn room(&self) -> Option<&Room> {
+        self.room.as_ref().map(|(room, _)| room)
+    }
+
+    /// This method sets room. If it's `None`, Bubbles sound without room.
+    /// An error is returned if the room isn't valid.
+    pub fn set_room(&mut self, room: Option<Room>) -> Result<()> {
+        if let Some(room) = &room {
+            room.validate()?;
+        }
+        self.room = room.map(|room| {
+            let reverb = LateReverb::new(&room, self.sampling_rate);
+            (room, reverb)
+        });
+        for state in &mut self.bub_states {
+            state.room_line = None;
+            state.reflections = None;
+        }
+
+        Ok(())
+    }
+
+    // This method returns delay in samples after direct sound and gains of each speaker for early reflections.
+    fn reflections(&self, room: &Room, position: FieldPosition, pose: &Pose) -> Reflections {
+        let direct = room.meters(position, pose.position);
+        room.image_sources(position).into_iter().filter(|image| image.order > 0).map(|image| {
+            let meters = room.meters(image.position, pose.position);
+            let (direction, _) = pose.relative(image.position);
+            let gain = image.gain * direct.max(0.1) / meters;
+            let delay = (meters - direct) / room.speed_of_sound * self.sampling_rate as f64;
+            (delay, self.pan(direction).into_iter().map(|g| g * gain).collect())
+        }).collect()
+    }
+
+    /// This method returns gain of each speaker for the direction seen from the listener.
+    /// The sum of squared gains is 1. LFE gets nothing.
+    pub fn pan(&self, direction: [f64; 3]) -> Vec<f64> {
+        let mut gains: Vec<f64> = self.directions.iter().map(|speaker| match speaker {
+            None => 0.0,
+            // Bubble is on the listener.
+            Some(_) if direction == [0.0; 3] => 1.0,
+            Some(s) => ((1.0 + s[0] * direction[0] + s[1] * direction[1] + s[2] * direction[2]) / 2.0).powf(self.focus)
+        }).collect();
+        let power = gains.iter().map(|g| g * g).sum::<f64>().sqrt();
+        if power > 0.0 {
+            gains.iter_mut().for_each(|g| *g /= power);
+        }
+
+        gains
+    }
+
+    /// This method renders Floaout block, and returns sample of each speaker.
+    pub fn render_block(&mut self, oao_block: &FloaoutBlock) -> Vec<f64> {
+        let pose = self.listener.pose_at(self.block as f64 / self.sampling_rate as f64);
+        let mut speakers = vec![0.0; self.directions.len()];
+        if self.bub_states.len() < oao_block.0.len() {
+            self.bub_states.resize_with(oao_block.0.len(), Default::default);
+        }
+        let default_motion = BubbleMotion::default();
+        let mut reverb_input = 0.0;
+        for (i, bub_block) in oao_block.0.iter().enumerate() {
+            let motion = self.motions.get(i).unwrap_or(&default_motion);
+            let state = &mut self.bub_states[i];
+            // Empty field holds the last position.
+            if let Some(centroid) = bub_block.bub_field.centroid() {
+                state.position = Some(centroid);
+            }
+            let position = match state.position {
+                Some(position) => position,
+                None => continue
+            };
+            let sample: f64 = bub_block.wav_block.into();
+            let mut sample = sample * bub_block.bub_field.peak() as f64 / 255.0;
+            if let Some((room, _)) = &self.room {
+                let reflections = match self.bub_states[i].reflections.take() {
+                    Some((p, q, reflections)) if p == position && q == pose => reflections,
+                    _ => self.reflections(room, position, &pose)
+                };
+                let state = &mut self.bub_states[i];
+                // Images are at most `order + 1` fields away.
+                let diagonal = room.meters((0.0, 0.0, 0.0).into(), (1.0, 1.0, 1.0).into());
+                let max_delay = diagonal * (room.order + 1) as f64 / room.speed_of_sound * self.sampling_rate as f64;
+                let room_line = state.room_line.get_or_insert_with(|| DelayLine::new(max_delay.ceil() as usize));
+                room_line.push(sample);
+                for (delay, gains) in &reflections {
+                    let n = room_line.read(*delay);
+                    for (speaker, gain) in speakers.iter_mut().zip(gains) {
+                        *speaker += n * gain;
+                    }
+                }
+                state.reflections = Some((position, pose, reflections));
+                reverb_input += sample * room.reverb;
+            }
+            let state = &mut self.bub_states[i];
+            let (direction, distance) = pose.relative(position);
+            let distance = match state.distance {
+                Some(last) if motion.smoothing > 0.0 => {
+                    last + (distance - last) * (1.0 - (-1.0 / (motion.smoothing * self.sampling_rate as f64)).exp())
+                },
+                _ => distance
+            };
+            state.distance = Some(distance);
+            if let Some(doppler) = motion.doppler {
+                let sampling_rate = self.sampling_rate;
+                // The longest distance in Bubble field is diagonal.
+                let delay_line = state.delay_line.get_or_insert_with(|| DelayLine::new(doppler.delay(3f64.sqrt(), sampling_rate).ceil() as usize));
+                delay_line.push(sample);
+                sample = delay_line.read(doppler.delay(distance, sampling_rate));
+            }
+            sample *= motion.distance_model.gain(distance);
+            for (speaker, gain) in speakers.iter_mut().zip(self.pan(direction)) {
+                *speaker += sample * gain;
+            }
+        }
+        if let Some((_, reverb)) = &mut self.room {
+            let outputs = reverb.process(reverb_input);
+            for (i, speaker) in speakers.iter_mut().enumerate() {
+                if self.directions[i].is_some() {
+                    *speaker += LateReverb::mix(&outputs, i);
+                }
+            }
+        }
+        // Trim and delay of each speaker
+        for (i, speaker) in speakers.iter_mut().enumerate() {
+            self.delay_lines[i].push(*speaker * self.gains[i]);
+            *speaker = self.delay_lines[i].read(self.delays[i]);
+        }
+        self.block += 1;
+
+        speakers
+    }
+
+    /// This method reads Floaout and writes Wav whose channels are speakers.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::io;
+    /// use std::fs::File;
+    /// use floaout::render::Renderer;
+    /// use floaout::render::layout::SpeakerLayout;
+    ///
+    /// fn main() -> io::Result<()> {
+    ///     let mut reader = io::BufReader::new(File::open("foo.oao")?);
+    ///     let mut writer = io::BufWriter::new(File::create("foo.wav")?);
+    ///     let mut renderer = Renderer::new(SpeakerLayout::surround_5_1(), 48000)?;
+    ///
+    ///     // render Floaout to 5.1 Wav
+    ///     renderer.render_floaout(&mut reader, &mut writer)?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn render_floaout<R: Read + Seek, W: Write>(&mut self, reader: &mut BufReader<R>, writer: &mut BufWriter<W>) -> Result<Wav> {
+        let oao: Floaout = reader.read_details()?;
+        let _: BubblesInFloaout = reader.read_bubs_details(&oao)?;
+        if oao.sampling_rate != self.sampling_rate {
+            return Err(Error::new(ErrorKind::InvalidInput, "Sampling rate of Floaout and Renderer are different."));
+        }
+        let wav = Wav::from_format(self.directions.len() as u16, oao.sampling_rate, oao.bits_per_sample, oao.blocks)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        writer.write_details(&wav)?;
+        for _ in 0..oao.blocks {
+            let oao_block: FloaoutBlock = reader.read_block(&oao)?;
+            for n in self.render_block(&oao_block) {
+                writer.write_block(&wav, WavBlock(Sample::from_f64_and_bits_per_sample(n, oao.bits_per_sample)))?;
+            }
+        }
+
+        Ok(wav)
+    }
+}
\ No newline at end of file
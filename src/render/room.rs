@@ -0,0 +1,198 @@
+//! Structures related to `Room`
+//!
+//! Room treats Bubble field as a shoebox room.
+//! Early reflections are made by the image-source method, and late reverb is made by a feedback delay network.
+
+use crate::format::FieldPosition;
+use std::io::{Error, ErrorKind, Result};
+
+/// This structure is shoebox room whose walls are the sides of Bubble field.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Room {
+    /// Meters of width, length and height.
+    pub size: (f64, f64, f64),
+    /// Absorption coefficient (0.0~1.0) of each wall.
+    /// ```txt
+    /// [left, right, back, front, floor, ceiling]
+    /// ```
+    pub absorption: [f64; 6],
+    /// Max order of reflections
+    pub order: u32,
+    /// Gain of late reverb
+    pub reverb: f64,
+    /// Speed of sound in meters per second
+    pub speed_of_sound: f64
+}
+
+impl Room {
+    /// This method casts size in meters to `Room`.
+    /// Walls absorb 30 percent, and reflections are up to 2nd order.
+    /// Size is checked by `validate` when the room is set to `Renderer`.
+    pub fn from_size(size: (f64, f64, f64)) -> Self {
+        Self {
+            size,
+            absorption: [0.3; 6],
+            order: 2,
+            reverb: 0.5,
+            speed_of_sound: 343.0
+        }
+    }
+
+    /// This method checks whether sizes and speed of sound are positive and absorption is from 0.0 to 1.0.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::room::Room;
+    ///
+    /// assert!(Room::from_size((4.0, 5.0, 3.0)).validate().is_ok());
+    /// assert!(Room::from_size((4.0, 0.0, 3.0)).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: &str| Err(Error::new(ErrorKind::InvalidInput, msg));
+        let (w, l, h) = self.size;
+        if ![w, l, h].iter().all(|n| *n > 0.0 && n.is_finite()) {
+            return invalid("Size of room isn't positive.");
+        }
+        if !self.absorption.iter().all(|a| (0.0..=1.0).contains(a)) {
+            return invalid("Absorption of room is out of 0.0 to 1.0.");
+        }
+        if !(self.speed_of_sound > 0.0 && self.speed_of_sound.is_finite()) {
+            return invalid("Speed of sound isn't positive.");
+        }
+
+        Ok(())
+    }
+
+    /// This method returns reverberation time (RT60) in seconds by Sabine's formula.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::room::Room;
+    ///
+    /// let mut room = Room::from_size((10.0, 10.0, 10.0));
+    /// room.absorption = [0.161; 6];
+    ///
+    /// assert!((room.rt60() - 10.0 / 6.0).abs() < 1e-9);
+    /// ```
+    pub fn rt60(&self) -> f64 {
+        let (w, l, h) = self.size;
+        let walls = [l * h, l * h, w * h, w * h, w * l, w * l];
+        let absorption: f64 = walls.iter().zip(&self.absorption).map(|(s, a)| s * a).sum();
+        if absorption > 0.0 {
+            0.161 * w * l * h / absorption
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// This method returns distance in meters between 2 positions in Bubble field.
+    pub fn meters(&self, a: FieldPosition, b: FieldPosition) -> f64 {
+        let (w, l, h) = self.size;
+        (((a.x - b.x) * w).powi(2) + ((a.y - b.y) * l).powi(2) + ((a.z - b.z) * h).powi(2)).sqrt()
+    }
+
+    /// This method returns image sources of the source up to `order`, including the source itself.
+    /// Positions of images are out of Bubble field.
+    ///
+    /// # Examples
+    /// ```
+    /// use floaout::render::room::Room;
+    ///
+    /// let mut room = Room::from_size((4.0, 4.0, 4.0));
+    /// room.order = 1;
+    /// let images = room.image_sources((0.25, 0.5, 0.5).into());
+    ///
+    /// // Source and 6 walls
+    /// assert_eq!(images.len(), 7);
+    /// // Image behind left wall
+    /// assert!(images.iter().any(|image| image.position == (-0.25, 0.5, 0.5).into()));
+    /// ```
+    pub fn image_sources(&self, source: FieldPosition) -> Vec<ImageSource> {
+        let order = self.order as i64;
+        let reflection: Vec<f64> = self.absorption.iter().map(|a| (1.0 - a.clamp(0.0, 1.0)).sqrt()).collect();
+        // Position and gain on one axis
+        let axis = |i: i64, n: f64, low: f64, high: f64| {
+            let position = i as f64 + if i % 2 == 0 { n } else { 1.0 - n };
+            // Times of hitting each wall
+            let (low_hits, high_hits) = if i >= 0 { (i / 2, (i + 1) / 2) } else { ((-i + 1) / 2, -i / 2) };
+            (position, low.powi(low_hits as i32) * high.powi(high_hits as i32))
+        };
+        let mut images = Vec::new();
+        for i in -order..=order {
+            for j in -(order - i.abs())..=(order - i.abs()) {
+                let rest = order - i.abs() - j.abs();
+                for k in -rest..=rest {
+                    let (x, gx) = axis(i, source.x, reflection[0], reflection[1]);
+                    let (y, gy) = axis(j, source.y, reflection[2], reflection[3]);
+                    let (z, gz) = axis(k, source.z, reflection[4], reflection[5]);
+                    images.push(
+                        ImageSource {
+                            position: (x, y, z).into(),
+                            gain: gx * gy * gz,
+                            order: (i.abs() + j.abs() + k.abs()) as u32
+                        }
+                    );
+                }
+            }
+        }
+
+        images
+    }
+}
+
+/// This structure is image of source reflected by walls.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct ImageSource {
+    /// Position in the coordinate of Bubble field
+    pub position: FieldPosition,
+    /// Product of reflection coefficients
+    pub gain: f64,
+    /// Number of reflections
+    pub order: u32
+}
+
+/// Late reverb by feedback delay network.
+#[derive(Clone, Debug)]
+pub(crate) struct LateReverb {
+    lines: Vec<(Vec<f64>, usize)>,
+    feedback: Vec<f64>
+}
+
+impl LateReverb {
+    pub(crate) fn new(room: &Room, sampling_rate: u32) -> Self {
+        const LENGTHS: [f64; 8] = [1433.0, 1601.0, 1867.0, 2053.0, 2251.0, 2399.0, 2617.0, 2833.0];
+        // Lengths are for 10 meters room at 48 kHz.
+        let (w, l, h) = room.size;
+        let scale = (w + l + h) / 30.0 * sampling_rate as f64 / 48000.0;
+        let rt60 = room.rt60();
+        let lengths: Vec<usize> = LENGTHS.iter().map(|n| ((n * scale) as usize).max(16)).collect();
+
+        Self {
+            lines: lengths.iter().map(|&n| (vec![0.0; n], 0)).collect(),
+            feedback: lengths.iter().map(|&n| 10f64.powf(-3.0 * n as f64 / (rt60 * sampling_rate as f64))).collect()
+        }
+    }
+
+    /// This method processes one sample, and returns the output of each line.
+    pub(crate) fn process(&mut self, input: f64) -> Vec<f64> {
+        let outputs: Vec<f64> = self.lines.iter().map(|(buf, pos)| buf[*pos]).collect();
+        let attenuated: Vec<f64> = outputs.iter().zip(&self.feedback).map(|(o, g)| o * g).collect();
+        // Householder matrix
+        let sum = attenuated.iter().sum::<f64>() * 2.0 / attenuated.len() as f64;
+        for ((buf, pos), a) in self.lines.iter_mut().zip(attenuated) {
+            buf[*pos] = input + a - sum;
+            *pos = (*pos + 1) % buf.len();
+        }
+
+        outputs
+    }
+
+    /// This method mixes outputs of lines differently for each channel.
+    pub(crate) fn mix(outputs: &[f64], channel: usize) -> f64 {
+        let sum: f64 = outputs.iter().enumerate().map(|(i, o)| {
+            if (channel * 5 + i * 3 + (channel & i)).count_ones() & 1 == 0 { *o } else { -o }
+        }).sum();
+
+        sum / (outputs.len() as f64).sqrt()
+    }
+}
\ No newline at end of file
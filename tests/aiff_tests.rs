@@ -0,0 +1,158 @@
+use std::io::{BufReader, BufWriter, Cursor};
+use std::fs::{File, remove_file};
+use floaout::convert::aiff::{aiff_to_bub, bub_to_aiff};
+use floaout::convert::wav::FieldHandling;
+use floaout::format::aiff::{Aiff, AiffBlock, AiffBlocks, Compression};
+use floaout::format::bub::{Bubble, BubbleBlocks};
+use floaout::io::read::ReadFmt;
+use floaout::io::write::WriteFmt;
+
+#[test]
+fn aiff_test() -> Result<(), Box<dyn std::error::Error>> {
+    // File name
+    let file = "test1.aiff";
+    // Details of AIFF that is going to be written.
+    let write_aiff = Aiff::from_format(2, 44100, Compression::None, 24, 2)?;
+    // AIFF blocks
+    let samples = [0.5f32, -0.5, 0.25, -1.0];
+    let aiff_blocks = AiffBlocks::from(samples.iter().map(|&n| AiffBlock::from(n)).collect::<Vec<AiffBlock>>().into_boxed_slice());
+    // Writer
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&write_aiff)?;
+    writer.write_blocks(&write_aiff, aiff_blocks.clone())?;
+    // Finish writing.
+    drop(writer);
+    // Reader
+    let mut reader = BufReader::new(File::open(file)?);
+    let read_aiff: Aiff = reader.read_details()?;
+    let read_aiff_blocks: AiffBlocks = reader.read_blocks(&read_aiff)?;
+
+    assert_eq!(read_aiff, write_aiff);
+    assert_eq!(read_aiff.form_size as u64 + 8, std::fs::metadata(file)?.len());
+    assert_eq!(read_aiff_blocks, aiff_blocks);
+
+    remove_file(file)?;
+
+    Ok(())
+}
+
+#[test]
+fn aifc_test() -> Result<(), Box<dyn std::error::Error>> {
+    // File name
+    let file = "test1.aifc";
+    // Details of AIFF-C that is going to be written.
+    let write_aiff = Aiff::from_format(1, 48000, Compression::Float64, 64, 3)?;
+    // AIFF blocks
+    let aiff_blocks = AiffBlocks::from(vec![AiffBlock::from(0.1f64), AiffBlock::from(-2.0f64), AiffBlock::from(0.0f64)].into_boxed_slice());
+    // Writer
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&write_aiff)?;
+    writer.write_blocks(&write_aiff, aiff_blocks.clone())?;
+    // Finish writing.
+    drop(writer);
+    // Reader
+    let mut reader = BufReader::new(File::open(file)?);
+    let read_aiff: Aiff = reader.read_details()?;
+    let read_aiff_blocks: AiffBlocks = reader.read_blocks(&read_aiff)?;
+
+    assert_eq!(read_aiff, write_aiff);
+    assert!(read_aiff.aifc);
+    assert_eq!(read_aiff.form_size as u64 + 8, std::fs::metadata(file)?.len());
+    assert_eq!(read_aiff_blocks, aiff_blocks);
+
+    remove_file(file)?;
+
+    Ok(())
+}
+
+#[test]
+fn aiff_chunk_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 12 bytes of Form Chunk, 26 bytes of Common Chunk and 28 bytes of Sound Data Chunk
+    let write_aiff = Aiff::from_format(2, 44100, Compression::None, 24, 2)?;
+    let samples = [0.5f32, -0.5, 0.25, -1.0];
+    let aiff_blocks = AiffBlocks::from(samples.iter().map(|&n| AiffBlock::from(n)).collect::<Vec<AiffBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_details(&write_aiff)?;
+    writer.write_blocks(&write_aiff, aiff_blocks.clone())?;
+    let bytes = writer.into_inner()?;
+    assert_eq!(bytes.len(), 66);
+
+    // Common Chunk after Sound Data Chunk
+    let moved = [&bytes[..12], &bytes[38..], &bytes[12..38]].concat();
+    let mut reader = BufReader::new(Cursor::new(moved));
+    let read_aiff: Aiff = reader.read_details()?;
+    let read_aiff_blocks: AiffBlocks = reader.read_blocks(&read_aiff)?;
+
+    assert_eq!(read_aiff, write_aiff);
+    assert_eq!(read_aiff_blocks, aiff_blocks);
+
+    // No Common Chunk
+    let missing = [&bytes[..12], &bytes[38..]].concat();
+
+    assert!(BufReader::new(Cursor::new(missing)).read_details().map(|_: Aiff| ()).is_err());
+
+    // Common Chunk which is shorter than 18 bytes
+    let mut short = bytes.clone();
+    short[16..20].copy_from_slice(&10u32.to_be_bytes());
+
+    assert!(BufReader::new(Cursor::new(short)).read_details().map(|_: Aiff| ()).is_err());
+
+    // Offset which is larger than Sound Data Chunk
+    let mut offset = bytes;
+    offset[46..50].copy_from_slice(&100u32.to_be_bytes());
+
+    assert!(BufReader::new(Cursor::new(offset)).read_details().map(|_: Aiff| ()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn aiff_and_bub_test() -> Result<(), Box<dyn std::error::Error>> {
+    let aiff_file = "test2.aiff";
+    let bub_file = "test2.bub";
+    // 8 bits AIFF has odd data size.
+    let write_aiff = Aiff::from_format(1, 22050, Compression::None, 8, 3)?;
+    let aiff_blocks = AiffBlocks::from(vec![AiffBlock::from(0.5f32), AiffBlock::from(-0.5f32), AiffBlock::from(-1.0f32)].into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(aiff_file)?);
+    writer.write_details(&write_aiff)?;
+    writer.write_blocks(&write_aiff, aiff_blocks)?;
+    drop(writer);
+    assert_eq!(write_aiff.form_size as u64 + 8, std::fs::metadata(aiff_file)?.len());
+
+    // AIFF to Bubble
+    let bub = Bubble {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        name: "Bass".into(),
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(aiff_file)?);
+    let mut writer = BufWriter::new(File::create(bub_file)?);
+    aiff_to_bub(&mut reader, &mut writer, &bub, |_| vec![vec![vec![255]]].into())?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub.sampling_rate, 22050);
+    assert_eq!(read_bub.blocks, 3);
+    let read_samples: Vec<f32> = read_bub_blocks.0.iter().map(|b| b.wav_block.into()).collect();
+    assert_eq!(read_samples, vec![0.5, -0.5, -1.0]);
+
+    // Bubble to AIFF-C
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let mut writer = BufWriter::new(File::create(aiff_file)?);
+    let written_aiff = bub_to_aiff(&mut reader, &mut writer, FieldHandling::Drop, Compression::Float32, 32)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(aiff_file)?);
+    let read_aiff: Aiff = reader.read_details()?;
+    let read_aiff_blocks: AiffBlocks = reader.read_blocks(&read_aiff)?;
+    assert_eq!(read_aiff, written_aiff);
+    assert_eq!(read_aiff.compression, Compression::Float32);
+    assert_eq!(read_aiff_blocks.0[2], AiffBlock::from(-1.0f32));
+
+    remove_file(aiff_file)?;
+    remove_file(bub_file)?;
+
+    Ok(())
+}
\ No newline at end of file
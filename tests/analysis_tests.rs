@@ -0,0 +1,327 @@
+use std::f64::consts::PI;
+use std::io::{BufReader, BufWriter};
+use std::fs::{File, remove_file};
+use floaout::analysis::diff::{diff_oao, DiffOptions};
+use floaout::analysis::loudness::{measure_oao, measure_wav, LoudnessMeter};
+use floaout::analysis::spatial::{spatial_oao, SpatialOptions};
+use floaout::analysis::stats::{stats_oao, StatsOptions};
+use floaout::format::bub::BubbleBlock;
+use floaout::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
+use floaout::format::wav::{SampleFormat, Wav, WavBlock, WavBlocks};
+use floaout::io::write::{WriteBubsIn, WriteFmt};
+use floaout::render::Renderer;
+use floaout::render::layout::SpeakerLayout;
+
+/// This function returns 1 kHz sine wave.
+fn sine(amplitude: f64, i: u64, sampling_rate: u32) -> f64 {
+    amplitude * (2.0 * PI * 1000.0 * i as f64 / sampling_rate as f64).sin()
+}
+
+#[test]
+fn loudness_test() -> Result<(), Box<dyn std::error::Error>> {
+    // stereo sine wave at -20 dBFS for 2 seconds
+    let wav_file = "loudness.wav";
+    let wav = Wav::from_sample_format(2, 48000, SampleFormat::Float, 32, 96000)?;
+    let wav_blocks = WavBlocks::from((0..192000).map(|i| WavBlock::from(sine(0.1, i / 2, 48000) as f32)).collect::<Vec<WavBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    writer.write_details(&wav)?;
+    writer.write_blocks(&wav, wav_blocks)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let loudness = measure_wav(&mut reader)?;
+    assert!((loudness.integrated + 20.0).abs() < 0.1);
+    assert!((loudness.momentary_max + 20.0).abs() < 0.1);
+    assert_eq!(loudness.short_term_max, f64::NEG_INFINITY);
+    assert!((loudness.true_peak + 20.0).abs() < 0.1);
+    remove_file(wav_file)?;
+
+    // -20 dBFS for 10 seconds and -30 dBFS for 10 seconds
+    let mut meter = LoudnessMeter::mono(8000)?;
+    for i in 0..160000 {
+        let amplitude = if i < 80000 { 0.1 } else { 0.1 / 10f64.sqrt() };
+        meter.add_frame(&[sine(amplitude, i, 8000)]);
+    }
+    assert!((meter.loudness_range() - 10.0).abs() < 0.2);
+    assert!((meter.short_term() + 33.0).abs() < 0.1);
+    // Quiet part is within the relative gate.
+    assert!(meter.integrated() > -27.0 && meter.integrated() < -25.0);
+
+    // -66 LUFS for 10 seconds and -72 LUFS for 10 seconds
+    let mut meter = LoudnessMeter::mono(8000)?;
+    for i in 0..160000 {
+        let amplitude = if i < 80000 { 0.1 * 10f64.powf(-43.0 / 20.0) } else { 0.1 * 10f64.powf(-49.0 / 20.0) };
+        meter.add_frame(&[sine(amplitude, i, 8000)]);
+    }
+    // Quiet part is within the relative gate, but not within the absolute gate.
+    assert!((meter.integrated() + 66.0).abs() < 0.2);
+
+    Ok(())
+}
+
+#[test]
+fn oao_loudness_test() -> Result<(), Box<dyn std::error::Error>> {
+    let oao_file = "loudness.oao";
+    let oao = Floaout {
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 48000,
+        sampling_rate: 48000,
+        bits_per_sample: 64,
+        ..Default::default()
+    };
+    let bub_in_oao = |name: &str| BubbleInFloaout {
+        bubble_id: 0,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    let oao_blocks = FloaoutBlocks::from((0..48000).map(|i| {
+        FloaoutBlock::from(vec![
+            BubbleBlock::from_wav_block_and_bub_field(sine(0.1, i, 48000).into(), vec![vec![vec![255], vec![0]]].into()),
+            BubbleBlock::from_wav_block_and_bub_field(0.0f64.into(), vec![vec![vec![0], vec![0]]].into())
+        ])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(vec![bub_in_oao("Sine"), bub_in_oao("Silence")]))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 48000)?;
+    let loudness = measure_oao(&mut reader, Some(&mut renderer))?;
+    assert_eq!(loudness.bubbles.len(), 2);
+    assert!((loudness.bubbles[0].integrated + 23.0).abs() < 0.1);
+    assert_eq!(loudness.bubbles[1].integrated, f64::NEG_INFINITY);
+    let rendered = loudness.rendered.unwrap();
+    assert!(rendered.integrated.is_finite() && rendered.integrated < -20.0);
+
+    // Sampling rate of Renderer is different.
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 44100)?;
+    assert!(measure_oao(&mut reader, Some(&mut renderer)).is_err());
+
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn stats_test() -> Result<(), Box<dyn std::error::Error>> {
+    let oao_file = "stats.oao";
+    // 10 blocks per second
+    let oao = Floaout {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 20,
+        sampling_rate: 10,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    let bub_in_oao = |name: &str| BubbleInFloaout {
+        bubble_id: 0,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    let oao_blocks = FloaoutBlocks::from((0..20).map(|i| {
+        // Piano is silent from block 5 to 12, and clipped at block 15.
+        let piano: f32 = match i {
+            5..=11 => 0.0,
+            15 => 1.5,
+            _ => 0.5
+        };
+        // Vocal has NaN at block 3, and its Bubble field is 0 from block 10.
+        let vocal = if i == 3 { f32::NAN } else { -0.25 };
+        let value = if i < 10 { 255 } else { 0 };
+        FloaoutBlock::from(vec![
+            BubbleBlock::from_wav_block_and_bub_field(piano.into(), vec![vec![vec![255]]].into()),
+            BubbleBlock::from_wav_block_and_bub_field(vocal.into(), vec![vec![vec![value]]].into())
+        ])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(vec![bub_in_oao("Piano"), bub_in_oao("Vocal")]))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let report = stats_oao(&mut reader, &StatsOptions::default())?;
+    assert_eq!(report.blocks, 20);
+    let (piano, vocal) = (&report.bubbles[0], &report.bubbles[1]);
+    assert_eq!(piano.name, "Piano");
+    assert_eq!(piano.peak, 1.5);
+    assert_eq!(piano.clipped, 1);
+    assert_eq!(piano.silence, vec![5..12]);
+    assert_eq!(piano.active_blocks, 13);
+    assert!(vocal.has_problem());
+    assert_eq!(vocal.nan, 1);
+    assert_eq!(vocal.rms, 0.25);
+    assert_eq!(vocal.dc_offset, -0.25);
+    assert_eq!(vocal.silence, vec![10..20]);
+    assert_eq!(vocal.active_blocks, 10);
+    let json = report.to_json();
+    assert!(json.contains(r#""name":"Piano","peak":1.5"#));
+    assert!(json.contains(r#""silence":[[5,12]],"active_duration":1.3"#));
+
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn spatial_test() -> Result<(), Box<dyn std::error::Error>> {
+    let oao_file = "spatial.oao";
+    // width 4 and 4 blocks per second
+    let oao = Floaout {
+        bub_field_size: (0u8, 2u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 8,
+        sampling_rate: 4,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    let bub_in_oao = |name: &str| BubbleInFloaout {
+        bubble_id: 0,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    let cell = |w: Option<usize>| {
+        let mut values = vec![vec![0u8]; 4];
+        if let Some(w) = w {
+            values[w][0] = 255;
+        }
+        vec![values].into()
+    };
+    let oao_blocks = FloaoutBlocks::from((0..8).map(|i| {
+        // Drums moves to right, and its Bubble field is empty at the end.
+        let drums = if i < 6 { Some(i / 2) } else { None };
+        FloaoutBlock::from(vec![
+            BubbleBlock::from_wav_block_and_bub_field(0.5f32.into(), cell(Some(0))),
+            BubbleBlock::from_wav_block_and_bub_field(0.5f32.into(), cell(drums))
+        ])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(vec![bub_in_oao("Piano"), bub_in_oao("Drums")]))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+    drop(writer);
+
+    let options = SpatialOptions {
+        window: 0.5,
+        crowded: 2,
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let report = spatial_oao(&mut reader, &options)?;
+    assert_eq!(report.windows.len(), 4);
+    let (first, second) = (&report.windows[0], &report.windows[1]);
+    assert_eq!((second.start, second.blocks), (2, 2));
+    assert_eq!(first.overlaps[0][1], 1.0);
+    assert_eq!(second.overlaps[0][1], 0.0);
+    assert_eq!(first.crowded, vec![(0, 0, 0)]);
+    assert!(second.crowded.is_empty());
+    assert_eq!(second.shapes[1].unwrap().centroid, (0.375, 0.5, 0.5).into());
+    assert_eq!(second.speeds, vec![Some(0.0), Some(0.5)]);
+    assert_eq!(report.windows[3].shapes[1], None);
+
+    let (piano, drums) = (&report.bubbles[0], &report.bubbles[1]);
+    assert!(piano.is_static);
+    assert_eq!(piano.empty_while_active, 0);
+    assert!(!drums.is_static);
+    assert_eq!(drums.travel, 0.5);
+    assert_eq!(drums.max_speed, 0.5);
+    assert_eq!(drums.empty_while_active, 2);
+
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn diff_test() -> Result<(), Box<dyn std::error::Error>> {
+    let (left_file, right_file) = ("diff_left.oao", "diff_right.oao");
+    let oao = Floaout {
+        bub_field_size: (1u8, 0u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 3,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    // Right file has renamed Vocal, Piano which is 0.01 louder at block 1 and Vocal whose cell (1, 0, 0) is different at block 2.
+    let write = |file: &str, oao: &Floaout, vocal: &str, difference: f32, value: u8| -> std::io::Result<()> {
+        let bubs_in_oao = BubblesInFloaout::from(vec![
+            BubbleInFloaout { bubble_id: 1, name_size: 5, name: "Piano".into(), color: (0, 0, 0).into() },
+            BubbleInFloaout { bubble_id: 2, name_size: vocal.len() as u8, name: vocal.into(), color: (0, 0, 0).into() }
+        ]);
+        let oao_blocks = FloaoutBlocks::from((0..3).map(|i| {
+            let piano = if i == 1 { 0.5 + difference } else { 0.5f32 };
+            let vocal_value = if i == 2 { value } else { 0 };
+            FloaoutBlock::from(vec![
+                BubbleBlock::from_wav_block_and_bub_field(piano.into(), vec![vec![vec![255]], vec![vec![0]]].into()),
+                BubbleBlock::from_wav_block_and_bub_field(0.25f32.into(), vec![vec![vec![0]], vec![vec![vocal_value]]].into())
+            ])
+        }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+        let mut writer = BufWriter::new(File::create(file)?);
+        writer.write_details(oao)?;
+        writer.write_bubs_details(&bubs_in_oao)?;
+        writer.write_blocks(oao, oao_blocks)
+    };
+    write(left_file, &oao, "Vocal", 0.0, 0)?;
+    write(right_file, &oao, "Vocal 2", 0.01, 10)?;
+
+    let diff = diff_oao(&mut BufReader::new(File::open(left_file)?), &mut BufReader::new(File::open(right_file)?), &DiffOptions::default())?;
+    assert!(!diff.is_same());
+    assert!(diff.details.is_empty());
+    assert_eq!(diff.first_block(), Some(1));
+    let (piano, vocal) = (&diff.bubbles[0], &diff.bubbles[1]);
+    assert_eq!((piano.first_block, piano.samples, piano.cells), (Some(1), 1, 0));
+    assert!((piano.max_difference + 40.0).abs() < 0.01);
+    assert_eq!(vocal.details[0].right, r#""Vocal 2""#);
+    assert_eq!((vocal.first_block, vocal.samples, vocal.cells), (Some(2), 0, 1));
+    assert_eq!(vocal.cell_blocks, vec![((1, 0, 0), 1)]);
+    let json = diff.to_json();
+    assert!(json.starts_with(r#"{"same":false,"details":[],"blocks":3,"first_block":1"#));
+    assert!(json.contains(r#""cell_blocks":[{"cell":[1,0,0],"blocks":1}]"#));
+    assert!(json.contains(r#""non_finite":false"#));
+    assert!(diff.to_string().contains("name: \"Vocal\" -> \"Vocal 2\""));
+
+    // Tolerances hide sample and cell differences.
+    let options = DiffOptions {
+        sample_tolerance: -30.0,
+        field_tolerance: 10
+    };
+    let diff = diff_oao(&mut BufReader::new(File::open(left_file)?), &mut BufReader::new(File::open(right_file)?), &options)?;
+    assert_eq!(diff.bubbles.len(), 1);
+    assert_eq!(diff.first_block(), None);
+
+    // Infinite sample is different from any sample.
+    write(right_file, &oao, "Vocal", f32::INFINITY, 0)?;
+    let diff = diff_oao(&mut BufReader::new(File::open(left_file)?), &mut BufReader::new(File::open(right_file)?), &DiffOptions::default())?;
+    assert_eq!(diff.bubbles.len(), 1);
+    assert_eq!(diff.bubbles[0].max_difference, f64::INFINITY);
+    assert!(diff.to_json().contains(r#""max_difference":null,"non_finite":true"#));
+
+    // Title and artist are compared.
+    let titled = Floaout {
+        title_size: 4,
+        title: "Song".into(),
+        artist_size: 6,
+        artist: "Artist".into(),
+        ..oao.clone()
+    };
+    write(right_file, &titled, "Vocal", 0.0, 0)?;
+    let diff = diff_oao(&mut BufReader::new(File::open(left_file)?), &mut BufReader::new(File::open(right_file)?), &DiffOptions::default())?;
+    let names: Vec<&str> = diff.details.iter().map(|detail| detail.name.as_str()).collect();
+    assert_eq!(names, vec!["title", "artist"]);
+    assert_eq!(diff.details[0].right, r#""Song""#);
+    assert!(diff.bubbles.is_empty());
+
+    remove_file(left_file)?;
+    remove_file(right_file)?;
+
+    Ok(())
+}
\ No newline at end of file
@@ -13,6 +13,7 @@ fn bub_details_without_name_test() -> Result<(), Box<dyn std::error::Error>> {
     // Details of Bubble that is going to be written.
     let write_bub = Bubble {
         version: 0,
+        bubble_id: 0,
         bub_field_size: (1u8, 1u8, 1u8).into(),
         color: (0, 0, 0).into(),
         blocks: 0,
@@ -45,6 +46,7 @@ fn bub_test() -> Result<(), Box<dyn std::error::Error>> {
     // Details of Bubble that is going to be written.
     let write_bub = Bubble {
         version: 0,
+        bubble_id: 1,
         bub_field_size: (2u8, 1u8, 0u8).into(),
         color: (255, 255, 255).into(),
         blocks: 2,
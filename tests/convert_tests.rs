@@ -0,0 +1,547 @@
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::fs::{File, remove_file};
+use floaout::convert::mux::{demux, mux, BlocksPolicy, MuxOptions};
+use floaout::convert::resample::{resample_bub, resample_oao, FieldInterpolation};
+use floaout::convert::transcode::{transcode_bub, transcode_wav, NarrowingStats};
+use floaout::convert::wav::{bub_to_wav, wav_to_bub, wav_to_bub_along, wav_to_oao, FieldHandling};
+use floaout::format::BubbleField;
+use floaout::format::bub::{Bubble, BubbleBlock, BubbleBlocks};
+use floaout::format::oao::{BubblesInFloaout, Floaout, FloaoutBlocks};
+use floaout::format::wav::{SampleFormat, Wav, WavBlock, WavBlocks};
+use floaout::io::read::{ReadBlock, ReadBubsIn, ReadFmt};
+use floaout::io::write::WriteFmt;
+use floaout::motion::trajectory::{extract_bub, Keyframe, Trajectory};
+
+#[test]
+fn wav_to_bub_and_bub_to_wav_test() -> Result<(), Box<dyn std::error::Error>> {
+    let wav_file = "convert1.wav";
+    let bub_file = "convert1.bub";
+    // 16 bits integer Wav
+    let write_wav = Wav::from_sample_format(1, 44100, SampleFormat::Int, 16, 3)?;
+    let samples = [0.5f32, -0.25, 1.0];
+    let wav_blocks = WavBlocks::from(samples.iter().map(|&n| WavBlock::from(n)).collect::<Vec<WavBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    writer.write_details(&write_wav)?;
+    writer.write_blocks(&write_wav, wav_blocks)?;
+    drop(writer);
+
+    // Wav to Bubble
+    let left: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let right: BubbleField = vec![vec![vec![0], vec![128]]].into();
+    let bub = Bubble {
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        color: (1, 2, 3).into(),
+        name: "Piano".into(),
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let mut writer = BufWriter::new(File::create(bub_file)?);
+    let written_bub = wav_to_bub(&mut reader, &mut writer, &bub, |i| if i == 0 { left.clone() } else { right.clone() })?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bub);
+    assert_eq!(read_bub.blocks, 3);
+    assert_eq!(read_bub.bits_per_sample, 32);
+    assert_eq!(read_bub.name_size, 5);
+    assert_eq!(read_bub.overall, vec![vec![vec![255], vec![128]]].into());
+    assert_eq!(read_bub_blocks.0[0].bub_field, left);
+    assert_eq!(read_bub_blocks.0[2].bub_field, right);
+    // 1.0 is clipped by 16 bits integer.
+    let read_samples: Vec<f32> = read_bub_blocks.0.iter().map(|b| b.wav_block.into()).collect();
+    assert_eq!(read_samples, vec![0.5, -0.25, 32767.0 / 32768.0]);
+
+    // Bubble to Wav
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    let written_wav = bub_to_wav(&mut reader, &mut writer, FieldHandling::Drop, SampleFormat::Int, 24)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let read_wav: Wav = reader.read_details()?;
+    let read_wav_blocks: WavBlocks = reader.read_blocks(&read_wav)?;
+    assert_eq!(read_wav, written_wav);
+    assert_eq!(read_wav.bits_per_sample, 24);
+    let read_samples: Vec<f32> = read_wav_blocks.0.iter().map(|&b| b.into()).collect();
+    assert_eq!(read_samples, vec![0.5, -0.25, 32767.0 / 32768.0]);
+
+    remove_file(wav_file)?;
+    remove_file(bub_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn mux_and_demux_test() -> Result<(), Box<dyn std::error::Error>> {
+    let bub_files = ["mux1.bub", "mux2.bub"];
+    let oao_file = "mux1.oao";
+    // 2 blocks, width 2
+    let write_bub1 = Bubble {
+        bubble_id: 1,
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        color: (255, 0, 0).into(),
+        blocks: 2,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        name_size: 5,
+        name: "Piano".into(),
+        overall: vec![vec![vec![255], vec![0]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks1 = BubbleBlocks::from(vec![
+        BubbleBlock::from_wav_block_and_bub_field(0.5f32.into(), vec![vec![vec![255], vec![0]]].into()),
+        BubbleBlock::from_wav_block_and_bub_field(0.25f32.into(), vec![vec![vec![255], vec![0]]].into())
+    ].into_boxed_slice());
+    // 1 block, width 4
+    let write_bub2 = Bubble {
+        bubble_id: 2,
+        bub_field_size: (0u8, 2u8, 0u8).into(),
+        color: (0, 0, 255).into(),
+        blocks: 1,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        name_size: 5,
+        name: "Vocal".into(),
+        overall: vec![vec![vec![0], vec![0], vec![0], vec![9]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks2 = BubbleBlocks::from(vec![
+        BubbleBlock::from_wav_block_and_bub_field((-1.0f32).into(), vec![vec![vec![0], vec![0], vec![0], vec![9]]].into())
+    ].into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(bub_files[0])?);
+    writer.write_details(&write_bub1)?;
+    writer.write_blocks(&write_bub1, bub_blocks1)?;
+    drop(writer);
+    let mut writer = BufWriter::new(File::create(bub_files[1])?);
+    writer.write_details(&write_bub2)?;
+    writer.write_blocks(&write_bub2, bub_blocks2)?;
+    drop(writer);
+
+    // Bubble field size is different.
+    let mut readers = vec![BufReader::new(File::open(bub_files[0])?), BufReader::new(File::open(bub_files[1])?)];
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    assert!(mux(&mut readers, &mut writer, &MuxOptions::default()).is_err());
+    drop(writer);
+
+    let mut readers = vec![BufReader::new(File::open(bub_files[0])?), BufReader::new(File::open(bub_files[1])?)];
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    let options = MuxOptions {
+        song_id: 7,
+        blocks_policy: BlocksPolicy::Pad,
+        bub_field_size: Some((0u8, 1u8, 0u8).into())
+    };
+    let written_oao = mux(&mut readers, &mut writer, &options)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_oao.song_id, 7);
+    assert_eq!(read_oao.bubbles, 2);
+    assert_eq!(read_oao.blocks, 2);
+    assert_eq!(read_oao.sampling_rate, 48000);
+    assert_eq!(read_bubs_in_oao.0[0].bubble_id, 1);
+    assert_eq!(read_bubs_in_oao.0[0].name, "Piano");
+    assert_eq!(read_bubs_in_oao.0[1].bubble_id, 2);
+    assert_eq!(read_bubs_in_oao.0[1].color, (0, 0, 255).into());
+    assert_eq!(read_oao_blocks.0[0].0[0].wav_block, 0.5f32.into());
+    assert_eq!(read_oao_blocks.0[0].0[1].wav_block, (-1.0f32).into());
+    assert_eq!(read_oao_blocks.0[0].0[1].bub_field, vec![vec![vec![0], vec![9]]].into());
+    // padded
+    assert_eq!(read_oao_blocks.0[1].0[0].wav_block, 0.25f32.into());
+    assert_eq!(read_oao_blocks.0[1].0[1].wav_block, 0.0f32.into());
+    assert_eq!(read_oao_blocks.0[1].0[1].bub_field, vec![vec![vec![0], vec![0]]].into());
+
+    // demux
+    let demux_files = ["demux1.bub", "demux2.bub"];
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut writers = vec![BufWriter::new(File::create(demux_files[0])?), BufWriter::new(File::create(demux_files[1])?)];
+    let written_bubs = demux(&mut reader, &mut writers)?;
+    drop(writers);
+
+    let mut reader = BufReader::new(File::open(demux_files[1])?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bubs[1]);
+    assert_eq!(read_bub.bubble_id, 2);
+    assert_eq!(read_bub.name, "Vocal");
+    assert_eq!(read_bub.blocks, 2);
+    assert_eq!(read_bub.bub_field_size, (0u8, 1u8, 0u8).into());
+    assert_eq!(read_bub.overall, vec![vec![vec![0], vec![9]]].into());
+    assert_eq!(read_bub_blocks.0[0].wav_block, (-1.0f32).into());
+    let mut reader = BufReader::new(File::open(demux_files[0])?);
+    let read_bub: Bubble = reader.read_details()?;
+    assert_eq!(read_bub, written_bubs[0]);
+    assert_eq!(read_bub.overall, vec![vec![vec![255], vec![0]]].into());
+
+    for bub_file in bub_files.iter().chain(&demux_files) {
+        remove_file(bub_file)?;
+    }
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn wav_to_oao_test() -> Result<(), Box<dyn std::error::Error>> {
+    let wav_file = "convert2.wav";
+    let oao_file = "convert2.oao";
+    // 5.1 (side) WAVE_FORMAT_EXTENSIBLE, 16 bits integer, 2 frames
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(4u32 + 48 + 8 + 24).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&40u32.to_le_bytes());
+    bytes.extend_from_slice(&0xfffeu16.to_le_bytes());
+    bytes.extend_from_slice(&6u16.to_le_bytes());
+    bytes.extend_from_slice(&48000u32.to_le_bytes());
+    bytes.extend_from_slice(&(48000u32 * 12).to_le_bytes());
+    bytes.extend_from_slice(&12u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(&22u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(&0x60fu32.to_le_bytes());
+    bytes.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71]);
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&24u32.to_le_bytes());
+    for frame in 0..2i16 {
+        for channel in 0..6i16 {
+            bytes.extend_from_slice(&((channel + 1) * 1024 * (1 - 2 * frame)).to_le_bytes());
+        }
+    }
+    std::fs::write(wav_file, bytes)?;
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let read_wav: Wav = reader.read_details()?;
+    assert_eq!(read_wav.format_tag, 1);
+    assert_eq!(read_wav.channel_mask, 0x60f);
+    assert_eq!(read_wav.blocks(), 2);
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    let written_oao = wav_to_oao(&mut reader, &mut writer, None, (1u8, 1u8, 1u8).into())?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_oao.bubbles, 6);
+    assert_eq!(read_oao.blocks, 2);
+    assert_eq!(read_oao.sampling_rate, 48000);
+    let names: Vec<&str> = read_bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.as_str()).collect();
+    assert_eq!(names, vec!["L", "R", "C", "LFE", "Ls", "Rs"]);
+    for (i, a) in read_bubs_in_oao.0.iter().enumerate() {
+        for b in &read_bubs_in_oao.0[i + 1..] {
+            assert_ne!(a.color, b.color);
+        }
+    }
+    let samples: Vec<f32> = read_oao_blocks.0[1].0.iter().map(|b| b.wav_block.into()).collect();
+    assert_eq!(samples, vec![-1.0 / 32.0, -2.0 / 32.0, -3.0 / 32.0, -4.0 / 32.0, -5.0 / 32.0, -6.0 / 32.0]);
+    // L is front left, and Rs is back right.
+    let left = read_oao_blocks.0[0].0[0].bub_field.centroid().unwrap();
+    let right_surround = read_oao_blocks.0[0].0[5].bub_field.centroid().unwrap();
+    assert!(left.x < 0.5 && left.y > 0.5);
+    assert!(right_surround.x > 0.5 && right_surround.y < 0.5);
+    assert_eq!(read_oao_blocks.0[0].0[0].bub_field, read_oao_blocks.0[1].0[0].bub_field);
+
+    remove_file(wav_file)?;
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn resample_test() -> Result<(), Box<dyn std::error::Error>> {
+    let bub_files = ["resample1.bub", "resample2.bub"];
+    let oao_files = ["resample1.oao", "resample2.oao"];
+    // 1 kHz sine at 44100 Hz, which moves from left to right at block 441
+    let left: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let right: BubbleField = vec![vec![vec![0], vec![255]]].into();
+    let sine = |t: f64| (2.0 * std::f64::consts::PI * 1000.0 * t).sin() * 0.5;
+    let write_bub = Bubble {
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        blocks: 882,
+        sampling_rate: 44100,
+        bits_per_sample: 64,
+        overall: vec![vec![vec![255], vec![255]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks = BubbleBlocks::from((0..882).map(|i| {
+        let bub_field = if i < 441 { left.clone() } else { right.clone() };
+        BubbleBlock::from_wav_block_and_bub_field(sine(i as f64 / 44100.0).into(), bub_field)
+    }).collect::<Vec<BubbleBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(bub_files[0])?);
+    writer.write_details(&write_bub)?;
+    writer.write_blocks(&write_bub, bub_blocks.clone())?;
+    drop(writer);
+
+    // Same sampling rate doesn't change samples.
+    let mut reader = BufReader::new(File::open(bub_files[0])?);
+    let mut writer = BufWriter::new(File::create(bub_files[1])?);
+    resample_bub(&mut reader, &mut writer, 44100, FieldInterpolation::Hold)?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(bub_files[1])?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, write_bub);
+    assert_eq!(read_bub_blocks, bub_blocks);
+
+    // 44100 Hz to 48000 Hz
+    let mut reader = BufReader::new(File::open(bub_files[0])?);
+    let mut writer = BufWriter::new(File::create(bub_files[1])?);
+    let written_bub = resample_bub(&mut reader, &mut writer, 48000, FieldInterpolation::Linear)?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(bub_files[1])?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bub);
+    assert_eq!(read_bub.sampling_rate, 48000);
+    assert_eq!(read_bub.blocks, 960);
+    assert_eq!(read_bub.overall, vec![vec![vec![255], vec![255]]].into());
+    // Samples far from both ends follow the sine.
+    for i in 100..860 {
+        let n: f64 = read_bub_blocks.0[i].wav_block.into();
+        assert!((n - sine(i as f64 / 48000.0)).abs() < 1e-3, "block {}", i);
+    }
+    assert_eq!(read_bub_blocks.0[0].bub_field, left);
+    assert_eq!(read_bub_blocks.0[959].bub_field, right);
+    // Block 478 is between block 439 and 440, block 479 is between block 440 and 441, and block 480 is block 441.
+    assert_eq!(read_bub_blocks.0[478].bub_field, left);
+    let middle: Vec<Vec<Vec<u8>>> = read_bub_blocks.0[479].bub_field.clone().into();
+    assert!(middle[0][0][0] > 0 && middle[0][0][0] < 255);
+    assert_eq!(read_bub_blocks.0[480].bub_field, right);
+
+    // Floaout of 2 Bubbles from 48000 Hz to 24000 Hz
+    let mut readers = vec![BufReader::new(File::open(bub_files[1])?), BufReader::new(File::open(bub_files[1])?)];
+    let mut writer = BufWriter::new(File::create(oao_files[0])?);
+    mux(&mut readers, &mut writer, &MuxOptions::default())?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(oao_files[0])?);
+    let mut writer = BufWriter::new(File::create(oao_files[1])?);
+    let written_oao = resample_oao(&mut reader, &mut writer, 24000, FieldInterpolation::Hold)?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(oao_files[1])?);
+    let read_oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_oao.blocks, 480);
+    assert_eq!(read_oao.bubbles, 2);
+    for i in 100..380 {
+        let n: f64 = read_oao_blocks.0[i].0[1].wav_block.into();
+        assert!((n - sine(i as f64 / 24000.0)).abs() < 1e-3, "block {}", i);
+    }
+    assert_eq!(read_oao_blocks.0[239].0[0].bub_field, left);
+    // Block 240 is block 480, and block 479 between left and right is folded into it.
+    let folded: Vec<Vec<Vec<u8>>> = read_oao_blocks.0[240].0[0].bub_field.clone().into();
+    assert_eq!(folded[0][0][0], middle[0][0][0]);
+    assert_eq!(folded[0][1][0], 255);
+    assert_eq!(read_oao_blocks.0[241].0[0].bub_field, right);
+
+    for file in bub_files.iter().chain(&oao_files) {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn transcode_test() -> Result<(), Box<dyn std::error::Error>> {
+    let bub_files = ["transcode1.bub", "transcode2.bub"];
+    let wav_files = ["transcode1.wav", "transcode2.wav"];
+    let samples = [0.5f64, 0.1, 1e40, 1e-50];
+    let write_bub = Bubble {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        blocks: 4,
+        sampling_rate: 44100,
+        bits_per_sample: 64,
+        overall: vec![vec![vec![255]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks = BubbleBlocks::from(samples.iter().map(|&n| {
+        BubbleBlock::from_wav_block_and_bub_field(n.into(), vec![vec![vec![255]]].into())
+    }).collect::<Vec<BubbleBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(bub_files[0])?);
+    writer.write_details(&write_bub)?;
+    writer.write_blocks(&write_bub, bub_blocks)?;
+    drop(writer);
+
+    // 64 bits to 32 bits
+    let mut reader = BufReader::new(File::open(bub_files[0])?);
+    let mut writer = BufWriter::new(File::create(bub_files[1])?);
+    let (written_bub, stats) = transcode_bub(&mut reader, &mut writer, 32)?;
+    drop(writer);
+    assert_eq!(stats.samples, 4);
+    assert_eq!(stats.precision_loss, 3);
+    assert_eq!(stats.out_of_range, 1);
+    assert_eq!(stats.underflow, 1);
+    assert!(stats.max_error > 0.0 && stats.max_error < 1e-8);
+
+    let mut reader = BufReader::new(File::open(bub_files[1])?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bub);
+    assert_eq!(read_bub.bits_per_sample, 32);
+    let read_samples: Vec<f32> = read_bub_blocks.0.iter().map(|b| b.wav_block.into()).collect();
+    assert_eq!(read_samples, vec![0.5, 0.1, f32::INFINITY, 0.0]);
+    assert_eq!(read_bub_blocks.0[0].bub_field, vec![vec![vec![255]]].into());
+
+    // 32 bits to 64 bits Wav
+    let mut reader = BufReader::new(File::open(bub_files[1])?);
+    let mut writer = BufWriter::new(File::create(wav_files[0])?);
+    bub_to_wav(&mut reader, &mut writer, FieldHandling::Drop, SampleFormat::Float, 32)?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(wav_files[0])?);
+    let mut writer = BufWriter::new(File::create(wav_files[1])?);
+    let (written_wav, stats) = transcode_wav(&mut reader, &mut writer, 64)?;
+    drop(writer);
+    assert_eq!(stats, NarrowingStats { samples: 4, ..Default::default() });
+
+    let mut reader = BufReader::new(File::open(wav_files[1])?);
+    let read_wav: Wav = reader.read_details()?;
+    let read_wav_blocks: WavBlocks = reader.read_blocks(&read_wav)?;
+    assert_eq!(read_wav, written_wav);
+    assert_eq!(read_wav.bits_per_sample, 64);
+    let read_samples: Vec<f64> = read_wav_blocks.0.iter().map(|&b| b.into()).collect();
+    assert_eq!(read_samples, vec![0.5, 0.1f32 as f64, f64::INFINITY, 0.0]);
+
+    assert!(transcode_wav(&mut BufReader::new(File::open(wav_files[1])?), &mut BufWriter::new(Vec::new()), 16).is_err());
+
+    // 5.1 (side) 16 bits integer Wav to 32 bits
+    let write_wav = Wav {
+        channel_mask: 0x60f,
+        ..Wav::from_sample_format(6, 48000, SampleFormat::Int, 16, 2)?
+    };
+    let wav_blocks = WavBlocks::from((0..12).map(|i| WavBlock::from(i as f64 / 32.0)).collect::<Vec<WavBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(wav_files[0])?);
+    writer.write_details(&write_wav)?;
+    writer.write_blocks(&write_wav, wav_blocks)?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(wav_files[0])?);
+    let mut writer = BufWriter::new(File::create(wav_files[1])?);
+    let (written_wav, _) = transcode_wav(&mut reader, &mut writer, 32)?;
+    drop(writer);
+    assert_eq!(written_wav.channel_mask, 0x60f);
+
+    let mut reader = BufReader::new(File::open(wav_files[1])?);
+    let read_wav: Wav = reader.read_details()?;
+    assert_eq!(read_wav.format_tag, 3);
+    assert_eq!(read_wav.channels, 6);
+    assert_eq!(read_wav.channel_mask, 0x60f);
+    assert_eq!(read_wav.bits_per_sample, 32);
+    assert_eq!(read_wav.blocks(), 2);
+    let read_samples = (0..12).map(|_| {
+        let wav_block: WavBlock = reader.read_block(&read_wav)?;
+        Ok(wav_block.into())
+    }).collect::<std::io::Result<Vec<f32>>>()?;
+    assert_eq!(read_samples, (0..12).map(|i| i as f32 / 32.0).collect::<Vec<f32>>());
+
+    for file in bub_files.iter().chain(&wav_files) {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn wav_to_bub_along_test() -> Result<(), Box<dyn std::error::Error>> {
+    let wav_file = "along.wav";
+    let bub_file = "along.bub";
+    // 4 blocks per second
+    let write_wav = Wav::from_sample_format(1, 4, SampleFormat::Float, 32, 5)?;
+    let wav_blocks = WavBlocks::from(vec![WavBlock::from(0.5f32); 5].into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    writer.write_details(&write_wav)?;
+    writer.write_blocks(&write_wav, wav_blocks)?;
+    drop(writer);
+
+    // from left to right in 1 second
+    let keyframe = |x: f64, intensity: f64| Keyframe {
+        position: (x, 0.5, 0.5).into(),
+        intensity,
+        ..Default::default()
+    };
+    let trajectory = Trajectory::from(vec![(0.0, keyframe(0.0, 1.0)), (1.0, keyframe(1.0, 0.2))]);
+    let bub = Bubble {
+        bub_field_size: (0u8, 2u8, 0u8).into(),
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let mut writer = BufWriter::new(File::create(bub_file)?);
+    let written_bub = wav_to_bub_along(&mut reader, &mut writer, &bub, &trajectory)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bub);
+    assert_eq!(read_bub.blocks, 5);
+    let cells: Vec<Vec<u8>> = read_bub_blocks.0.iter().map(|b| b.bub_field.values()[0].iter().map(|column| column[0]).collect()).collect();
+    assert_eq!(cells, vec![
+        vec![255, 0, 0, 0],
+        vec![0, 204, 0, 0],
+        vec![0, 0, 153, 0],
+        vec![0, 0, 0, 102],
+        vec![0, 0, 0, 51]
+    ]);
+    assert_eq!(read_bub.overall, vec![vec![vec![255], vec![204], vec![153], vec![102]]].into());
+
+    // Trajectory extracted from Bubble makes same Bubble fields through CSV.
+    let mut reader = BufReader::new(File::open(bub_file)?);
+    let extracted = Trajectory::from_csv(&extract_bub(&mut reader)?.to_csv())?;
+    assert_eq!(extracted.keyframes.len(), 5);
+    assert_eq!(extracted.keyframes[1].1.position, (0.375, 0.5, 0.5).into());
+    for (i, bub_block) in read_bub_blocks.0.iter().enumerate() {
+        assert_eq!(extracted.bub_field(i as u64, 4, read_bub.bub_field_size)?, bub_block.bub_field);
+    }
+
+    remove_file(wav_file)?;
+    remove_file(bub_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn malformed_wav_test() -> Result<(), Box<dyn std::error::Error>> {
+    let wav_file = "convert5.wav";
+    let out_file = "convert5.out";
+    let wav = Wav::from_sample_format(1, 44100, SampleFormat::Int, 16, 4)?;
+    // No channels, no data block size and 12 bits integer
+    for wav in [
+        Wav { channels: 0, data_block_size: 0, ..wav },
+        Wav { data_block_size: 0, ..wav },
+        Wav { bits_per_sample: 12, ..wav }
+    ] {
+        let mut writer = BufWriter::new(File::create(wav_file)?);
+        writer.write_details(&wav)?;
+        writer.write_all(&[0; 8])?;
+        drop(writer);
+
+        let bub = Bubble { bub_field_size: (0u8, 0u8, 0u8).into(), ..Default::default() };
+        let mut reader = BufReader::new(File::open(wav_file)?);
+        let mut writer = BufWriter::new(File::create(out_file)?);
+        let error = wav_to_bub(&mut reader, &mut writer, &bub, |_| vec![vec![vec![255]]].into()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut reader = BufReader::new(File::open(wav_file)?);
+        let mut writer = BufWriter::new(File::create(out_file)?);
+        let error = wav_to_oao(&mut reader, &mut writer, None, (0u8, 0u8, 0u8).into()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let mut reader = BufReader::new(File::open(wav_file)?);
+        let mut writer = BufWriter::new(File::create(out_file)?);
+        let error = transcode_wav(&mut reader, &mut writer, 32).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    remove_file(wav_file)?;
+    remove_file(out_file)?;
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,571 @@
+use std::io::{BufReader, BufWriter};
+use floaout::analysis::loudness::measure_bub;
+use std::fs::{File, remove_file};
+use floaout::edit::concat::{concat, ConcatOptions, Matching};
+use floaout::edit::gain::{Fade, FadeCurve, GainStage};
+use floaout::edit::merge::{merge, DuplicatePolicy, MergeOptions};
+use floaout::edit::normalize::{normalize_bub, normalize_oao, GainMode, Limiter, NormalizeOptions};
+use floaout::edit::segment::{edit_bub, edit_oao, Segment};
+use floaout::edit::stems::{rewrite_stems, Stem};
+use floaout::format::BubbleField;
+use floaout::format::bub::{Bubble, BubbleBlock, BubbleBlocks};
+use floaout::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
+use floaout::io::read::{ReadBubsIn, ReadFmt};
+use floaout::io::validate::validate_oao;
+use floaout::io::write::{WriteBubsIn, WriteFmt};
+
+/// This function writes Bubble whose samples are `samples`, and whose field is left when sample is positive.
+fn write_bub(file: &str, samples: &[f32]) -> Result<(), Box<dyn std::error::Error>> {
+    let left: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let right: BubbleField = vec![vec![vec![0], vec![255]]].into();
+    let bub = Bubble {
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        blocks: samples.len() as u64,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        overall: vec![vec![vec![255], vec![255]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks = BubbleBlocks::from(samples.iter().map(|&n| {
+        BubbleBlock::from_wav_block_and_bub_field(n.into(), if n > 0.0 { left.clone() } else { right.clone() })
+    }).collect::<Vec<BubbleBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&bub)?;
+    writer.write_blocks(&bub, bub_blocks)?;
+
+    Ok(())
+}
+
+/// This function edits Bubbles and returns details and samples.
+fn edit(files: &[&str], output: &str, segments: &[Segment]) -> Result<(Bubble, Vec<f32>), Box<dyn std::error::Error>> {
+    let mut readers = files.iter().map(|file| Ok(BufReader::new(File::open(file)?))).collect::<std::io::Result<Vec<_>>>()?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    let written_bub = edit_bub(&mut readers, &mut writer, segments)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_bub: Bubble = reader.read_details()?;
+    let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+    assert_eq!(read_bub, written_bub);
+    remove_file(output)?;
+
+    Ok((read_bub, read_bub_blocks.0.iter().map(|b| b.wav_block.into()).collect()))
+}
+
+#[test]
+fn edit_bub_test() -> Result<(), Box<dyn std::error::Error>> {
+    let files = ["edit1.bub", "edit2.bub"];
+    let output = "edit3.bub";
+    write_bub(files[0], &[1.0, 2.0, 3.0, 4.0, 5.0])?;
+    write_bub(files[1], &[-1.0, -2.0])?;
+
+    // trim
+    let (bub, samples) = edit(&files, output, &Segment::trim(1..3))?;
+    assert_eq!(bub.blocks, 2);
+    assert_eq!(samples, vec![2.0, 3.0]);
+    // Only left is in overall.
+    assert_eq!(bub.overall, vec![vec![vec![255], vec![0]]].into());
+
+    // cut
+    let (bub, samples) = edit(&files, output, &Segment::cut(1..4))?;
+    assert_eq!(bub.blocks, 2);
+    assert_eq!(samples, vec![1.0, 5.0]);
+    // Reversed range deletes nothing.
+    let (bub, samples) = edit(&files, output, &Segment::cut(std::ops::Range { start: 4, end: 1 }))?;
+    assert_eq!(bub.blocks, 5);
+    assert_eq!(samples, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+
+    // insert silence
+    let (bub, samples) = edit(&files, output, &Segment::insert_silence(2, 2))?;
+    assert_eq!(bub.blocks, 7);
+    assert_eq!(samples, vec![1.0, 2.0, 0.0, 0.0, 3.0, 4.0, 5.0]);
+    assert_eq!(bub.overall, vec![vec![vec![255], vec![0]]].into());
+
+    // splice
+    let (bub, samples) = edit(&files, output, &Segment::splice(4))?;
+    assert_eq!(bub.blocks, 7);
+    assert_eq!(samples, vec![1.0, 2.0, 3.0, 4.0, -1.0, -2.0, 5.0]);
+    assert_eq!(bub.overall, vec![vec![vec![255], vec![255]]].into());
+
+    // out of range
+    let mut readers = vec![BufReader::new(File::open(files[0])?)];
+    let mut writer = BufWriter::new(File::create(output)?);
+    assert!(edit_bub(&mut readers, &mut writer, &Segment::trim(6..8)).is_err());
+    drop(writer);
+
+    for file in files.iter().chain(&[output]) {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn edit_oao_test() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "edit1.oao";
+    let output = "edit2.oao";
+    let oao = Floaout {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 3,
+        sampling_rate: 48000,
+        bits_per_sample: 64,
+        ..Default::default()
+    };
+    let bubs_in_oao = BubblesInFloaout::from(vec![
+        BubbleInFloaout { bubble_id: 1, name_size: 1, name: "A".into(), color: (1, 1, 1).into() },
+        BubbleInFloaout { bubble_id: 2, name_size: 1, name: "B".into(), color: (2, 2, 2).into() }
+    ]);
+    let oao_blocks = FloaoutBlocks::from((0..3).map(|i| {
+        FloaoutBlock::from(vec![
+            BubbleBlock::from_wav_block_and_bub_field((i as f64).into(), vec![vec![vec![255]]].into()),
+            BubbleBlock::from_wav_block_and_bub_field((-i as f64).into(), vec![vec![vec![1]]].into())
+        ])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(input)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    writer.write_blocks(&oao, oao_blocks.clone())?;
+    drop(writer);
+
+    let mut readers = vec![BufReader::new(File::open(input)?)];
+    let mut writer = BufWriter::new(File::create(output)?);
+    let segments = vec![Segment::Blocks(0, 2..3), Segment::Silence(1), Segment::Blocks(0, 0..1)];
+    let written_oao = edit_oao(&mut readers, &mut writer, &segments)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_oao.blocks, 3);
+    assert_eq!(read_bubs_in_oao, bubs_in_oao);
+    assert_eq!(read_oao_blocks.0[0], oao_blocks.0[2]);
+    assert_eq!(read_oao_blocks.0[1].0[1], BubbleBlock::from_wav_block_and_bub_field(0.0f64.into(), vec![vec![vec![0]]].into()));
+    assert_eq!(read_oao_blocks.0[2], oao_blocks.0[0]);
+
+    remove_file(input)?;
+    remove_file(output)?;
+
+    Ok(())
+}
+
+/// This function writes Floaout whose Bubbles have same sample and Bubble field in each block.
+fn write_oao(file: &str, bubs_in_oao: Vec<BubbleInFloaout>, samples: &[f64], value: u8) -> Result<(), Box<dyn std::error::Error>> {
+    let oao = Floaout {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        bubbles: bubs_in_oao.len() as u16,
+        blocks: samples.len() as u64,
+        sampling_rate: 48000,
+        bits_per_sample: 64,
+        ..Default::default()
+    };
+    let oao_blocks = FloaoutBlocks::from(samples.iter().map(|&n| {
+        FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(n.into(), vec![vec![vec![value]]].into()); bubs_in_oao.len()])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(bubs_in_oao))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+
+    Ok(())
+}
+
+#[test]
+fn concat_test() -> Result<(), Box<dyn std::error::Error>> {
+    let files = ["concat1.oao", "concat2.oao"];
+    let output = "concat3.oao";
+    let bub_in_oao = |bubble_id: u128, name: &str| BubbleInFloaout {
+        bubble_id,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    write_oao(files[0], vec![bub_in_oao(1, "Piano"), bub_in_oao(0, "Vocal")], &[1.0; 4], 200)?;
+    write_oao(files[1], vec![bub_in_oao(0, "Vocal"), bub_in_oao(3, "Drums"), bub_in_oao(1, "Keys")], &[0.5; 4], 100)?;
+
+    // without crossfade
+    let mut readers = vec![BufReader::new(File::open(files[0])?), BufReader::new(File::open(files[1])?)];
+    let mut writer = BufWriter::new(File::create(output)?);
+    let written_oao = concat(&mut readers, &mut writer, &ConcatOptions::default())?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_oao.blocks, 8);
+    // "Keys" is same as "Piano" by Bubble ID.
+    let names: Vec<&str> = read_bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.as_str()).collect();
+    assert_eq!(names, vec!["Piano", "Vocal", "Drums"]);
+    let samples = |block: &FloaoutBlock| block.0.iter().map(|b| b.wav_block.into()).collect::<Vec<f64>>();
+    assert_eq!(samples(&read_oao_blocks.0[3]), vec![1.0, 1.0, 0.0]);
+    assert_eq!(samples(&read_oao_blocks.0[4]), vec![0.5, 0.5, 0.5]);
+    assert_eq!(read_oao_blocks.0[3].0[2].bub_field, vec![vec![vec![0]]].into());
+
+    // crossfade of 2 blocks by name
+    let mut readers = vec![BufReader::new(File::open(files[0])?), BufReader::new(File::open(files[1])?)];
+    let mut writer = BufWriter::new(File::create(output)?);
+    let options = ConcatOptions {
+        matching: Matching::Name,
+        crossfade: 2
+    };
+    concat(&mut readers, &mut writer, &options)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao.blocks, 6);
+    let names: Vec<&str> = read_bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.as_str()).collect();
+    assert_eq!(names, vec!["Piano", "Vocal", "Drums", "Keys"]);
+    // Vocal is on both sides.
+    let t = 0.25 * std::f64::consts::FRAC_PI_2;
+    let vocal: f64 = read_oao_blocks.0[2].0[1].wav_block.into();
+    assert!((vocal - (t.cos() + 0.5 * t.sin())).abs() < 1e-12);
+    assert_eq!(read_oao_blocks.0[2].0[1].bub_field, vec![vec![vec![175]]].into());
+    // Piano fades out, and its Bubble field is kept.
+    let piano: f64 = read_oao_blocks.0[3].0[0].wav_block.into();
+    assert!((piano - (3.0 * t).cos()).abs() < 1e-12);
+    assert_eq!(read_oao_blocks.0[3].0[0].bub_field, vec![vec![vec![200]]].into());
+    assert_eq!(samples(&read_oao_blocks.0[4]), vec![0.0, 0.5, 0.5, 0.5]);
+
+    // Crossfade is longer than Floaout.
+    let mut readers = vec![BufReader::new(File::open(files[0])?), BufReader::new(File::open(files[1])?)];
+    let mut writer = BufWriter::new(Vec::new());
+    let options = ConcatOptions {
+        crossfade: 5,
+        ..Default::default()
+    };
+    assert!(concat(&mut readers, &mut writer, &options).is_err());
+
+    for file in files.iter().chain(&[output]) {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gain_test() -> Result<(), Box<dyn std::error::Error>> {
+    let file = "gain1.oao";
+    let output = "gain2.oao";
+    let bub_in_oao = |name: &str| BubbleInFloaout {
+        bubble_id: 0,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    write_oao(file, vec![bub_in_oao("Piano"), bub_in_oao("Vocal"), bub_in_oao("Drums")], &[1.0; 4], 255)?;
+
+    let mut stage = GainStage::new(3);
+    stage.bubbles[0].solo = true;
+    stage.bubbles[0].fade_in = Some(Fade { blocks: 2, curve: FadeCurve::Linear });
+    stage.bubbles[1].solo = true;
+    stage.bubbles[1].gain = -20.0;
+    stage.bubbles[1].fade_out = Some(Fade { blocks: 2, curve: FadeCurve::Linear });
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let written_oao = stage.process(&mut reader, &mut writer)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_bubs_in_oao.0.len(), 3);
+    let samples = |bubble: usize| read_oao_blocks.0.iter().map(|block| block.0[bubble].wav_block.into()).collect::<Vec<f64>>();
+    assert_eq!(samples(0), vec![0.0, 0.5, 1.0, 1.0]);
+    for (n, m) in samples(1).into_iter().zip(&[0.1, 0.1, 0.05, 0.0]) {
+        assert!((n - m).abs() < 1e-12);
+    }
+    // Drums isn't soloed.
+    assert_eq!(samples(2), vec![0.0; 4]);
+    assert_eq!(read_oao_blocks.0[0].0[2].bub_field, vec![vec![vec![255]]].into());
+
+    // Number of gains is different.
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut writer = BufWriter::new(Vec::new());
+    assert!(GainStage::new(2).process(&mut reader, &mut writer).is_err());
+
+    // Breakpoints aren't sorted.
+    let mut stage = GainStage::new(3);
+    stage.bubbles[2].automation = vec![(2.0, -10.0), (1.0, 0.0)];
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut writer = BufWriter::new(Vec::new());
+    assert_eq!(stage.process(&mut reader, &mut writer).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+
+    remove_file(file)?;
+    remove_file(output)?;
+
+    Ok(())
+}
+
+#[test]
+fn merge_test() -> Result<(), Box<dyn std::error::Error>> {
+    let files = ["merge1.oao", "merge2.oao"];
+    let output = "merge3.oao";
+    let bub_in_oao = |bubble_id: u128, name: &str| BubbleInFloaout {
+        bubble_id,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    write_oao(files[0], vec![bub_in_oao(1, "Piano"), bub_in_oao(0, "Vocal")], &[1.0; 4], 200)?;
+    write_oao(files[1], vec![bub_in_oao(0, "Vocal"), bub_in_oao(1, "Piano FX")], &[0.5; 3], 100)?;
+    let merged = |options: &MergeOptions| -> Result<(Floaout, BubblesInFloaout, FloaoutBlocks), Box<dyn std::error::Error>> {
+        let mut a = BufReader::new(File::open(files[0])?);
+        let mut b = BufReader::new(File::open(files[1])?);
+        let mut writer = BufWriter::new(File::create(output)?);
+        let written_oao = merge(&mut a, &mut b, &mut writer, options)?;
+        drop(writer);
+
+        let mut reader = BufReader::new(File::open(output)?);
+        let read_oao: Floaout = reader.read_details()?;
+        let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+        let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+        assert_eq!(read_oao, written_oao);
+        Ok((read_oao, read_bubs_in_oao, read_oao_blocks))
+    };
+    let samples = |block: &FloaoutBlock| block.0.iter().map(|b| b.wav_block.into()).collect::<Vec<f64>>();
+
+    // keep both with offset
+    let options = MergeOptions {
+        offset: 2,
+        bub_field_size: Some((1u8, 0u8, 0u8).into()),
+        ..Default::default()
+    };
+    let (read_oao, read_bubs_in_oao, read_oao_blocks) = merged(&options)?;
+    assert_eq!(read_oao.bubbles, 4);
+    assert_eq!(read_oao.blocks, 5);
+    let names: Vec<&str> = read_bubs_in_oao.0.iter().map(|bub_in_oao| bub_in_oao.name.as_str()).collect();
+    assert_eq!(names, vec!["Piano", "Vocal", "Vocal", "Piano FX"]);
+    assert_eq!(samples(&read_oao_blocks.0[1]), vec![1.0, 1.0, 0.0, 0.0]);
+    assert_eq!(samples(&read_oao_blocks.0[2]), vec![1.0, 1.0, 0.5, 0.5]);
+    assert_eq!(samples(&read_oao_blocks.0[4]), vec![0.0, 0.0, 0.5, 0.5]);
+    assert_eq!(read_oao_blocks.0[2].0[3].bub_field, vec![vec![vec![100]], vec![vec![100]]].into());
+
+    // sum
+    let options = MergeOptions {
+        duplicate_policy: DuplicatePolicy::Sum,
+        ..Default::default()
+    };
+    let (read_oao, _, read_oao_blocks) = merged(&options)?;
+    assert_eq!(read_oao.bubbles, 3);
+    assert_eq!(read_oao.blocks, 4);
+    assert_eq!(samples(&read_oao_blocks.0[0]), vec![1.5, 1.0, 0.5]);
+    assert_eq!(samples(&read_oao_blocks.0[3]), vec![1.0, 1.0, 0.0]);
+    assert_eq!(read_oao_blocks.0[0].0[0].bub_field, vec![vec![vec![200]]].into());
+
+    // fail
+    let options = MergeOptions {
+        duplicate_policy: DuplicatePolicy::Fail,
+        ..Default::default()
+    };
+    assert!(merged(&options).is_err());
+
+    for file in files.iter().chain(&[output]) {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn rewrite_stems_test() -> Result<(), Box<dyn std::error::Error>> {
+    let file = "stems1.oao";
+    let output = "stems2.oao";
+    let bub_in_oao = |bubble_id: u128, name: &str| BubbleInFloaout {
+        bubble_id,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    let bubs_in_oao = vec![bub_in_oao(1, "Piano"), bub_in_oao(2, "Vocal"), bub_in_oao(3, "Drums")];
+    let oao = Floaout {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        bubbles: 3,
+        blocks: 2,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    let oao_blocks = FloaoutBlocks::from((0..2).map(|i| {
+        FloaoutBlock::from((0..3).map(|j| BubbleBlock::from_wav_block_and_bub_field(((i * 3 + j) as f32).into(), vec![vec![vec![j as u8]]].into())).collect::<Vec<BubbleBlock>>())
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(bubs_in_oao))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+    drop(writer);
+
+    let stems = vec![
+        Stem {
+            source: 2,
+            name: Some("Percussion".into()),
+            color: Some((1, 2, 3).into())
+        },
+        Stem::new(0)
+    ];
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let (written_oao, written_bubs_in_oao) = rewrite_stems(&mut reader, &mut writer, &stems)?;
+    drop(writer);
+    // CRC-32C of rewritten details, Bubbles and blocks is valid.
+    assert_eq!(validate_oao(&mut BufReader::new(File::open(output)?))?, vec![]);
+
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let read_bubs_in_oao: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    assert_eq!(read_oao, written_oao);
+    assert_eq!(read_bubs_in_oao, written_bubs_in_oao);
+    assert_eq!(read_oao.bubbles, 2);
+    assert_eq!(read_bubs_in_oao.0[0], BubbleInFloaout {
+        bubble_id: 3,
+        name_size: 10,
+        name: "Percussion".into(),
+        color: (1, 2, 3).into()
+    });
+    assert_eq!(read_bubs_in_oao.0[1], bub_in_oao(1, "Piano"));
+    let samples = |block: &FloaoutBlock| block.0.iter().map(|b| b.wav_block.into()).collect::<Vec<f32>>();
+    assert_eq!(samples(&read_oao_blocks.0[0]), vec![2.0, 0.0]);
+    assert_eq!(samples(&read_oao_blocks.0[1]), vec![5.0, 3.0]);
+    assert_eq!(read_oao_blocks.0[1].0[0].bub_field, vec![vec![vec![2]]].into());
+
+    // There is no Bubble 3.
+    let mut reader = BufReader::new(File::open(file)?);
+    let mut writer = BufWriter::new(Vec::new());
+    assert!(rewrite_stems(&mut reader, &mut writer, &Stem::reorder(&[3, 0])).is_err());
+
+    remove_file(file)?;
+    remove_file(output)?;
+
+    Ok(())
+}
+
+#[test]
+fn normalize_test() -> Result<(), Box<dyn std::error::Error>> {
+    let file = "normalize1.bub";
+    let output = "normalize2.bub";
+    // 1 kHz sine wave at -20 dBFS is -23 LUFS.
+    let sine = |amplitude: f64, i: u64| amplitude * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / 48000.0).sin();
+    write_bub(file, &(0..48000).map(|i| sine(0.1, i) as f32).collect::<Vec<f32>>())?;
+    let normalize = |options: &NormalizeOptions| -> Result<(f64, Vec<f32>), Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(File::open(file)?);
+        let mut writer = BufWriter::new(File::create(output)?);
+        let (written_bub, gain) = normalize_bub(&mut reader, &mut writer, options)?;
+        drop(writer);
+
+        let mut reader = BufReader::new(File::open(output)?);
+        let read_bub: Bubble = reader.read_details()?;
+        let read_bub_blocks: BubbleBlocks = reader.read_blocks(&read_bub)?;
+        assert_eq!(read_bub, written_bub);
+        Ok((gain, read_bub_blocks.0.iter().map(|b| b.wav_block.into()).collect()))
+    };
+
+    let options = NormalizeOptions {
+        target: -13.0,
+        ..Default::default()
+    };
+    let (gain, _) = normalize(&options)?;
+    assert!((gain - 10.0).abs() < 0.1);
+    let loudness = measure_bub(&mut BufReader::new(File::open(output)?))?;
+    assert!((loudness.integrated + 13.0).abs() < 0.1);
+
+    // Gain is lowered by true peak.
+    let options = NormalizeOptions {
+        target: -3.0,
+        ..Default::default()
+    };
+    let (gain, _) = normalize(&options)?;
+    assert!((gain - 19.0).abs() < 0.1);
+
+    // Limiter keeps samples under the ceiling.
+    let options = NormalizeOptions {
+        target: -3.0,
+        limiter: Some(Limiter::default()),
+        ..Default::default()
+    };
+    let (gain, samples) = normalize(&options)?;
+    assert!((gain - 20.0).abs() < 0.1);
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(samples.iter().all(|n| n.abs() <= ceiling + 1e-6));
+    assert!(samples.iter().any(|n| n.abs() > ceiling * 0.99));
+
+    // Limiter keeps true peak under the ceiling.
+    // Samples of the sine wave of a quarter of sampling rate are about 3 dB under its true peak.
+    write_bub(file, &(0..48000).map(|i| (0.1 * (std::f64::consts::PI * (i as f64 / 2.0 + 0.25)).sin()) as f32).collect::<Vec<f32>>())?;
+    let options = NormalizeOptions {
+        target: 0.0,
+        limiter: Some(Limiter::default()),
+        ..Default::default()
+    };
+    let (_, samples) = normalize(&options)?;
+    let loudness = measure_bub(&mut BufReader::new(File::open(output)?))?;
+    assert!(loudness.true_peak <= -1.0 + 1e-3);
+    assert!(samples.iter().all(|n| n.abs() <= ceiling * 0.75));
+
+    // each Bubble in Floaout
+    let oao_file = "normalize.oao";
+    let oao = Floaout {
+        bub_field_size: (0u8, 0u8, 0u8).into(),
+        bubbles: 2,
+        blocks: 48000,
+        sampling_rate: 48000,
+        bits_per_sample: 64,
+        ..Default::default()
+    };
+    let bub_in_oao = |name: &str| BubbleInFloaout {
+        bubble_id: 0,
+        name_size: name.len() as u8,
+        name: name.into(),
+        color: (0, 0, 0).into()
+    };
+    let oao_blocks = FloaoutBlocks::from((0..48000).map(|i| {
+        FloaoutBlock::from(vec![
+            BubbleBlock::from_wav_block_and_bub_field(sine(0.1, i).into(), vec![vec![vec![255]]].into()),
+            BubbleBlock::from_wav_block_and_bub_field(sine(0.05, i).into(), vec![vec![vec![255]]].into())
+        ])
+    }).collect::<Vec<FloaoutBlock>>().into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(oao_file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&BubblesInFloaout::from(vec![bub_in_oao("Piano"), bub_in_oao("Vocal")]))?;
+    writer.write_blocks(&oao, oao_blocks)?;
+    drop(writer);
+
+    let options = NormalizeOptions {
+        mode: GainMode::PerBubble,
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let (_, gains) = normalize_oao(&mut reader, &mut writer, &options)?;
+    drop(writer);
+    assert!(gains[0].abs() < 0.1);
+    assert!((gains[1] - 6.02).abs() < 0.1);
+    let mut reader = BufReader::new(File::open(output)?);
+    let read_oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let read_oao_blocks: FloaoutBlocks = reader.read_blocks(&read_oao)?;
+    let sample: f64 = read_oao_blocks.0[12].0[1].wav_block.into();
+    assert!((sample - sine(0.1, 12)).abs() < 1e-3);
+
+    // Bubbles are summed.
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let (_, gains) = normalize_oao(&mut reader, &mut writer, &NormalizeOptions::default())?;
+    drop(writer);
+    assert!((gains[0] + 3.52).abs() < 0.1);
+    assert_eq!(gains[0], gains[1]);
+
+    for file in &[file, output, oao_file] {
+        remove_file(file)?;
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+use floaout::render::layout::{SpeakerLayout, SpeakerPosition};
+
+#[test]
+fn layout_presets_test() -> Result<(), Box<dyn std::error::Error>> {
+    for (name, speakers, lfe) in &[("stereo", 2, 0), ("5.1", 6, 1), ("7.1.4", 12, 1), ("22.2", 24, 2)] {
+        let layout = SpeakerLayout::preset(name).unwrap();
+        layout.validate()?;
+
+        assert_eq!(layout.speakers.len(), *speakers);
+        assert_eq!(layout.speakers.iter().filter(|s| s.lfe).count(), *lfe);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn layout_text_and_json_test() -> Result<(), Box<dyn std::error::Error>> {
+    let mut layout = SpeakerLayout::surround_5_1();
+    layout.speakers[0].delay = 2.5;
+    layout.speakers[1].trim = -3.0;
+    layout.speakers[2].position = SpeakerPosition::Field((0.5, 1.0, 0.5).into());
+
+    assert_eq!(SpeakerLayout::from_text(&layout.to_string())?, layout);
+    assert_eq!(SpeakerLayout::from_json(&layout.to_json())?, layout);
+
+    assert!(SpeakerLayout::from_text("L polar 30").is_err());
+    assert!(SpeakerLayout::from_text("L field 0 0 2").is_err());
+    assert!(SpeakerLayout::from_json(r#"{"speakers": [{"name": "L"}]}"#).is_err());
+    // Deep nesting and a sign in unicode escape are errors, not a stack overflow or a character.
+    assert!(SpeakerLayout::from_json(&format!("{}{}", "[".repeat(100000), "]".repeat(100000))).is_err());
+    assert!(SpeakerLayout::from_json(r#"{"speakers": [{"name": "\u0041", "azimuth": 0, "elevation": 0}]}"#).is_ok());
+    assert!(SpeakerLayout::from_json(r#"{"speakers": [{"name": "\u+041", "azimuth": 0, "elevation": 0}]}"#).is_err());
+    // High surrogate is followed by low surrogate.
+    let layout = SpeakerLayout::from_json(r#"{"speakers": [{"name": "\ud83d\ude00", "azimuth": 0, "elevation": 0}]}"#)?;
+    assert_eq!(layout.speakers[0].name, "\u{1f600}");
+    assert!(SpeakerLayout::from_json(r#"{"speakers": [{"name": "\ud800\u0041", "azimuth": 0, "elevation": 0}]}"#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn layout_cells_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 4 x 4 x 4
+    let cells = SpeakerLayout::surround_7_1_4().cells((2u8, 2u8, 2u8).into())?;
+
+    // L is front left, Rrs is back right, Ltf is top front left.
+    assert_eq!(cells[0], (3, 1, 2));
+    assert_eq!(cells[7], (0, 3, 2));
+    assert_eq!(cells[8], (3, 0, 3));
+
+    Ok(())
+}
\ No newline at end of file
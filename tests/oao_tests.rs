@@ -1,4 +1,4 @@
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Cursor, ErrorKind};
 use std::fs::{File, remove_file};
 use floaout::format::BubbleField;
 use floaout::format::bub::BubbleBlock;
@@ -18,15 +18,21 @@ fn oao_test() -> Result<(), Box<dyn std::error::Error>> {
         bubbles: 2,
         blocks: 2,
         sampling_rate: 44100,
-        bits_per_sample: 32
+        bits_per_sample: 32,
+        title_size: 4,
+        title: "Song".into(),
+        artist_size: 6,
+        artist: "Artist".into()
     };
     // Details of BubblesInFloaout
     let write_bub1_in_oao = BubbleInFloaout {
+        bubble_id: 1,
         name_size: 3,
         name: "た".into(),
         color: (255, 255, 255).into()
     };
     let write_bub2_in_oao = BubbleInFloaout {
+        bubble_id: 0,
         name_size: 0,
         name: "".into(),
         color: (0, 0, 0).into()
@@ -74,6 +80,23 @@ fn oao_test() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(read_bubs_in_oao, write_bubs_in_oao);
     assert_eq!(read_oao_blocks, write_oao_blocks);
 
+    // Every CRC-32C is checked.
+    let mut bytes = std::fs::read(file)?;
+    let length = bytes.len();
+    bytes[length - 5] ^= 1;
+    let mut reader = BufReader::new(Cursor::new(bytes.clone()));
+    let read_oao: Floaout = reader.read_details()?;
+    let _: BubblesInFloaout = reader.read_bubs_details(&read_oao)?;
+    let error = reader.read_blocks(&read_oao).map(|_: FloaoutBlocks| ()).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    bytes[length - 5] ^= 1;
+    bytes[32] ^= 1;
+    let error = BufReader::new(Cursor::new(bytes)).read_details().map(|_: Floaout| ()).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
     remove_file(file)?;
 
     Ok(())
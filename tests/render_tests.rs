@@ -0,0 +1,213 @@
+use std::io::{BufReader, BufWriter};
+use std::fs::{File, remove_file};
+use floaout::format::BubbleField;
+use floaout::format::bub::BubbleBlock;
+use floaout::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
+use floaout::format::wav::{Wav, WavBlocks};
+use floaout::io::read::ReadFmt;
+use floaout::io::write::{WriteBubsIn, WriteFmt};
+use floaout::render::Renderer;
+use floaout::render::distance::{BubbleMotion, DistanceModel, Doppler};
+use floaout::render::downmix::{Limiter, PanLaw, StereoDownmix};
+use floaout::render::layout::SpeakerLayout;
+use floaout::render::listener::{Listener, Pose, PoseTrack};
+use floaout::render::room::Room;
+
+fn write_left_bubble(file: &str, blocks: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let oao = Floaout {
+        bub_field_size: (0u8, 1u8, 0u8).into(),
+        bubbles: 1,
+        blocks,
+        sampling_rate: 100,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    let bubs_in_oao: BubblesInFloaout = vec![BubbleInFloaout::default()].into();
+    // Bubble in the left half of the field
+    let bub_field: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let oao_block = FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(1.0f32.into(), bub_field)]);
+    let oao_blocks = FloaoutBlocks::from(vec![oao_block; blocks as usize].into_boxed_slice());
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    writer.write_blocks(&oao, oao_blocks)?;
+
+    Ok(())
+}
+
+fn render(oao_file: &str, wav_file: &str, renderer: &mut Renderer) -> Result<(Wav, Vec<f32>), Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    let wav = renderer.render_floaout(&mut reader, &mut writer)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let read_wav: Wav = reader.read_details()?;
+    assert_eq!(read_wav, wav);
+    // Read all samples of all channels.
+    let all = Wav {
+        channels: 1,
+        ..read_wav
+    };
+    let wav_blocks: WavBlocks = reader.read_blocks(&all)?;
+    remove_file(wav_file)?;
+
+    Ok((read_wav, wav_blocks.0.iter().map(|&b| b.into()).collect()))
+}
+
+#[test]
+fn render_listener_test() -> Result<(), Box<dyn std::error::Error>> {
+    let oao_file = "render1.oao";
+    write_left_bubble(oao_file, 4)?;
+
+    // Facing front, the bubble is on the left.
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    let (wav, samples) = render(oao_file, "render1.wav", &mut renderer)?;
+    assert_eq!(wav.channels, 2);
+    assert_eq!(samples.len(), 8);
+    assert!(samples[0] > samples[1]);
+
+    // Facing back, the bubble is on the right.
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    renderer.listener = Listener::Static(Pose {
+        yaw: 180.0,
+        ..Default::default()
+    });
+    let (_, samples) = render(oao_file, "render2.wav", &mut renderer)?;
+    assert!(samples[0] < samples[1]);
+
+    // Moving to the left of the bubble, the bubble comes on the right.
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    renderer.listener = Listener::Track(PoseTrack::from_text("0.00, 0.5, 0.5, 0.5, 0, 0, 0\n0.03, 0.0, 0.5, 0.5, 0, 0, 0")?);
+    let (_, samples) = render(oao_file, "render3.wav", &mut renderer)?;
+    assert!(samples[0] > samples[1]);
+    assert!(samples[6] < samples[7]);
+
+    remove_file(oao_file)?;
+
+    Ok(())
+}
+
+#[test]
+fn render_distance_test() -> Result<(), Box<dyn std::error::Error>> {
+    // Bubble at the left of the listener. Distance is 0.25.
+    let bub_field: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let block = |sample: f32| FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field.clone())]);
+
+    // Gain
+    let mut near = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    let mut far = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    far.motions = vec![BubbleMotion {
+        distance_model: DistanceModel::Inverse { reference: 0.125, rolloff: 1.0 },
+        ..Default::default()
+    }];
+    let near_sample = near.render_block(&block(1.0))[0];
+    let far_sample = far.render_block(&block(1.0))[0];
+    assert!((far_sample - near_sample * 0.5).abs() < 1e-6);
+
+    // Delay is 0.25 seconds, that is 25 blocks.
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 100)?;
+    renderer.motions = vec![BubbleMotion {
+        doppler: Some(Doppler { meters: 343.0, speed_of_sound: 343.0 }),
+        ..Default::default()
+    }];
+    let samples: Vec<f64> = (0..30).map(|i| renderer.render_block(&block(if i == 0 { 1.0 } else { 0.0 }))[0]).collect();
+    assert_eq!(samples.iter().position(|&n| n != 0.0), Some(25));
+
+    // Bubble goes left from the listener by a cell of 256 cells every 16 blocks, and impulses are made every 100 blocks.
+    // Delay grows 1000 / 256 / 16 samples every block, so impulses arrive about every 132 blocks and they get quieter.
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 1000)?;
+    renderer.motions = vec![BubbleMotion {
+        distance_model: DistanceModel::Inverse { reference: 0.01, rolloff: 1.0 },
+        doppler: Some(Doppler { meters: 343.0, speed_of_sound: 343.0 }),
+        ..Default::default()
+    }];
+    let samples: Vec<f64> = (0..2048).map(|i| {
+        let mut bub_field = vec![vec![vec![0]; 256]];
+        bub_field[0][127 - i / 16][0] = 255;
+        let sample = if i % 100 == 0 && i <= 1500 { 1.0f32 } else { 0.0 };
+        renderer.render_block(&FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field.into())]))[0]
+    }).collect();
+    // Start and sum of each arrival, which is interpolated over 2 blocks.
+    let mut arrivals: Vec<(usize, f64)> = Vec::new();
+    for (i, &n) in samples.iter().enumerate() {
+        match arrivals.last_mut() {
+            Some((start, sum)) if n != 0.0 && i <= *start + 2 => *sum += n,
+            _ if n != 0.0 => arrivals.push((i, n)),
+            _ => ()
+        }
+    }
+    assert_eq!(arrivals.len(), 16);
+    // Smoothing of distance takes a few blocks at the start.
+    for pair in arrivals[1..].windows(2) {
+        assert!((128..137).contains(&(pair[1].0 - pair[0].0)));
+    }
+    assert!(arrivals[15].1 < arrivals[10].1 && arrivals[10].1 < arrivals[5].1 && arrivals[5].1 < arrivals[0].1);
+
+    Ok(())
+}
+
+#[test]
+fn render_room_test() -> Result<(), Box<dyn std::error::Error>> {
+    let bub_field: BubbleField = vec![vec![vec![255], vec![0]]].into();
+    let block = |sample: f32| FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(sample.into(), bub_field.clone())]);
+    let mut renderer = Renderer::new(SpeakerLayout::stereo(), 1000)?;
+    let mut room = Room::from_size((3.43, 3.43, 3.43));
+    room.order = 1;
+    // Room without width or with absorption over 1.0 isn't valid.
+    assert!(renderer.set_room(Some(Room { size: (0.0, 3.43, 3.43), ..room.clone() })).is_err());
+    assert!(renderer.set_room(Some(Room { absorption: [1.5; 6], ..room.clone() })).is_err());
+    assert!(renderer.room().is_none());
+    renderer.set_room(Some(room))?;
+
+    let samples: Vec<f64> = (0..1000).map(|i| renderer.render_block(&block(if i == 0 { 1.0 } else { 0.0 }))[0]).collect();
+    // Direct sound
+    assert!(samples[0] > 0.0);
+    // Reflection from the left wall comes 1.715 meters later, that is 5 blocks.
+    assert!(samples[1..4].iter().all(|&n| n == 0.0));
+    assert!(samples[5] != 0.0);
+    // Reverb tail
+    assert!(samples[500..].iter().any(|&n| n != 0.0));
+    assert!(samples.iter().all(|n| n.is_finite()));
+
+    Ok(())
+}
+
+#[test]
+fn render_downmix_test() -> Result<(), Box<dyn std::error::Error>> {
+    let oao_file = "render2.oao";
+    let wav_file = "render4.wav";
+    write_left_bubble(oao_file, 4)?;
+
+    // Linear pan law and limiter at -6 dB
+    let downmix = StereoDownmix {
+        pan_law: PanLaw::Linear,
+        height: 6.0,
+        limiter: Some(Limiter::new(-6.0, 0.1)),
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(File::open(oao_file)?);
+    let mut writer = BufWriter::new(File::create(wav_file)?);
+    let wav = downmix.downmix(&mut reader, &mut writer)?;
+    drop(writer);
+
+    let mut reader = BufReader::new(File::open(wav_file)?);
+    let read_wav: Wav = reader.read_details()?;
+    assert_eq!(read_wav, wav);
+    assert_eq!(read_wav.channels, 2);
+    let all = Wav {
+        channels: 1,
+        ..read_wav
+    };
+    let wav_blocks: WavBlocks = reader.read_blocks(&all)?;
+    let samples: Vec<f32> = wav_blocks.0.iter().map(|&b| b.into()).collect();
+    // The left quarter is 0.75 and 0.25, and height of the field is 1, so that isn't attenuated.
+    // Limiter lowers both channels by the same gain.
+    assert!((samples[0] - 0.5012).abs() < 1e-3);
+    assert!((samples[1] - 0.5012 / 3.0).abs() < 1e-3);
+
+    remove_file(oao_file)?;
+    remove_file(wav_file)?;
+
+    Ok(())
+}
\ No newline at end of file
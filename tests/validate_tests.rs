@@ -0,0 +1,148 @@
+use std::io::{BufReader, BufWriter, Cursor};
+use floaout::format::bub::{Bubble, BubbleBlock, BubbleBlocks};
+use floaout::format::oao::{BubbleInFloaout, BubblesInFloaout, Floaout, FloaoutBlock, FloaoutBlocks};
+use floaout::format::wav::WavBlock;
+use floaout::io::validate::{validate_bub, validate_oao, Problem};
+use floaout::io::write::{WriteBubsIn, WriteFmt};
+
+/// This function returns offsets of problems.
+fn offsets(problems: &[Problem]) -> Vec<u64> {
+    problems.iter().map(|problem| problem.offset).collect()
+}
+
+#[test]
+fn validate_bub_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 46 bytes of details, 2 bytes of overall and 3 blocks of 6 bytes
+    let bub = Bubble {
+        bub_field_size: (1u8, 0u8, 0u8).into(),
+        blocks: 3,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        name_size: 5,
+        name: "Piano".into(),
+        overall: vec![vec![vec![3]], vec![vec![2]]].into(),
+        ..Default::default()
+    };
+    let bub_blocks: Vec<BubbleBlock> = [(0.5f32, 1, 0), (-0.5, 0, 2), (0.25, 3, 0)].iter().map(|&(n, a, b)| {
+        BubbleBlock::from_wav_block_and_bub_field(WavBlock::from(n), vec![vec![vec![a]], vec![vec![b]]].into())
+    }).collect();
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_details(&bub)?;
+    writer.write_blocks(&bub, BubbleBlocks::from(bub_blocks.into_boxed_slice()))?;
+    let bytes = writer.into_inner()?;
+    assert_eq!(bytes.len(), 66);
+
+    assert_eq!(validate_bub(&mut BufReader::new(Cursor::new(bytes.clone())))?, vec![]);
+
+    // Wrong version, overall, NaN sample of block 1 and trailing garbage
+    let mut broken = bytes.clone();
+    broken[3] = 1;
+    broken[47] = 9;
+    broken[54..58].copy_from_slice(&f32::NAN.to_le_bytes());
+    broken.extend_from_slice(&[0, 0]);
+    let problems = validate_bub(&mut BufReader::new(Cursor::new(broken)))?;
+
+    assert_eq!(offsets(&problems), vec![3, 47, 54, 66]);
+    assert!(problems[2].message.contains("blocks 1..2"));
+    assert!(problems[3].message.contains("2 bytes"));
+
+    // Truncated in block 2
+    let problems = validate_bub(&mut BufReader::new(Cursor::new(bytes[..63].to_vec())))?;
+
+    assert_eq!(offsets(&problems), vec![60]);
+    assert!(problems[0].message.contains("3 blocks are expected but there are 2 blocks"));
+
+    // Name size which takes a byte of overall
+    let mut broken = bytes;
+    broken[40] = 6;
+    let problems = validate_bub(&mut BufReader::new(Cursor::new(broken)))?;
+
+    assert_eq!(offsets(&problems), vec![40]);
+    assert!(problems[0].message.starts_with("Name size 6 doesn't match name of 5 bytes"));
+
+    Ok(())
+}
+
+#[test]
+fn validate_oao_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 37 bytes of details, 2 Bubbles in Floaout of 29 and 24 bytes and 2 blocks of 2 Bubbles of 5 bytes and CRC-32C
+    let oao = Floaout {
+        bubbles: 2,
+        blocks: 2,
+        sampling_rate: 48000,
+        bits_per_sample: 32,
+        ..Default::default()
+    };
+    let bubs_in_oao = BubblesInFloaout::from(vec![
+        BubbleInFloaout {
+            bubble_id: 1,
+            name_size: 5,
+            name: "Drums".into(),
+            color: (255, 0, 0).into()
+        },
+        BubbleInFloaout::default()
+    ]);
+    let oao_blocks: Vec<FloaoutBlock> = (0..2).map(|i| {
+        FloaoutBlock::from(vec![BubbleBlock::from_wav_block_and_bub_field(WavBlock::from(i as f32), vec![vec![vec![1]]].into()); 2])
+    }).collect();
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    writer.write_blocks(&oao, FloaoutBlocks::from(oao_blocks.into_boxed_slice()))?;
+    let bytes = writer.into_inner()?;
+    assert_eq!(bytes.len(), 118);
+
+    assert_eq!(validate_oao(&mut BufReader::new(Cursor::new(bytes.clone())))?, vec![]);
+
+    // Wrong magic, infinite sample of Bubble 1 in block 1 and name which isn't UTF-8 break CRC-32C of details, Bubble 0 and block 1.
+    let mut broken = bytes.clone();
+    broken[0..3].copy_from_slice(b"bub");
+    broken[109..113].copy_from_slice(&f32::INFINITY.to_le_bytes());
+    broken[54] = 0xff;
+    let problems = validate_oao(&mut BufReader::new(Cursor::new(broken)))?;
+
+    assert_eq!(offsets(&problems), vec![0, 33, 54, 62, 104, 109]);
+    assert_eq!(problems[3].message, "CRC-32C of Bubble 0 doesn't match.");
+    assert_eq!(problems[4].message, "CRC-32C of blocks 1..2 doesn't match.");
+    assert!(problems[5].message.starts_with("Bubble 1: Samples of blocks 1..2"));
+
+    // Bubble field of block 0
+    let mut broken = bytes.clone();
+    broken[94] = 2;
+    let problems = validate_oao(&mut BufReader::new(Cursor::new(broken)))?;
+
+    assert_eq!(offsets(&problems), vec![90]);
+
+    // Bits per sample which can't locate blocks
+    let mut broken = bytes;
+    broken[29] = 16;
+    let problems = validate_oao(&mut BufReader::new(Cursor::new(broken)))?;
+
+    assert_eq!(offsets(&problems), vec![29, 33]);
+
+    // Title size and name size which don't match the byte length of title and name
+    let oao = Floaout {
+        title_size: 5,
+        title: "Song".into(),
+        ..oao
+    };
+    let bubs_in_oao = BubblesInFloaout::from(vec![
+        BubbleInFloaout {
+            name_size: 6,
+            ..bubs_in_oao.0[0].clone()
+        },
+        BubbleInFloaout::default()
+    ]);
+    let mut writer = BufWriter::new(Vec::new());
+    writer.write_details(&oao)?;
+    writer.write_bubs_details(&bubs_in_oao)?;
+    let bytes = writer.into_inner()?;
+    let problems = validate_oao(&mut BufReader::new(Cursor::new(bytes)))?;
+
+    assert_eq!(offsets(&problems), vec![31, 57, 94]);
+    assert_eq!(problems[0].message, "Title size 5 doesn't match title of 4 bytes.");
+    assert_eq!(problems[1].message, "Name size 6 doesn't match name of 5 bytes.");
+    assert!(problems[2].message.contains("2 blocks are expected but there are 0 blocks"));
+
+    Ok(())
+}
\ No newline at end of file
@@ -18,6 +18,7 @@ fn wav_details_test() -> Result<(), Box<dyn std::error::Error>> {
         data_rate: 176400,
         data_block_size: 4,
         bits_per_sample: 32,
+        channel_mask: 0,
         data_size: 0,
         other_size: 0
     };
@@ -32,6 +33,30 @@ fn wav_details_test() -> Result<(), Box<dyn std::error::Error>> {
 
     assert_eq!(read_wav, write_wav);
 
+    // ADPCM isn't integer PCM or IEEE float.
+    let mut writer = BufWriter::new(File::create(file)?);
+    writer.write_details(&Wav { format_tag: 2, ..write_wav })?;
+    drop(writer);
+    let mut reader = BufReader::new(File::open(file)?);
+    let read_wav: std::io::Result<Wav> = reader.read_details();
+    assert!(read_wav.is_err());
+
+    // Bits per sample, channels and data block size don't match.
+    for wav in [
+        Wav { format_tag: 1, bits_per_sample: 12, data_block_size: 2, ..write_wav },
+        Wav { bits_per_sample: 16, data_block_size: 2, ..write_wav },
+        Wav { channels: 0, data_block_size: 0, ..write_wav },
+        Wav { channels: 2, ..write_wav },
+        Wav { data_block_size: 0, ..write_wav }
+    ] {
+        let mut writer = BufWriter::new(File::create(file)?);
+        writer.write_details(&wav)?;
+        drop(writer);
+        let mut reader = BufReader::new(File::open(file)?);
+        let read_wav: std::io::Result<Wav> = reader.read_details();
+        assert_eq!(read_wav.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    }
+
     remove_file(file)?;
 
     Ok(())
@@ -51,6 +76,7 @@ fn wav_test() -> Result<(), Box<dyn std::error::Error>> {
         data_rate: 176400,
         data_block_size: 4,
         bits_per_sample: 32,
+        channel_mask: 0,
         data_size: 8,
         other_size: 0
     };